@@ -0,0 +1,251 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use egui::epaint::{CircleShape, TextShape};
+use egui::text::LayoutJob;
+use egui::{Align2, Color32, Pos2, Rect, TextFormat};
+use serde::{Deserialize, Serialize};
+
+use super::gizmo::GizmoComponent;
+use super::source::SourceType;
+use crate::math::constants::*;
+use crate::math::transformations::grid_to_image;
+use crate::math::window::{chebyshev, hann};
+use crate::render::gradient::Gradient;
+use crate::ui::state::ToolType;
+
+/// An array of sources along a line or an arc, driven with the same signal.
+///
+/// Each element is delayed and weighted so that the main lobe of the array
+/// is steered to `steering_angle`.
+#[derive(Debug, Component, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LineArray {
+    /// x position of the center of the array
+    pub x: u32,
+    /// y position of the center of the array
+    pub y: u32,
+    pub id: usize,
+    /// signal every element is driven with
+    pub source_type: SourceType,
+    /// number of elements
+    pub elements: u32,
+    /// distance between two neighboring elements (in m)
+    pub spacing: f32,
+    /// direction the array is facing (in °, counterclockwise starting to the right)
+    pub rotation: f32,
+    /// opening angle of the arc the elements are placed on (in °), 0 places them on a line
+    pub arc_angle: f32,
+    /// angle of the main lobe relative to the facing direction (in °)
+    pub steering_angle: f32,
+    /// weighting of the element gains
+    pub window: ArrayWindow,
+}
+
+/// The weighting of the element gains of a [`LineArray`]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub enum ArrayWindow {
+    #[default]
+    Uniform,
+    Hann,
+    Chebyshev {
+        /// attenuation of the side lobes (in dB)
+        sidelobe_level: f32,
+    },
+}
+
+impl fmt::Display for ArrayWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArrayWindow::Uniform => write!(f, "Uniform"),
+            ArrayWindow::Hann => write!(f, "Hann"),
+            ArrayWindow::Chebyshev { .. } => write!(f, "Chebyshev"),
+        }
+    }
+}
+
+/// A single element of a [`LineArray`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArrayElement {
+    pub x: u32,
+    pub y: u32,
+    /// delay of the element (in s)
+    pub delay: f32,
+    /// gain of the element
+    pub gain: f32,
+}
+
+impl LineArray {
+    pub fn new(x: u32, y: u32, id: usize) -> Self {
+        Self {
+            x,
+            y,
+            id,
+            source_type: SourceType::default(),
+            elements: 8,
+            spacing: 0.1,
+            rotation: 0.,
+            arc_angle: 0.,
+            steering_angle: 0.,
+            window: ArrayWindow::default(),
+        }
+    }
+
+    /// Calculates the grid positions (in cells) of all elements. Elements outside of the
+    /// simulation area are clamped to its edge.
+    /// * `delta_l` - The size of a cell (in m).
+    pub fn positions(&self, delta_l: f32) -> Vec<(u32, u32)> {
+        let rotation = self.rotation.to_radians();
+        // facing direction and direction of the array axis (y is pointing up)
+        let normal = Vec2::new(rotation.cos(), rotation.sin());
+        let axis = Vec2::new(-rotation.sin(), rotation.cos());
+
+        let arc_angle = self.arc_angle.to_radians();
+        let length = (self.elements.max(1) - 1) as f32 * self.spacing;
+
+        self.axis_coordinates()
+            .map(|u| {
+                let position = if arc_angle > 0. && length > 0. {
+                    // the elements bend back from the facing direction
+                    let radius = length / arc_angle;
+                    let angle = u / radius;
+                    radius * angle.sin() * axis + radius * (angle.cos() - 1.) * normal
+                } else {
+                    u * axis
+                };
+
+                (
+                    (self.x as f32 + position.x / delta_l)
+                        .round()
+                        .clamp(0., SIMULATION_WIDTH as f32 - 1.) as u32,
+                    (self.y as f32 - position.y / delta_l)
+                        .round()
+                        .clamp(0., SIMULATION_HEIGHT as f32 - 1.) as u32,
+                )
+            })
+            .collect()
+    }
+
+    /// Calculates the position, delay and gain of all elements. The delays only depend on the
+    /// position along the array axis, so an arc keeps its curvature when it is steered.
+    /// * `delta_l` - The size of a cell (in m).
+    pub fn calc_elements(&self, delta_l: f32) -> Vec<ArrayElement> {
+        let steering = self.steering_angle.to_radians().sin();
+        let coordinates = self.axis_coordinates().collect::<Vec<_>>();
+        let first_delay = coordinates
+            .iter()
+            .map(|u| u * steering)
+            .fold(f32::INFINITY, f32::min);
+
+        let gains = match self.window {
+            ArrayWindow::Uniform => vec![1.; self.elements as usize],
+            ArrayWindow::Hann => hann(self.elements as usize),
+            ArrayWindow::Chebyshev { sidelobe_level } => {
+                chebyshev(self.elements as usize, sidelobe_level)
+            }
+        };
+
+        self.positions(delta_l)
+            .into_iter()
+            .zip(coordinates)
+            .zip(gains)
+            .map(|(((x, y), u), gain)| ArrayElement {
+                x,
+                y,
+                delay: (u * steering - first_delay) / SPEED_OF_SOUND,
+                gain,
+            })
+            .collect()
+    }
+
+    /// Seed of the white noise of the array, distinct from the seeds of sources
+    pub fn noise_seed(&self) -> u64 {
+        1 << 24 | self.id as u64
+    }
+
+    /// Delay between two neighboring elements (in s)
+    pub fn element_delay(&self) -> f32 {
+        self.spacing * self.steering_angle.to_radians().sin() / SPEED_OF_SOUND
+    }
+
+    /// Coordinates of the elements along the array axis (in m), centered around 0
+    fn axis_coordinates(&self) -> impl Iterator<Item = f32> + '_ {
+        let center = (self.elements.max(1) - 1) as f32 / 2.;
+        (0..self.elements).map(move |index| (index as f32 - center) * self.spacing)
+    }
+}
+
+impl GizmoComponent for LineArray {
+    fn get_gizmo_positions(&self, _tool_type: &ToolType) -> Vec<Pos2> {
+        vec![Pos2 {
+            x: self.x as f32,
+            y: self.y as f32,
+        }]
+    }
+
+    fn draw_gizmo(
+        &self,
+        painter: &egui::Painter,
+        tool_type: &ToolType,
+        highlight: bool,
+        image_rect: &Rect,
+        text: Option<&str>,
+        delta_l: f32,
+        current_gradient: Gradient,
+    ) {
+        let (gizmo_color, text_color) = match current_gradient {
+            Gradient::Turbo => (Color32::from_rgb(1, 89, 88), Color32::WHITE),
+            _ => (Color32::from_rgb(15, 194, 192), Color32::BLACK),
+        };
+
+        for (x, y) in self.positions(delta_l) {
+            painter.add(egui::Shape::Circle(CircleShape::filled(
+                grid_to_image(
+                    Pos2 {
+                        x: x as f32,
+                        y: y as f32,
+                    },
+                    image_rect,
+                ),
+                if highlight { 4. } else { 3. },
+                gizmo_color,
+            )));
+        }
+
+        for pos in self.get_gizmo_positions(tool_type) {
+            // direction of the main lobe
+            let direction = (self.rotation + self.steering_angle).to_radians();
+            let tip = Pos2 {
+                x: pos.x + 30. * direction.cos(),
+                y: pos.y - 30. * direction.sin(),
+            };
+            painter.line_segment(
+                [
+                    grid_to_image(pos, image_rect),
+                    grid_to_image(tip, image_rect),
+                ],
+                (3., gizmo_color),
+            );
+            painter.add(egui::Shape::Circle(CircleShape::stroke(
+                grid_to_image(pos, image_rect),
+                if highlight { 15. } else { 10. },
+                (3., gizmo_color),
+            )));
+            if let Some(text) = text {
+                let galley = {
+                    let layout_job = LayoutJob::single_section(
+                        text.to_owned(),
+                        TextFormat {
+                            color: text_color,
+                            background: Color32::TRANSPARENT,
+                            ..Default::default()
+                        },
+                    );
+                    painter.layout_job(layout_job)
+                };
+                let rect = Align2::CENTER_CENTER
+                    .anchor_size(grid_to_image(pos, image_rect), galley.size());
+                painter.add(TextShape::new(rect.min, galley, Color32::BLACK));
+            }
+        }
+    }
+}
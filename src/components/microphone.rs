@@ -5,17 +5,19 @@ use egui::{Align2, Color32, Pos2, Rect, TextFormat};
 use serde::{Deserialize, Serialize};
 
 use super::gizmo::GizmoComponent;
+use crate::math::calibration::pressure_to_db_spl;
 use crate::math::transformations::grid_to_image;
 use crate::render::gradient::Gradient;
 use crate::simulation::plugin::ComponentIDs;
 use crate::ui::state::ToolType;
 
-/// A microphone on the grid that records the pressure at its position
+/// A microphone on the grid that records the sound pressure (in Pa) at its position
 #[derive(Debug, Default, Component, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Microphone {
     pub x: u32,
     pub y: u32,
     pub id: usize,
+    /// recorded (time in s, sound pressure in Pa) pairs
     #[serde(skip_serializing, skip_deserializing)]
     pub record: Vec<[f64; 2]>,
     pub show_fft: bool,
@@ -45,13 +47,20 @@ impl Microphone {
         self.record = vec![];
     }
 
-    pub fn write_to_file(&mut self, path: &str) {
-        let mut wtr = csv::Writer::from_path(path).unwrap();
-        for record in &self.record {
-            wtr.write_record(&[record[0].to_string(), record[1].to_string()])
-                .unwrap();
-        }
-        wtr.flush().unwrap();
+    /// Calculates the sound pressure level (in dB SPL) of the last `duration` seconds of the record.
+    /// Returns `None` if nothing was recorded yet.
+    pub fn level(&self, duration: f64) -> Option<f32> {
+        let end_time = self.record.last()?[0];
+        let (sum, count) = self
+            .record
+            .iter()
+            .rev()
+            .take_while(|record| end_time - record[0] < duration)
+            .fold((0., 0), |(sum, count), record| {
+                (sum + record[1].powi(2), count + 1)
+            });
+
+        Some(pressure_to_db_spl((sum / count as f64).sqrt() as f32))
     }
 }
 
@@ -1,5 +1,9 @@
 pub mod gizmo;
+pub mod line_array;
 pub mod microphone;
+pub mod plane_wave;
 pub mod source;
+pub mod source_group;
 pub mod states;
+pub mod vibrating_surface;
 pub mod wall;
@@ -0,0 +1,183 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use bevy::prelude::*;
+use egui::epaint::{CircleShape, TextShape};
+use egui::text::LayoutJob;
+use egui::{Align2, Color32, Pos2, Rect, TextFormat};
+use serde::{Deserialize, Serialize};
+
+use super::gizmo::GizmoComponent;
+use super::line_array::ArrayElement;
+use super::source::SourceType;
+use crate::math::constants::*;
+use crate::math::transformations::grid_to_image;
+use crate::render::gradient::Gradient;
+use crate::ui::state::ToolType;
+
+/// A plane wave that travels across the whole simulation area.
+///
+/// The wave is excited along the edges of the simulation area it enters through. Each edge cell
+/// is delayed by the travel time of the wavefront, so the excitation adds up to a flat wavefront
+/// inside the simulation area, while the wave radiated outwards is absorbed by the boundary.
+#[derive(Debug, Component, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PlaneWave {
+    pub id: usize,
+    /// signal of the wave (in Pa)
+    pub source_type: SourceType,
+    /// direction the wave is traveling in (in °, counterclockwise starting to the right)
+    pub angle: f32,
+}
+
+impl PlaneWave {
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            source_type: SourceType::default(),
+            angle: 0.,
+        }
+    }
+
+    /// Calculates the position, delay and gain of all excited cells on the edges of the
+    /// simulation area.
+    ///
+    /// The gain of an edge is the cosine of the angle between the wave and the edge normal,
+    /// so an oblique wave has the same amplitude as a perpendicular one. Injecting the gain into
+    /// all four ports of a cell results in a wave with an amplitude of √2, hence the factor 1/√2.
+    /// * `delta_l` - The size of a cell (in m).
+    pub fn calc_elements(&self, delta_l: f32) -> Vec<ArrayElement> {
+        let (direction_x, direction_y) = self.direction();
+        // the corner the wave enters through is reached first
+        let first = (direction_x * (SIMULATION_WIDTH - 1) as f32).min(0.)
+            + (direction_y * (SIMULATION_HEIGHT - 1) as f32).min(0.);
+        let element = |x: u32, y: u32, gain: f32| ArrayElement {
+            x,
+            y,
+            delay: ((x as f32 * direction_x + y as f32 * direction_y) - first) * delta_l
+                / SPEED_OF_SOUND,
+            gain: gain * FRAC_1_SQRT_2,
+        };
+
+        let mut elements = Vec::new();
+        if direction_x.abs() > f32::EPSILON {
+            let x = if direction_x > 0. {
+                0
+            } else {
+                SIMULATION_WIDTH - 1
+            };
+            elements.extend((0..SIMULATION_HEIGHT).map(|y| element(x, y, direction_x.abs())));
+        }
+        if direction_y.abs() > f32::EPSILON {
+            let y = if direction_y > 0. {
+                0
+            } else {
+                SIMULATION_HEIGHT - 1
+            };
+            elements.extend((0..SIMULATION_WIDTH).map(|x| element(x, y, direction_y.abs())));
+        }
+        elements
+    }
+
+    /// Returns whether a position (in cells) is on the gizmo of the wave, in the center of the
+    /// simulation area.
+    pub fn contains_pointer(&self, x: u32, y: u32) -> bool {
+        x.abs_diff(SIMULATION_WIDTH / 2) <= 10 && y.abs_diff(SIMULATION_HEIGHT / 2) <= 10
+    }
+
+    /// Turns the wave to travel from the center of the simulation area towards a position
+    /// (in cells). Positions on the gizmo are ignored, their direction is not well defined.
+    pub fn point_to(&mut self, x: u32, y: u32) {
+        if self.contains_pointer(x, y) {
+            return;
+        }
+        let offset_x = x as f32 - SIMULATION_WIDTH as f32 / 2.;
+        let offset_y = SIMULATION_HEIGHT as f32 / 2. - y as f32;
+        self.angle = offset_y.atan2(offset_x).to_degrees().rem_euclid(360.);
+    }
+
+    /// Seed of the white noise of the wave, distinct from the seeds of sources and line arrays
+    pub fn noise_seed(&self) -> u64 {
+        2 << 24 | self.id as u64
+    }
+
+    /// Direction of the wave in grid coordinates (y is pointing down)
+    fn direction(&self) -> (f32, f32) {
+        let angle = self.angle.to_radians();
+        (angle.cos(), -angle.sin())
+    }
+}
+
+impl GizmoComponent for PlaneWave {
+    fn get_gizmo_positions(&self, _tool_type: &ToolType) -> Vec<Pos2> {
+        vec![Pos2 {
+            x: SIMULATION_WIDTH as f32 / 2.,
+            y: SIMULATION_HEIGHT as f32 / 2.,
+        }]
+    }
+
+    fn draw_gizmo(
+        &self,
+        painter: &egui::Painter,
+        tool_type: &ToolType,
+        highlight: bool,
+        image_rect: &Rect,
+        text: Option<&str>,
+        _delta_l: f32,
+        current_gradient: Gradient,
+    ) {
+        let (gizmo_color, text_color) = match current_gradient {
+            Gradient::Turbo => (Color32::from_rgb(1, 89, 88), Color32::WHITE),
+            _ => (Color32::from_rgb(15, 194, 192), Color32::BLACK),
+        };
+        let (direction_x, direction_y) = self.direction();
+
+        for pos in self.get_gizmo_positions(tool_type) {
+            // wavefront and direction of the wave
+            let front = if highlight { 60. } else { 40. };
+            painter.line_segment(
+                [
+                    grid_to_image(
+                        Pos2 {
+                            x: pos.x - front * direction_y,
+                            y: pos.y + front * direction_x,
+                        },
+                        image_rect,
+                    ),
+                    grid_to_image(
+                        Pos2 {
+                            x: pos.x + front * direction_y,
+                            y: pos.y - front * direction_x,
+                        },
+                        image_rect,
+                    ),
+                ],
+                (3., gizmo_color),
+            );
+            painter.arrow(
+                grid_to_image(pos, image_rect),
+                egui::Vec2::new(direction_x, direction_y) * 40.,
+                (3., gizmo_color),
+            );
+            painter.add(egui::Shape::Circle(CircleShape::filled(
+                grid_to_image(pos, image_rect),
+                if highlight { 15. } else { 10. },
+                gizmo_color,
+            )));
+            if let Some(text) = text {
+                let galley = {
+                    let layout_job = LayoutJob::single_section(
+                        text.to_owned(),
+                        TextFormat {
+                            color: text_color,
+                            background: Color32::TRANSPARENT,
+                            ..Default::default()
+                        },
+                    );
+                    painter.layout_job(layout_job)
+                };
+                let rect = Align2::CENTER_CENTER
+                    .anchor_size(grid_to_image(pos, image_rect), galley.size());
+                painter.add(TextShape::new(rect.min, galley, Color32::BLACK));
+            }
+        }
+    }
+}
@@ -5,34 +5,182 @@ use bevy::prelude::*;
 use egui::epaint::{CircleShape, TextShape};
 use egui::text::LayoutJob;
 use egui::{Align2, Color32, Pos2, Rect, TextFormat};
-use rand::{thread_rng, Rng};
 use serde::{Deserialize, Serialize};
 
 use super::gizmo::GizmoComponent;
+use crate::math::calibration::source_gain;
 use crate::math::constants::*;
+use crate::math::expression::Expression;
+use crate::math::noise::white_noise;
 use crate::math::transformations::grid_to_image;
 use crate::render::gradient::Gradient;
-use crate::simulation::plugin::{ComponentIDs, WaveSamples};
+use crate::simulation::plugin::{interpolate, ComponentIDs, SampleTables, WaveSamples};
 use crate::ui::state::ToolType;
 
 /// A sound source on the grid
-#[derive(Debug, Default, Component, Serialize, Deserialize, Clone, PartialEq, Copy)]
+#[derive(Debug, Default, Component, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Source {
     pub x: u32,
     pub y: u32,
     /// type of the source
     pub source_type: SourceType,
     pub id: usize,
+    /// radiation pattern of the source
+    #[serde(default)]
+    pub directivity: Directivity,
+    /// direction the pattern is pointing to (in °, counterclockwise starting to the right)
+    #[serde(default)]
+    pub orientation: f32,
+    /// processing steps the signal passes through before it is injected, in order
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+    /// whether the source takes part in the simulation
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub muted: bool,
+    /// if any enabled source is soloed, only the soloed sources are audible
+    #[serde(default)]
+    pub solo: bool,
+    /// id of the [`SourceGroup`](super::source_group::SourceGroup) whose signal is played instead
+    /// of the own signal
+    #[serde(default)]
+    pub group: Option<usize>,
+    /// phase shift of a periodic group signal (in °)
+    #[serde(default)]
+    pub phase_offset: f32,
 }
 
+fn default_enabled() -> bool {
+    true
+}
+
+/// The radiation pattern of a source.
+///
+/// All patterns are first order patterns `monopole + dipole * cos(θ - orientation)`. They are
+/// created by weighting the values that are injected into the four ports of the source cell.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub enum Directivity {
+    #[default]
+    Omnidirectional,
+    Dipole,
+    Cardioid,
+    /// user defined first order pattern
+    Multipole {
+        /// weight of the omnidirectional part
+        monopole: f32,
+        /// weight of the figure of eight part
+        dipole: f32,
+    },
+}
+
+impl Directivity {
+    pub fn default_multipole() -> Directivity {
+        Directivity::Multipole {
+            monopole: 0.37,
+            dipole: 0.63,
+        }
+    }
+
+    /// Returns the monopole and dipole weight of the pattern.
+    pub fn weights(&self) -> (f32, f32) {
+        match *self {
+            Directivity::Omnidirectional => (1., 0.),
+            Directivity::Dipole => (0., 1.),
+            Directivity::Cardioid => (0.5, 0.5),
+            Directivity::Multipole { monopole, dipole } => (monopole, dipole),
+        }
+    }
+
+    /// Calculates the factors the source value is injected with into the
+    /// bottom, left, top and right port of the source cell.
+    ///
+    /// A dipole injection radiates with `1 / sqrt(2)` of the amplitude of a monopole injection,
+    /// which is compensated here so that the pattern keeps the calibration of the source.
+    /// * `orientation` - The direction the pattern is pointing to (in °).
+    pub fn port_factors(&self, orientation: f32) -> [f32; 4] {
+        let (monopole, dipole) = self.weights();
+        let orientation = orientation.to_radians();
+        // directions of the bottom, left, top and right port (y is pointing down in the grid)
+        [270_f32, 180., 90., 0.].map(|port_angle| {
+            monopole
+                + std::f32::consts::SQRT_2 * dipole * (port_angle.to_radians() - orientation).cos()
+        })
+    }
+}
+
+impl fmt::Display for Directivity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Directivity::Omnidirectional => write!(f, "Omnidirectional"),
+            Directivity::Dipole => write!(f, "Dipole"),
+            Directivity::Cardioid => write!(f, "Cardioid"),
+            Directivity::Multipole { .. } => write!(f, "Multipole"),
+        }
+    }
+}
+
+/// A processing step in the signal chain of a [`Source`]
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub enum Filter {
+    /// second order low-pass with the cutoff `frequency` (in Hz)
+    LowPass { frequency: f32, q: f32 },
+    /// second order high-pass with the cutoff `frequency` (in Hz)
+    HighPass { frequency: f32, q: f32 },
+    /// second order band-pass with the center `frequency` (in Hz)
+    BandPass { frequency: f32, q: f32 },
+    /// gain (in dB)
+    Gain { gain: f32 },
+    /// delay (in s)
+    Delay { delay: f32 },
+    /// inverts the signal
+    Polarity,
+}
+
+impl Filter {
+    /// Returns one filter of every kind with default settings.
+    pub fn defaults() -> [Filter; 6] {
+        [
+            Filter::LowPass {
+                frequency: 1000.,
+                q: std::f32::consts::FRAC_1_SQRT_2,
+            },
+            Filter::HighPass {
+                frequency: 1000.,
+                q: std::f32::consts::FRAC_1_SQRT_2,
+            },
+            Filter::BandPass {
+                frequency: 1000.,
+                q: 1.,
+            },
+            Filter::Gain { gain: 0. },
+            Filter::Delay { delay: 0.001 },
+            Filter::Polarity,
+        ]
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::LowPass { .. } => write!(f, "Low-pass"),
+            Filter::HighPass { .. } => write!(f, "High-pass"),
+            Filter::BandPass { .. } => write!(f, "Band-pass"),
+            Filter::Gain { .. } => write!(f, "Gain"),
+            Filter::Delay { .. } => write!(f, "Delay"),
+            Filter::Polarity => write!(f, "Polarity"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub enum SourceType {
     Sin {
         /// phase shift of the sin (in °)
         phase: f32,
         /// frequency of the sin (in Hz)
         frequency: f32,
-        /// amplitude of the sin (currently unitless)
+        /// amplitude of the sin (peak sound pressure at 1 m in Pa)
         amplitude: f32,
     },
     PeriodicGauss {
@@ -40,28 +188,117 @@ pub enum SourceType {
         phase: f32,
         /// frequency of the bell (in Hz)
         frequency: f32,
-        /// amplitude of the bell (currently unitless)
+        /// amplitude of the bell (peak sound pressure at 1 m in Pa)
         amplitude: f32,
         std_dev: f32,
     },
     GaussImpulse {
-        /// amplitude of the bell (currently unitless)
+        /// amplitude of the bell (in Pa, calibrated at [`CALIBRATION_FREQUENCY`])
         amplitude: f32,
         std_dev: f32,
     },
     WhiteNoise {
-        /// amplitude of the noise (currently unitless)
+        /// amplitude of the noise (RMS sound pressure at 1 m in Pa, calibrated at [`CALIBRATION_FREQUENCY`])
         amplitude: f32,
     },
     WaveFile {
+        /// path of the wave file, the samples are kept in [`WaveSamples`] (empty if no file is
+        /// loaded)
+        #[serde(default)]
+        path: String,
+        /// channel of the file that is played back
+        #[serde(default)]
+        channel: WaveChannel,
+        /// amplitude the samples of the file are scaled with (sound pressure of a full scale
+        /// sample at 1 m in Pa, calibrated at [`CALIBRATION_FREQUENCY`])
+        amplitude: f32,
+        /// whether the file is looped or only played once
+        #[serde(default)]
+        playback: Playback,
+        /// time after the simulation start at which the playback begins (in s)
+        #[serde(default)]
+        start_offset: f32,
+    },
+    Csv {
+        /// path of the CSV file with (time, value) pairs, the values are kept in [`SampleTables`]
+        path: String,
+        /// amplitude the values of the file are scaled with (sound pressure of a value of 1
+        /// at 1 m in Pa, calibrated at [`CALIBRATION_FREQUENCY`])
+        amplitude: f32,
+    },
+    Expression {
+        /// user defined signal in the time `t` (in s)
+        expression: Expression,
+        /// amplitude the expression is scaled with (sound pressure of a value of 1
+        /// at 1 m in Pa, calibrated at [`CALIBRATION_FREQUENCY`])
+        amplitude: f32,
+    },
+    /// A time-reversed microphone recording. The samples are stored with the source, so they are
+    /// part of saved scenes and of the undo history.
+    Recording {
+        /// id of the [`Microphone`](crate::components::microphone::Microphone) the recording was
+        /// taken with
+        mic_id: usize,
+        /// (time in s, sound pressure in Pa) pairs sorted by time
+        points: Vec<[f32; 2]>,
+        /// factor the recorded sound pressure is scaled with (sound pressure at 1 m per recorded
+        /// sound pressure, calibrated at [`CALIBRATION_FREQUENCY`])
         amplitude: f32,
     },
+    /// The live sound pressure at a microphone, the recorded pressure is kept in
+    /// [`MicrophoneSignals`](crate::simulation::plugin::MicrophoneSignals) and routed into the
+    /// source by the grid.
+    Microphone {
+        /// id of the [`Microphone`](crate::components::microphone::Microphone) the signal is taken from
+        mic_id: usize,
+        /// delay of the signal (in s)
+        delay: f32,
+        /// factor the sound pressure at the microphone is scaled with (sound pressure at 1 m
+        /// per sound pressure at the microphone, calibrated at [`CALIBRATION_FREQUENCY`])
+        gain: f32,
+    },
+}
+
+/// How a wave file is played back by a source
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub enum Playback {
+    #[default]
+    Loop,
+    OneShot,
+}
+
+/// Which channel of a wave file is played back by a source
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub enum WaveChannel {
+    /// mean of all channels
+    #[default]
+    Downmix,
+    /// a single channel (starting at 0)
+    Channel(usize),
+}
+
+impl fmt::Display for WaveChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaveChannel::Downmix => write!(f, "Downmix"),
+            WaveChannel::Channel(channel) => write!(f, "Channel {}", channel + 1),
+        }
+    }
+}
+
+impl fmt::Display for Playback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Playback::Loop => write!(f, "Loop"),
+            Playback::OneShot => write!(f, "One-shot"),
+        }
+    }
 }
 
 impl Default for SourceType {
     fn default() -> Self {
         SourceType::Sin {
-            amplitude: 10.,
+            amplitude: 0.5,
             phase: 0.0,
             frequency: 1000.0,
         }
@@ -71,14 +308,14 @@ impl Default for SourceType {
 impl SourceType {
     pub fn default_sin() -> SourceType {
         SourceType::Sin {
-            amplitude: 10.,
+            amplitude: 0.5,
             phase: 0.0,
             frequency: 1000.0,
         }
     }
     pub fn default_periodic_gauss() -> SourceType {
         SourceType::PeriodicGauss {
-            amplitude: 10.,
+            amplitude: 0.5,
             phase: 0.0,
             frequency: 1000.0,
             std_dev: 0.45,
@@ -86,42 +323,126 @@ impl SourceType {
     }
     pub fn default_gauss_impulse() -> SourceType {
         SourceType::GaussImpulse {
-            amplitude: 1.,
+            amplitude: 0.02,
             std_dev: 0.001,
         }
     }
     pub fn default_noise() -> SourceType {
-        SourceType::WhiteNoise { amplitude: 10. }
+        SourceType::WhiteNoise { amplitude: 0.5 }
+    }
+    pub fn default_csv() -> SourceType {
+        SourceType::Csv {
+            path: String::new(),
+            amplitude: 0.5,
+        }
+    }
+    pub fn default_expression() -> SourceType {
+        SourceType::Expression {
+            expression: Expression::new("sin(2*pi*440*t) * exp(-t/0.01)"),
+            amplitude: 0.5,
+        }
+    }
+    pub fn default_microphone(mic_id: usize) -> SourceType {
+        SourceType::Microphone {
+            mic_id,
+            delay: 0.001,
+            gain: 1.,
+        }
     }
     pub fn default_wave() -> SourceType {
-        SourceType::WaveFile { amplitude: 100. } // bro wtf why 100
+        SourceType::WaveFile {
+            path: String::new(),
+            channel: WaveChannel::Downmix,
+            amplitude: 1.,
+            playback: Playback::Loop,
+            start_offset: 0.,
+        }
     }
-}
 
-impl fmt::Display for SourceType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    /// Returns the frequency at which the amplitude of the source is calibrated (in Hz).
+    ///
+    /// Periodic sources are calibrated at their frequency, all others at [`CALIBRATION_FREQUENCY`].
+    pub fn calibration_frequency(&self) -> f32 {
         match self {
-            SourceType::Sin { .. } => write!(f, "Sinusoidal"),
-            SourceType::PeriodicGauss { .. } => write!(f, "Periodic Gaussian"),
-            SourceType::GaussImpulse { .. } => write!(f, "Gaussian Impulse"),
-            SourceType::WhiteNoise { .. } => write!(f, "White noise"),
-            SourceType::WaveFile { .. } => write!(f, "Wave file"),
+            SourceType::Sin { frequency, .. } | SourceType::PeriodicGauss { frequency, .. } => {
+                *frequency
+            }
+            _ => CALIBRATION_FREQUENCY,
         }
     }
-}
 
-impl Source {
-    pub fn new(x: u32, y: u32, source_type: SourceType, id: usize) -> Self {
-        Self {
-            x,
-            y,
-            source_type,
-            id,
+    /// Returns whether the signal is not calibrated at its own frequency, so its amplitude is
+    /// only exact at [`CALIBRATION_FREQUENCY`].
+    pub fn is_broadband(&self) -> bool {
+        !matches!(
+            self,
+            SourceType::Sin { .. } | SourceType::PeriodicGauss { .. }
+        )
+    }
+
+    /// Scales the amplitude (or gain) of the signal by the given factor.
+    pub fn scale_amplitude(&mut self, factor: f32) {
+        match self {
+            SourceType::Sin { amplitude, .. }
+            | SourceType::PeriodicGauss { amplitude, .. }
+            | SourceType::GaussImpulse { amplitude, .. }
+            | SourceType::WhiteNoise { amplitude }
+            | SourceType::WaveFile { amplitude, .. }
+            | SourceType::Csv { amplitude, .. }
+            | SourceType::Expression { amplitude, .. }
+            | SourceType::Recording { amplitude, .. } => *amplitude *= factor,
+            SourceType::Microphone { gain, .. } => *gain *= factor,
         }
     }
 
-    pub fn calc(&self, time: f32, cur_sample: usize, wave_samples: &WaveSamples) -> f32 {
-        match self.source_type {
+    /// Returns the delay (in s) that shifts a periodic signal by `phase` (in °).
+    /// Signals that are not periodic are not shifted.
+    pub fn phase_delay(&self, phase: f32) -> f32 {
+        match self {
+            SourceType::Sin { frequency, .. } | SourceType::PeriodicGauss { frequency, .. } => {
+                phase / (360. * frequency)
+            }
+            _ => 0.,
+        }
+    }
+
+    /// Returns the factor that converts the amplitude of the source to its RMS sound pressure.
+    pub fn rms_factor(&self) -> f32 {
+        match self {
+            SourceType::WhiteNoise { .. } => 1.,
+            _ => std::f32::consts::FRAC_1_SQRT_2,
+        }
+    }
+
+    /// Calculates the value that is injected into the grid at the given time.
+    ///
+    /// The signal (in Pa at 1 m) is scaled with the [`source_gain`] of the grid.
+    pub fn calc(
+        &self,
+        time: f32,
+        cur_sample: usize,
+        noise_seed: u64,
+        delta_t: f32,
+        wave_samples: &WaveSamples,
+        sample_tables: &SampleTables,
+    ) -> f32 {
+        source_gain(self.calibration_frequency(), delta_t)
+            * self.signal(time, cur_sample, noise_seed, wave_samples, sample_tables)
+    }
+
+    /// Calculates the value of the signal at the given time.
+    ///
+    /// White noise is generated from `noise_seed` and `cur_sample`, so delayed copies of the
+    /// signal (e.g. in line arrays) are the same noise.
+    pub fn signal(
+        &self,
+        time: f32,
+        cur_sample: usize,
+        noise_seed: u64,
+        wave_samples: &WaveSamples,
+        sample_tables: &SampleTables,
+    ) -> f32 {
+        match *self {
             SourceType::Sin {
                 phase,
                 frequency,
@@ -136,13 +457,71 @@ impl Source {
             SourceType::GaussImpulse { amplitude, std_dev } => {
                 self.gaussian_impulse(time, amplitude, 0.001, std_dev)
             }
-            SourceType::WhiteNoise { amplitude } => {
-                thread_rng().sample::<f32, _>(rand_distr::StandardNormal) * amplitude
-            }
-            SourceType::WaveFile { amplitude } => {
-                wave_samples.0[cur_sample % wave_samples.0.len()] * amplitude
-            }
+            SourceType::WhiteNoise { amplitude } => white_noise(noise_seed, cur_sample) * amplitude,
+            SourceType::WaveFile {
+                ref path,
+                channel,
+                amplitude,
+                playback,
+                start_offset,
+            } => self.wave_file(
+                cur_sample,
+                wave_samples,
+                path,
+                channel,
+                amplitude,
+                playback,
+                start_offset,
+            ),
+            SourceType::Csv {
+                ref path,
+                amplitude,
+            } => sample_tables.0.get(path).map_or(0., |sample_table| {
+                sample_table.sample(cur_sample) * amplitude
+            }),
+            SourceType::Expression {
+                ref expression,
+                amplitude,
+            } => expression.eval(time) * amplitude,
+            SourceType::Recording {
+                ref points,
+                amplitude,
+                ..
+            } => interpolate(points, time) * amplitude,
+            // routed by the grid in `Grid::apply_sources`
+            SourceType::Microphone { .. } => 0.,
+        }
+    }
+
+    fn wave_file(
+        &self,
+        cur_sample: usize,
+        wave_samples: &WaveSamples,
+        path: &str,
+        channel: WaveChannel,
+        amplitude: f32,
+        playback: Playback,
+        start_offset: f32,
+    ) -> f32 {
+        let Some(wave_data) = wave_samples.0.get(path) else {
+            return 0.;
+        };
+        if wave_data.is_empty() {
+            return 0.;
         }
+
+        let offset_samples = (start_offset * wave_data.sample_rate).round() as usize;
+        if cur_sample < offset_samples {
+            return 0.;
+        }
+
+        let index = cur_sample - offset_samples;
+        let sample = match playback {
+            Playback::Loop => wave_data.sample(index % wave_data.len(), channel),
+            Playback::OneShot => wave_data.sample(index, channel),
+        };
+
+        sample * amplitude
     }
 
     fn sin(&self, time: f32, phase: f32, frequency: f32, amplitude: f32) -> f32 {
@@ -184,6 +563,52 @@ impl Source {
 
         amplitude * scaling_factor * exp_term
     }
+}
+
+impl fmt::Display for SourceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceType::Sin { .. } => write!(f, "Sinusoidal"),
+            SourceType::PeriodicGauss { .. } => write!(f, "Periodic Gaussian"),
+            SourceType::GaussImpulse { .. } => write!(f, "Gaussian Impulse"),
+            SourceType::WhiteNoise { .. } => write!(f, "White noise"),
+            SourceType::WaveFile { .. } => write!(f, "Wave file"),
+            SourceType::Csv { .. } => write!(f, "CSV file"),
+            SourceType::Expression { .. } => write!(f, "Expression"),
+            SourceType::Recording { .. } => write!(f, "Time-reversed recording"),
+            SourceType::Microphone { .. } => write!(f, "Microphone"),
+        }
+    }
+}
+
+impl Source {
+    pub fn new(x: u32, y: u32, source_type: SourceType, id: usize) -> Self {
+        Self {
+            x,
+            y,
+            source_type,
+            id,
+            directivity: Directivity::default(),
+            orientation: 0.,
+            filters: Vec::new(),
+            enabled: true,
+            muted: false,
+            solo: false,
+            group: None,
+            phase_offset: 0.,
+        }
+    }
+
+    /// Returns whether the source is injected into the grid.
+    /// * `any_solo` - Whether any enabled source is soloed.
+    pub fn is_audible(&self, any_solo: bool) -> bool {
+        self.enabled && !self.muted && (self.solo || !any_solo)
+    }
+
+    /// Seed of the white noise of the source
+    pub fn noise_seed(&self) -> u64 {
+        self.id as u64
+    }
 
     pub fn spawn_initial_sources(mut commands: Commands, mut component_ids: ResMut<ComponentIDs>) {
         commands.spawn(Source::new(
@@ -225,6 +650,20 @@ impl GizmoComponent for Source {
         };
 
         for pos in self.get_gizmo_positions(tool_type) {
+            if self.directivity != Directivity::Omnidirectional {
+                let orientation = self.orientation.to_radians();
+                let tip = Pos2 {
+                    x: pos.x + 20. * orientation.cos(),
+                    y: pos.y - 20. * orientation.sin(),
+                };
+                painter.line_segment(
+                    [
+                        grid_to_image(pos, image_rect),
+                        grid_to_image(tip, image_rect),
+                    ],
+                    (3., gizmo_color),
+                );
+            }
             painter.add(egui::Shape::Circle(CircleShape::filled(
                 grid_to_image(pos, image_rect),
                 if highlight { 15. } else { 10. },
@@ -257,3 +696,136 @@ impl GizmoComponent for Source {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use super::*;
+    use crate::simulation::grid::testing::{
+        coarse_grid, inject, peak, settled_pressure, ONE_METER,
+    };
+
+    /// Returns the peak sound pressure at a distance of 1 m of a harmonic source with the given
+    /// pattern pointing to the right, at 16 evenly spaced angles starting on axis.
+    fn peaks_at_one_meter(directivity: Directivity) -> Vec<f32> {
+        let frequency = 1000.;
+        let mut grid = coarse_grid();
+        let gain = source_gain(frequency, grid.delta_t);
+
+        let center = SIMULATION_WIDTH / 2;
+        let positions = (0..16)
+            .map(|index| {
+                let angle = TAU * index as f32 / 16.;
+                (
+                    (center as f32 + ONE_METER as f32 * angle.cos()).round() as u32,
+                    (center as f32 - ONE_METER as f32 * angle.sin()).round() as u32,
+                )
+            })
+            .collect::<Vec<_>>();
+        let factors = directivity.port_factors(0.);
+
+        settled_pressure(&mut grid, &positions, |grid, step| {
+            let value = gain * (TAU * frequency * step as f32 * grid.delta_t).sin();
+            inject(grid, center, center, factors, value);
+        })
+        .iter()
+        .map(|pressure| peak(pressure))
+        .collect()
+    }
+
+    /// Mean squared peak pressure over all angles, proportional to the radiated power
+    fn mean_square(peaks: &[f32]) -> f32 {
+        peaks.iter().map(|peak| peak.powi(2)).sum::<f32>() / peaks.len() as f32
+    }
+
+    #[test]
+    fn port_factors_point_the_pattern_to_the_orientation() {
+        let [bottom, left, top, right] = Directivity::Dipole.port_factors(90.);
+        assert!(bottom < 0. && top > 0.);
+        assert!(left.abs() < 1e-6 && right.abs() < 1e-6);
+        assert_eq!(Directivity::Omnidirectional.port_factors(45.), [1.; 4]);
+    }
+
+    #[test]
+    fn patterns_keep_the_on_axis_calibration() {
+        let omnidirectional = peaks_at_one_meter(Directivity::Omnidirectional);
+        let dipole = peaks_at_one_meter(Directivity::Dipole);
+        let cardioid = peaks_at_one_meter(Directivity::Cardioid);
+
+        for peaks in [&dipole, &cardioid] {
+            assert!(
+                (peaks[0] / omnidirectional[0] - 1.).abs() < 0.1,
+                "on axis {} Pa, omnidirectional {} Pa",
+                peaks[0],
+                omnidirectional[0]
+            );
+        }
+        // the dipole is silent at 90° and the cardioid at 180°
+        assert!(
+            dipole[4] < 0.1 * dipole[0],
+            "dipole at 90°: {} Pa",
+            dipole[4]
+        );
+        assert!(
+            cardioid[8] < 0.1 * cardioid[0],
+            "cardioid at 180°: {} Pa",
+            cardioid[8]
+        );
+        // a cardioid is 6 dB down at 90°
+        assert!((cardioid[4] / cardioid[0] - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn patterns_radiate_the_power_of_their_weights() {
+        let omnidirectional = mean_square(&peaks_at_one_meter(Directivity::Omnidirectional));
+
+        // the pattern monopole + dipole * cos(θ) radiates monopole² + dipole² / 2 of the power
+        // of an omnidirectional source with the same on axis pressure
+        for directivity in [Directivity::Dipole, Directivity::Cardioid] {
+            let (monopole, dipole) = directivity.weights();
+            let expected = monopole.powi(2) + dipole.powi(2) / 2.;
+            let ratio = mean_square(&peaks_at_one_meter(directivity)) / omnidirectional;
+            assert!(
+                (ratio / expected - 1.).abs() < 0.1,
+                "{} radiates {} of the power, expected {}",
+                directivity,
+                ratio,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn muted_and_not_soloed_sources_are_silent() {
+        let source = |muted: bool, solo: bool| Source {
+            muted,
+            solo,
+            ..Source::new(0, 0, SourceType::default_sin(), 0)
+        };
+
+        for (muted, solo, any_solo, audible) in [
+            (false, false, false, true),
+            (true, false, false, false),
+            // any solo silences the sources that are not soloed
+            (false, false, true, false),
+            (true, false, true, false),
+            (false, true, true, true),
+            // a muted source stays silent when it is soloed
+            (true, true, true, false),
+        ] {
+            assert_eq!(
+                source(muted, solo).is_audible(any_solo),
+                audible,
+                "muted: {}, solo: {}, any solo: {}",
+                muted,
+                solo,
+                any_solo
+            );
+        }
+
+        let mut disabled = source(false, true);
+        disabled.enabled = false;
+        assert!(!disabled.is_audible(true));
+    }
+}
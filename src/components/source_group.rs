@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::source::SourceType;
+
+/// A group of sources that play the same signal.
+///
+/// The members of the group are the [`Source`](super::source::Source)s with the id of the group,
+/// each member can shift the phase of periodic signals by its own offset.
+#[derive(Debug, Component, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SourceGroup {
+    pub id: usize,
+    /// signal of all members of the group
+    pub source_type: SourceType,
+}
+
+impl SourceGroup {
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            source_type: SourceType::default_sin(),
+        }
+    }
+
+    /// Seed of the white noise of the group, all members play the same noise
+    pub fn noise_seed(&self) -> u64 {
+        5 << 24 | self.id as u64
+    }
+}
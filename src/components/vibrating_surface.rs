@@ -0,0 +1,356 @@
+use std::f32::consts::{PI, SQRT_2, TAU};
+use std::fmt;
+
+use bevy::prelude::*;
+use egui::epaint::{CircleShape, TextShape};
+use egui::text::LayoutJob;
+use egui::{Align2, Color32, Pos2, Rect, TextFormat};
+use serde::{Deserialize, Serialize};
+
+use super::source::SourceType;
+use super::wall::{CircWall, RectWall, Wall};
+use crate::math::constants::*;
+use crate::math::transformations::grid_to_image;
+use crate::render::gradient::Gradient;
+
+/// A part of the surface of a wall that vibrates with a prescribed normal velocity
+/// and radiates into the simulation area.
+#[derive(Debug, Component, Serialize, Deserialize, Clone, PartialEq)]
+pub struct VibratingSurface {
+    pub id: usize,
+    /// id of the [`RectWall`] or [`CircWall`] the surface belongs to
+    pub wall_id: usize,
+    pub edge: SurfaceEdge,
+    /// normal velocity of the surface (in m/s)
+    pub source_type: SourceType,
+    pub mode: SurfaceMode,
+}
+
+/// The part of a wall that vibrates
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub enum SurfaceEdge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    /// An arc of a circular wall between two angles (in °, counterclockwise starting to the right)
+    Arc {
+        start: f32,
+        end: f32,
+    },
+}
+
+impl fmt::Display for SurfaceEdge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SurfaceEdge::Top => write!(f, "Top"),
+            SurfaceEdge::Bottom => write!(f, "Bottom"),
+            SurfaceEdge::Left => write!(f, "Left"),
+            SurfaceEdge::Right => write!(f, "Right"),
+            SurfaceEdge::Arc { .. } => write!(f, "Arc"),
+        }
+    }
+}
+
+/// The distribution of the velocity along the surface
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub enum SurfaceMode {
+    /// the whole surface moves in phase like a rigid piston
+    #[default]
+    Piston,
+    /// mode shape `sin(order * π * s)` of a simply supported plate, where `s` is the position
+    /// along the surface from 0 to 1
+    Flexural { order: u32 },
+}
+
+impl fmt::Display for SurfaceMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SurfaceMode::Piston => write!(f, "Piston"),
+            SurfaceMode::Flexural { .. } => write!(f, "Flexural mode"),
+        }
+    }
+}
+
+impl SurfaceMode {
+    /// Relative velocity at the position `s` (from 0 to 1) along the surface
+    fn shape(&self, s: f32) -> f32 {
+        match self {
+            SurfaceMode::Piston => 1.,
+            SurfaceMode::Flexural { order } => (*order as f32 * PI * s).sin(),
+        }
+    }
+}
+
+/// A wall cell of a [`VibratingSurface`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceElement {
+    pub x: u32,
+    pub y: u32,
+    /// factors of the velocity that are injected into the bottom, left, top and right port
+    pub port_factors: [f32; 4],
+}
+
+impl VibratingSurface {
+    pub fn new(wall_id: usize, edge: SurfaceEdge, id: usize) -> Self {
+        Self {
+            id,
+            wall_id,
+            edge,
+            source_type: SourceType::default_sin(),
+            mode: SurfaceMode::default(),
+        }
+    }
+
+    /// Calculates the wall cells of the surface and the factors their ports are driven with.
+    ///
+    /// A velocity `v` is injected into the outward facing ports of the wall cells as
+    /// `√2 * ρ * c * v`, which radiates a plane wave with a sound pressure of `ρ * c * v`
+    /// from a long rigid surface. Returns no elements if the wall does not exist or does not
+    /// have the edge of the surface.
+    pub fn calc_elements(
+        &self,
+        rect_walls: &[&RectWall],
+        circ_walls: &[&CircWall],
+    ) -> Vec<SurfaceElement> {
+        let gain = SQRT_2 * AIR_DENSITY * SPEED_OF_SOUND;
+
+        if let Some(wall) = rect_walls.iter().find(|wall| wall.id == self.wall_id) {
+            let (min, max) = (wall.rect.min, wall.rect.max);
+            let (cells, port): (Vec<(u32, u32)>, usize) = match self.edge {
+                SurfaceEdge::Top => ((min.x..=max.x).map(|x| (x, min.y)).collect(), 2),
+                SurfaceEdge::Bottom => ((min.x..=max.x).map(|x| (x, max.y)).collect(), 0),
+                SurfaceEdge::Left => ((min.y..=max.y).map(|y| (min.x, y)).collect(), 1),
+                SurfaceEdge::Right => ((min.y..=max.y).map(|y| (max.x, y)).collect(), 3),
+                SurfaceEdge::Arc { .. } => return vec![],
+            };
+            let last = (cells.len().max(2) - 1) as f32;
+            return cells
+                .into_iter()
+                .enumerate()
+                .map(|(index, (x, y))| {
+                    let mut port_factors = [0.; 4];
+                    port_factors[port] = gain * self.mode.shape(index as f32 / last);
+                    SurfaceElement { x, y, port_factors }
+                })
+                .collect();
+        }
+
+        if let Some(wall) = circ_walls.iter().find(|wall| wall.id == self.wall_id) {
+            let SurfaceEdge::Arc { start, end } = self.edge else {
+                return vec![];
+            };
+            let start = start.to_radians().rem_euclid(TAU);
+            let length = (end.to_radians() - start).rem_euclid(TAU);
+
+            let mut elements = wall
+                .circle_points()
+                .into_iter()
+                .filter(|point| {
+                    point.x >= 0
+                        && point.y >= 0
+                        && (point.x as u32) < SIMULATION_WIDTH
+                        && (point.y as u32) < SIMULATION_HEIGHT
+                })
+                .filter_map(|point| {
+                    let offset = (point - wall.center.as_ivec2()).as_vec2();
+                    // y is pointing down
+                    let angle = (-offset.y).atan2(offset.x);
+                    let s = (angle - start).rem_euclid(TAU);
+                    (s <= length).then(|| {
+                        // split the velocity onto the ports facing the normal of the surface
+                        let shape = gain * self.mode.shape(s / length.max(f32::EPSILON));
+                        let (cos, sin) = (angle.cos(), angle.sin());
+                        let element = SurfaceElement {
+                            x: point.x as u32,
+                            y: point.y as u32,
+                            port_factors: [
+                                shape * (-sin).max(0.),
+                                shape * (-cos).max(0.),
+                                shape * sin.max(0.),
+                                shape * cos.max(0.),
+                            ],
+                        };
+                        (s, element)
+                    })
+                })
+                .collect::<Vec<_>>();
+            // order the cells along the arc, the octants of the circle overlap on the diagonals
+            elements.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+            elements.dedup_by_key(|(_, element)| (element.x, element.y));
+            return elements.into_iter().map(|(_, element)| element).collect();
+        }
+
+        vec![]
+    }
+
+    /// Returns the wall and the part of it a surface at the given position vibrates on: the
+    /// edge of a rectangular wall closest to the position or an arc of 90° of a circular wall
+    /// around the position.
+    pub fn wall_edge_at(
+        x: u32,
+        y: u32,
+        rect_walls: &[&RectWall],
+        circ_walls: &[&CircWall],
+    ) -> Option<(usize, SurfaceEdge)> {
+        for wall in rect_walls {
+            if wall.contains_pointer(x, y) {
+                let (_, edge) = [
+                    (y - wall.rect.min.y, SurfaceEdge::Top),
+                    (wall.rect.max.y - y, SurfaceEdge::Bottom),
+                    (x - wall.rect.min.x, SurfaceEdge::Left),
+                    (wall.rect.max.x - x, SurfaceEdge::Right),
+                ]
+                .into_iter()
+                .min_by_key(|(distance, _)| *distance)
+                .unwrap();
+                return Some((wall.id, edge));
+            }
+        }
+        for wall in circ_walls {
+            let offset = Vec2::new(
+                x as f32 - wall.center.x as f32,
+                wall.center.y as f32 - y as f32,
+            );
+            if offset.length() <= wall.radius as f32 + 10. {
+                let angle = offset.y.atan2(offset.x).to_degrees();
+                return Some((
+                    wall.id,
+                    SurfaceEdge::Arc {
+                        start: angle - 45.,
+                        end: angle + 45.,
+                    },
+                ));
+            }
+        }
+        None
+    }
+
+    /// Returns whether the position is within 10 cells of the center of the surface, where its
+    /// id is drawn.
+    pub fn contains_pointer(
+        &self,
+        x: u32,
+        y: u32,
+        rect_walls: &[&RectWall],
+        circ_walls: &[&CircWall],
+    ) -> bool {
+        let elements = self.calc_elements(rect_walls, circ_walls);
+        elements
+            .get(elements.len() / 2)
+            .is_some_and(|element| element.x.abs_diff(x) <= 10 && element.y.abs_diff(y) <= 10)
+    }
+
+    /// Seed of the white noise of the surface, distinct from the seeds of the other objects
+    pub fn noise_seed(&self) -> u64 {
+        3 << 24 | self.id as u64
+    }
+
+    /// Draws the cells of the surface and the id of the surface.
+    pub fn draw_gizmo(
+        &self,
+        painter: &egui::Painter,
+        elements: &[SurfaceElement],
+        highlight: bool,
+        image_rect: &Rect,
+        current_gradient: Gradient,
+    ) {
+        let (gizmo_color, text_color) = match current_gradient {
+            Gradient::Turbo => (Color32::from_rgb(1, 89, 88), Color32::WHITE),
+            _ => (Color32::from_rgb(15, 194, 192), Color32::BLACK),
+        };
+
+        for element in elements {
+            painter.add(egui::Shape::Circle(CircleShape::filled(
+                grid_to_image(
+                    Pos2 {
+                        x: element.x as f32,
+                        y: element.y as f32,
+                    },
+                    image_rect,
+                ),
+                if highlight { 3. } else { 2. },
+                gizmo_color,
+            )));
+        }
+
+        if let Some(element) = elements.get(elements.len() / 2) {
+            let pos = grid_to_image(
+                Pos2 {
+                    x: element.x as f32,
+                    y: element.y as f32,
+                },
+                image_rect,
+            );
+            painter.add(egui::Shape::Circle(CircleShape::filled(
+                pos,
+                if highlight { 10. } else { 7. },
+                gizmo_color,
+            )));
+            let galley = painter.layout_job(LayoutJob::single_section(
+                format!("{}", self.id),
+                TextFormat {
+                    color: text_color,
+                    background: Color32::TRANSPARENT,
+                    ..Default::default()
+                },
+            ));
+            let rect = Align2::CENTER_CENTER.anchor_size(pos, galley.size());
+            painter.add(TextShape::new(rect.min, galley, Color32::BLACK));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the elements of a piston on an arc of a circular wall with its center at
+    /// (100, 100) and a radius of 20 cells.
+    fn arc_elements(start: f32, end: f32) -> Vec<SurfaceElement> {
+        let wall = CircWall::new(100, 100, 20, false, 1., 0);
+        VibratingSurface::new(0, SurfaceEdge::Arc { start, end }, 0).calc_elements(&[], &[&wall])
+    }
+
+    #[test]
+    fn arc_drives_the_ports_facing_its_normal() {
+        let gain = SQRT_2 * AIR_DENSITY * SPEED_OF_SOUND;
+
+        // the right quarter of the circle radiates to the right, up and down but never left
+        let elements = arc_elements(-45., 45.);
+        assert!(!elements.is_empty());
+        for element in &elements {
+            let [bottom, left, top, right] = element.port_factors;
+            assert_eq!(left, 0.);
+            assert!(right > 0.);
+            // above the center the cells face up, below the center they face down
+            assert_eq!(top > 0., element.y < 100);
+            assert_eq!(bottom > 0., element.y > 100);
+        }
+        let right_point = elements
+            .iter()
+            .find(|element| (element.x, element.y) == (120, 100))
+            .unwrap();
+        assert_eq!(right_point.port_factors[3], gain);
+        assert!(
+            right_point.port_factors[0].abs() < 1e-3 && right_point.port_factors[2].abs() < 1e-3
+        );
+
+        // the top quarter radiates upwards, y is pointing down
+        let elements = arc_elements(45., 135.);
+        assert!(elements.iter().all(|element| element.port_factors[0] == 0.));
+        let top_point = elements
+            .iter()
+            .find(|element| (element.x, element.y) == (100, 80))
+            .unwrap();
+        assert_eq!(top_point.port_factors[2], gain);
+
+        // on the diagonals the velocity is split equally onto both ports
+        let diagonal = elements
+            .iter()
+            .min_by_key(|element| (element.x as i32 - 86).abs() + (element.y as i32 - 86).abs())
+            .unwrap();
+        let [_, left, top, _] = diagonal.port_factors;
+        assert!((left - top).abs() < 0.1 * gain, "{:?}", diagonal);
+    }
+}
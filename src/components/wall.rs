@@ -597,6 +597,38 @@ impl Wall for CircWall {
 }
 
 impl CircWall {
+    /// Calculates the points on the circle of the wall using the midpoint circle algorithm.
+    /// Points can be outside of the simulation area.
+    pub fn circle_points(&self) -> Vec<IVec2> {
+        let center = self.center.as_ivec2();
+        let mut points = Vec::new();
+        let mut b_x = 0i32;
+        let mut b_y = self.radius as i32;
+        let mut d = 1 - self.radius as i32;
+        while b_x <= b_y {
+            points.extend([
+                center + IVec2::new(b_x, b_y),
+                center + IVec2::new(b_x, -b_y),
+                center + IVec2::new(-b_x, b_y),
+                center + IVec2::new(-b_x, -b_y),
+                center + IVec2::new(b_y, b_x),
+                center + IVec2::new(b_y, -b_x),
+                center + IVec2::new(-b_y, b_x),
+                center + IVec2::new(-b_y, -b_x),
+            ]);
+
+            if d < 0 {
+                d = d + 2 * b_x + 3;
+                b_x += 1;
+            } else {
+                d = d + 2 * (b_x - b_y) + 5;
+                b_x += 1;
+                b_y -= 1;
+            }
+        }
+        points
+    }
+
     pub fn new(
         x: u32,
         y: u32,
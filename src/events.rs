@@ -1,14 +1,21 @@
 use bevy::prelude::*;
 use bevy_file_dialog::FileDialogExt;
 
+use crate::components::line_array::LineArray;
 use crate::components::microphone::Microphone;
-use crate::components::source::Source;
+use crate::components::plane_wave::PlaneWave;
+use crate::components::source::{Source, SourceType};
+use crate::components::source_group::SourceGroup;
+use crate::components::vibrating_surface::VibratingSurface;
 use crate::components::wall::{CircWall, RectWall};
 use crate::render::gradient::Gradient;
+use crate::simulation::frequency_maps::FrequencyMaps;
 use crate::simulation::grid::Grid;
-use crate::simulation::plugin::ComponentIDs;
-use crate::ui::loading::{SceneSaveFileContents, WavFileContents};
-use crate::ui::state::{SimTime, UiState};
+use crate::simulation::levels::PressureLevels;
+use crate::simulation::noise_control::NoiseControl;
+use crate::simulation::plugin::{ComponentIDs, FilterStates, MicrophoneSignals};
+use crate::ui::loading::{CsvFileContents, SceneSaveFileContents, WavFileContents};
+use crate::ui::state::{ErrorMessage, SignalTarget, SimTime, UiState};
 
 pub struct EventPlugin;
 
@@ -22,18 +29,39 @@ impl Plugin for EventPlugin {
                 save_event,
                 load_scene_event,
                 load_wav_event,
+                load_csv_event,
                 new_event,
+                time_reversal_event,
             ),
         )
         .add_event::<UpdateWalls>()
         .add_event::<Reset>()
         .add_event::<LoadScene>()
         .add_event::<LoadWav>()
+        .add_event::<LoadCsv>()
         .add_event::<Save>()
-        .add_event::<New>();
+        .add_event::<New>()
+        .add_event::<TimeReversal>();
     }
 }
 
+/// All objects that are part of a scene
+pub type SceneObjects<'w, 's> = Query<
+    'w,
+    's,
+    Entity,
+    Or<(
+        With<Source>,
+        With<Microphone>,
+        With<RectWall>,
+        With<CircWall>,
+        With<LineArray>,
+        With<PlaneWave>,
+        With<VibratingSurface>,
+        With<SourceGroup>,
+    )>,
+>;
+
 #[derive(Event)]
 pub struct UpdateWalls;
 
@@ -43,9 +71,20 @@ pub fn update_wall_event(
     ui_state: Res<UiState>,
     rect_walls: Query<&RectWall>,
     circ_walls: Query<&CircWall>,
+    surfaces: Query<(Entity, &VibratingSurface)>,
+    mut commands: Commands,
 ) {
     for _ in wall_update_ev.read() {
         grid.update_walls(&rect_walls, &circ_walls, ui_state.boundary_width);
+
+        // remove the surfaces of deleted walls, their ids could be given to new walls
+        for (entity, surface) in surfaces.iter() {
+            if !rect_walls.iter().any(|wall| wall.id == surface.wall_id)
+                && !circ_walls.iter().any(|wall| wall.id == surface.wall_id)
+            {
+                commands.entity(entity).despawn();
+            }
+        }
     }
 }
 
@@ -61,6 +100,11 @@ pub fn reset_event(
     mut sim_time: ResMut<SimTime>,
     mut ui_state: ResMut<UiState>,
     mut mics: Query<&mut Microphone>,
+    mut filter_states: ResMut<FilterStates>,
+    mut mic_signals: ResMut<MicrophoneSignals>,
+    mut noise_control: ResMut<NoiseControl>,
+    mut levels: ResMut<PressureLevels>,
+    mut frequency_maps: ResMut<FrequencyMaps>,
 ) {
     for r in reset_ev.read() {
         if ui_state.reset_on_change || r.force {
@@ -68,6 +112,11 @@ pub fn reset_event(
             sim_time.samples_since_start = 0;
             grid.reset_cells(ui_state.boundary_width);
             mics.iter_mut().for_each(|mut mic| mic.clear());
+            filter_states.reset();
+            mic_signals.reset();
+            noise_control.reset();
+            levels.reset();
+            frequency_maps.reset();
             ui_state.highest_y_volume_plot = 0f64;
         }
     }
@@ -79,10 +128,7 @@ pub struct New;
 pub fn new_event(
     mut commands: Commands,
     mut new_ev: EventReader<New>,
-    sources: Query<(Entity, &Source)>,
-    mics: Query<(Entity, &Microphone)>,
-    rect_walls: Query<(Entity, &RectWall)>,
-    circ_walls: Query<(Entity, &CircWall)>,
+    objects: SceneObjects,
     mut ui_state: ResMut<UiState>,
     mut grid: ResMut<Grid>,
     mut wall_update_ev: EventWriter<UpdateWalls>,
@@ -92,16 +138,7 @@ pub fn new_event(
     mut sim_time: ResMut<SimTime>,
 ) {
     for _ in new_ev.read() {
-        for (e, _) in sources.iter() {
-            commands.entity(e).despawn();
-        }
-        for (e, _) in rect_walls.iter() {
-            commands.entity(e).despawn();
-        }
-        for (e, _) in circ_walls.iter() {
-            commands.entity(e).despawn();
-        }
-        for (e, _) in mics.iter() {
+        for e in objects.iter() {
             commands.entity(e).despawn();
         }
 
@@ -117,6 +154,88 @@ pub fn new_event(
     }
 }
 
+/// Event that turns the microphones into a time-reversal mirror.
+///
+/// Every microphone with a recording is replaced by a source that re-emits the time-reversed
+/// recording, so the wave refocuses at the positions of the original sources. The original sources
+/// are replaced by microphones to observe the refocusing, line arrays, plane waves, vibrating
+/// surfaces and source groups are removed.
+#[derive(Event)]
+pub struct TimeReversal;
+
+pub fn time_reversal_event(
+    mut commands: Commands,
+    mut time_reversal_ev: EventReader<TimeReversal>,
+    mut reset_ev: EventWriter<Reset>,
+    sources: Query<(Entity, &Source)>,
+    mics: Query<(Entity, &Microphone)>,
+    line_arrays: Query<Entity, With<LineArray>>,
+    plane_waves: Query<Entity, With<PlaneWave>>,
+    surfaces: Query<Entity, With<VibratingSurface>>,
+    source_groups: Query<Entity, With<SourceGroup>>,
+    mut ids: ResMut<ComponentIDs>,
+    ui_state: Res<UiState>,
+    mut error_message: ResMut<ErrorMessage>,
+) {
+    for _ in time_reversal_ev.read() {
+        let mut mics = mics
+            .iter()
+            .filter(|(_, mic)| !mic.record.is_empty())
+            .collect::<Vec<_>>();
+        if mics.is_empty() {
+            error_message.0 = Some(if ui_state.show_plots {
+                "No microphone has recorded anything yet. Run the simulation first.".to_string()
+            } else {
+                "Microphones only record while \"Show plots\" is enabled. Enable it and run the simulation first.".to_string()
+            });
+            continue;
+        }
+        mics.sort_by_key(|(_, mic)| mic.id);
+
+        let mut sources = sources.iter().collect::<Vec<_>>();
+        sources.sort_by_key(|(_, source)| source.id);
+        for (entity, source) in sources {
+            commands.entity(entity).despawn();
+            commands.spawn(Microphone::new(source.x, source.y, ids.get_new_mic_id()));
+        }
+
+        // they would emit their signal again on top of the refocusing wave, the groups have no
+        // members left
+        for entity in line_arrays
+            .iter()
+            .chain(&plane_waves)
+            .chain(&surfaces)
+            .chain(&source_groups)
+        {
+            commands.entity(entity).despawn();
+        }
+
+        for (entity, mic) in mics {
+            let end_time = mic.record.last().map_or(0., |record| record[0]);
+            let points = mic
+                .record
+                .iter()
+                .rev()
+                .map(|record| [(end_time - record[0]) as f32, record[1] as f32])
+                .collect();
+
+            commands.entity(entity).despawn();
+            commands.spawn(Source::new(
+                mic.x,
+                mic.y,
+                SourceType::Recording {
+                    mic_id: mic.id,
+                    points,
+                    amplitude: 1.,
+                },
+                ids.get_new_source_id(),
+            ));
+        }
+
+        reset_ev.send(Reset { force: true });
+    }
+}
+
 #[derive(Event)]
 pub struct Save {
     pub new_file: bool,
@@ -130,6 +249,10 @@ pub fn save_event(
     mics: Query<&Microphone>,
     rect_walls: Query<&RectWall>,
     circ_walls: Query<&CircWall>,
+    line_arrays: Query<&LineArray>,
+    plane_waves: Query<&PlaneWave>,
+    surfaces: Query<&VibratingSurface>,
+    source_groups: Query<&SourceGroup>,
     gradient: Res<Gradient>,
     ui_state: Res<UiState>,
 ) {
@@ -138,12 +261,20 @@ pub fn save_event(
         let mics = mics.iter().collect::<Vec<_>>();
         let rect_walls = rect_walls.iter().collect::<Vec<_>>();
         let circ_walls = circ_walls.iter().collect::<Vec<_>>();
+        let line_arrays = line_arrays.iter().collect::<Vec<_>>();
+        let plane_waves = plane_waves.iter().collect::<Vec<_>>();
+        let surfaces = surfaces.iter().collect::<Vec<_>>();
+        let source_groups = source_groups.iter().collect::<Vec<_>>();
 
         let data = crate::ui::saving::serialize(
             &sources,
             &mics,
             &rect_walls,
             &circ_walls,
+            &line_arrays,
+            &plane_waves,
+            &surfaces,
+            &source_groups,
             &gradient,
             ui_state.max_gradient,
             ui_state.min_gradient,
@@ -180,11 +311,19 @@ pub fn load_scene_event(mut commands: Commands, mut load_ev: EventReader<LoadSce
     }
 }
 
+/// Event that opens a file dialog to load a wave file for the given object.
 #[derive(Event)]
-pub struct LoadWav;
+pub struct LoadWav {
+    pub target: SignalTarget,
+}
 
-pub fn load_wav_event(mut commands: Commands, mut load_ev: EventReader<LoadWav>) {
-    for _ in load_ev.read() {
+pub fn load_wav_event(
+    mut commands: Commands,
+    mut load_ev: EventReader<LoadWav>,
+    mut ui_state: ResMut<UiState>,
+) {
+    for event in load_ev.read() {
+        ui_state.wave_file_target = Some(event.target);
         commands
             .dialog()
             .add_filter("WAV", &["wav"])
@@ -193,3 +332,102 @@ pub fn load_wav_event(mut commands: Commands, mut load_ev: EventReader<LoadWav>)
             .load_file::<WavFileContents>();
     }
 }
+
+/// Event that opens a file dialog to load a CSV sample table for the given object.
+#[derive(Event)]
+pub struct LoadCsv {
+    pub target: SignalTarget,
+}
+
+pub fn load_csv_event(
+    mut commands: Commands,
+    mut load_ev: EventReader<LoadCsv>,
+    mut ui_state: ResMut<UiState>,
+) {
+    for event in load_ev.read() {
+        ui_state.csv_file_target = Some(event.target);
+        commands
+            .dialog()
+            .add_filter("CSV", &["csv", "txt"])
+            .set_directory("./")
+            .set_title("Select a file to load")
+            .load_file::<CsvFileContents>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    /// Returns a world with the resources of the time reversal, a source and a microphone
+    fn time_reversal_world(record: Vec<[f64; 2]>) -> World {
+        let mut world = World::new();
+        world.init_resource::<Events<TimeReversal>>();
+        world.init_resource::<Events<Reset>>();
+        world.init_resource::<UiState>();
+        world.init_resource::<ErrorMessage>();
+        let mut ids = ComponentIDs::default();
+        world.spawn(Source::new(
+            30,
+            40,
+            SourceType::default_sin(),
+            ids.get_new_source_id(),
+        ));
+        let mut mic = Microphone::new(10, 20, ids.get_new_mic_id());
+        mic.record = record;
+        world.spawn(mic);
+        world.insert_resource(ids);
+        world.send_event(TimeReversal);
+        world
+    }
+
+    #[test]
+    fn recordings_are_played_backwards_from_the_microphones() {
+        let mut world = time_reversal_world(vec![[0., 1.], [0.25, 2.], [0.5, 3.], [1., 4.]]);
+        world.run_system_once(time_reversal_event);
+
+        let sources = world.query::<&Source>().iter(&world).collect::<Vec<_>>();
+        assert_eq!(sources.len(), 1);
+        assert_eq!((sources[0].x, sources[0].y), (10, 20));
+        assert_eq!(
+            sources[0].source_type,
+            SourceType::Recording {
+                mic_id: 0,
+                points: vec![[0., 4.], [0.5, 3.], [0.75, 2.], [1., 1.]],
+                amplitude: 1.,
+            }
+        );
+
+        // the source is replaced by a microphone
+        let mics = world
+            .query::<&Microphone>()
+            .iter(&world)
+            .collect::<Vec<_>>();
+        assert_eq!(mics.len(), 1);
+        assert_eq!((mics[0].x, mics[0].y), (30, 40));
+        assert!(mics[0].record.is_empty());
+
+        let resets = world
+            .resource_mut::<Events<Reset>>()
+            .drain()
+            .collect::<Vec<_>>();
+        assert!(resets.len() == 1 && resets[0].force);
+        assert!(world.resource::<ErrorMessage>().0.is_none());
+    }
+
+    #[test]
+    fn time_reversal_without_recordings_is_an_error() {
+        let mut world = time_reversal_world(Vec::new());
+        world.run_system_once(time_reversal_event);
+
+        assert!(world.resource::<ErrorMessage>().0.is_some());
+        // nothing is replaced
+        let sources = world.query::<&Source>().iter(&world).collect::<Vec<_>>();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].source_type, SourceType::default_sin());
+        assert_eq!(world.query::<&Microphone>().iter(&world).count(), 1);
+        assert!(world.resource::<Events<Reset>>().is_empty());
+    }
+}
@@ -2,9 +2,12 @@ use bevy::app::AppExit;
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 
+use crate::components::line_array::LineArray;
 use crate::components::microphone::Microphone;
+use crate::components::plane_wave::PlaneWave;
 use crate::components::source::{Source, SourceType};
 use crate::components::states::{Move, Selected};
+use crate::components::vibrating_surface::VibratingSurface;
 use crate::components::wall::{CircWall, RectWall, WResize, Wall};
 use crate::events::{LoadScene, Reset, Save, UpdateWalls};
 use crate::math::transformations::{screen_to_grid, screen_to_nearest_grid};
@@ -22,6 +25,9 @@ pub fn copy_paste_system(
     rect_walls: Query<(Entity, &RectWall), With<Selected>>,
     circ_walls: Query<(Entity, &CircWall), With<Selected>>,
     mics: Query<(Entity, &Microphone), With<Selected>>,
+    line_arrays: Query<(Entity, &LineArray), With<Selected>>,
+    plane_waves: Query<(Entity, &PlaneWave), With<Selected>>,
+    surfaces: Query<(Entity, &VibratingSurface), With<Selected>>,
 ) {
     #[cfg(not(target_os = "macos"))]
     let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
@@ -38,7 +44,7 @@ pub fn copy_paste_system(
     if ctrl && keys.just_pressed(KeyCode::KeyV) {
         if let Some(entity) = clipboard.get() {
             if let Ok((_, source)) = sources.get(entity) {
-                let mut source = *source;
+                let mut source = source.clone();
                 source.id = ids.get_new_source_id();
                 commands.spawn(source);
             } else if let Ok((_, rect_wall)) = rect_walls.get(entity) {
@@ -55,6 +61,18 @@ pub fn copy_paste_system(
                 let mut mic = mic.clone();
                 mic.id = ids.get_new_mic_id();
                 commands.spawn(mic);
+            } else if let Ok((_, line_array)) = line_arrays.get(entity) {
+                let mut line_array = line_array.clone();
+                line_array.id = ids.get_new_line_array_id();
+                commands.spawn(line_array);
+            } else if let Ok((_, plane_wave)) = plane_waves.get(entity) {
+                let mut plane_wave = plane_wave.clone();
+                plane_wave.id = ids.get_new_plane_wave_id();
+                commands.spawn(plane_wave);
+            } else if let Ok((_, surface)) = surfaces.get(entity) {
+                let mut surface = surface.clone();
+                surface.id = ids.get_new_surface_id();
+                commands.spawn(surface);
             }
         }
     }
@@ -64,6 +82,9 @@ type RectWalls<'w, 's> = Query<'w, 's, (Entity, &'static RectWall)>;
 type CircWalls<'w, 's> = Query<'w, 's, (Entity, &'static CircWall)>;
 type Mics<'w, 's> = Query<'w, 's, (Entity, &'static Microphone)>;
 type Sources<'w, 's> = Query<'w, 's, (Entity, &'static Source)>;
+type LineArrays<'w, 's> = Query<'w, 's, (Entity, &'static LineArray)>;
+type PlaneWaves<'w, 's> = Query<'w, 's, (Entity, &'static PlaneWave)>;
+type Surfaces<'w, 's> = Query<'w, 's, (Entity, &'static VibratingSurface)>;
 
 type ResizeRectWalls<'w, 's> =
     Query<'w, 's, (Entity, &'static WResize, &'static mut RectWall), With<WResize>>;
@@ -74,11 +95,18 @@ type MoveRectWalls<'w, 's> = Query<'w, 's, (Entity, &'static mut RectWall), With
 type MoveCircWalls<'w, 's> = Query<'w, 's, (Entity, &'static mut CircWall), With<Move>>;
 type MoveMics<'w, 's> = Query<'w, 's, (Entity, &'static mut Microphone), With<Move>>;
 type MoveSources<'w, 's> = Query<'w, 's, (Entity, &'static mut Source), With<Move>>;
+type MoveLineArrays<'w, 's> = Query<'w, 's, (Entity, &'static mut LineArray), With<Move>>;
+type MovePlaneWaves<'w, 's> = Query<'w, 's, (Entity, &'static mut PlaneWave), With<Move>>;
+type MoveSurfaces<'w, 's> = Query<'w, 's, (Entity, &'static mut VibratingSurface), With<Move>>;
 
 type UnselectedRectWalls<'w, 's> = Query<'w, 's, (Entity, &'static RectWall), Without<Selected>>;
 type UnselectedCircWalls<'w, 's> = Query<'w, 's, (Entity, &'static CircWall), Without<Selected>>;
 type UnselectedMics<'w, 's> = Query<'w, 's, (Entity, &'static Microphone), Without<Selected>>;
 type UnselectedSources<'w, 's> = Query<'w, 's, (Entity, &'static Source), Without<Selected>>;
+type UnselectedLineArrays<'w, 's> = Query<'w, 's, (Entity, &'static LineArray), Without<Selected>>;
+type UnselectedPlaneWaves<'w, 's> = Query<'w, 's, (Entity, &'static PlaneWave), Without<Selected>>;
+type UnselectedSurfaces<'w, 's> =
+    Query<'w, 's, (Entity, &'static VibratingSurface), Without<Selected>>;
 
 pub fn button_input(
     mouse_buttons: Res<ButtonInput<MouseButton>>,
@@ -92,6 +120,9 @@ pub fn button_input(
     // Param Sets
     mut source_set: ParamSet<(Sources, UnselectedSources, MoveSources)>,
     mut mic_set: ParamSet<(Mics, UnselectedMics, MoveMics)>,
+    mut line_array_set: ParamSet<(LineArrays, UnselectedLineArrays, MoveLineArrays)>,
+    mut plane_wave_set: ParamSet<(PlaneWaves, UnselectedPlaneWaves, MovePlaneWaves)>,
+    mut surface_set: ParamSet<(Surfaces, UnselectedSurfaces, MoveSurfaces)>,
     mut rect_wall_set: ParamSet<(
         RectWalls,
         UnselectedRectWalls,
@@ -141,6 +172,34 @@ pub fn button_input(
                                     break 'outer;
                                 }
                             }
+                            for (entity, line_array) in line_array_set.p1().iter() {
+                                if line_array.x.abs_diff(x) <= 10 && line_array.y.abs_diff(y) <= 10
+                                {
+                                    commands.entity(entity).insert(Selected);
+                                    break 'outer;
+                                }
+                            }
+                            for (entity, plane_wave) in plane_wave_set.p1().iter() {
+                                if plane_wave.contains_pointer(x, y) {
+                                    commands.entity(entity).insert(Selected);
+                                    break 'outer;
+                                }
+                            }
+                            // surfaces lie on walls, so they are picked before the walls
+                            {
+                                let rect_walls = rect_wall_set.p0();
+                                let rect_walls =
+                                    rect_walls.iter().map(|(_, w)| w).collect::<Vec<_>>();
+                                let circ_walls = circ_wall_set.p0();
+                                let circ_walls =
+                                    circ_walls.iter().map(|(_, w)| w).collect::<Vec<_>>();
+                                for (entity, surface) in surface_set.p1().iter() {
+                                    if surface.contains_pointer(x, y, &rect_walls, &circ_walls) {
+                                        commands.entity(entity).insert(Selected);
+                                        break 'outer;
+                                    }
+                                }
+                            }
                             for (entity, rect_wall) in rect_wall_set.p1().iter() {
                                 if rect_wall.contains_pointer(x, y) {
                                     commands.entity(entity).insert(Selected);
@@ -211,6 +270,42 @@ pub fn button_input(
                             commands.spawn(Microphone::new(x, y, component_ids.get_new_mic_id()));
                         }
                     }
+                    PlaceType::LineArray => {
+                        if let Some((x, y)) =
+                            screen_to_grid(position.x, position.y, ui_state.image_rect)
+                        {
+                            commands.spawn(LineArray::new(
+                                x,
+                                y,
+                                component_ids.get_new_line_array_id(),
+                            ));
+                        }
+                    }
+                    PlaceType::PlaneWave => {
+                        // the wave always spans the whole simulation area
+                        if screen_to_grid(position.x, position.y, ui_state.image_rect).is_some() {
+                            commands.spawn(PlaneWave::new(component_ids.get_new_plane_wave_id()));
+                        }
+                    }
+                    PlaceType::VibratingSurface => {
+                        if let Some((x, y)) =
+                            screen_to_nearest_grid(position.x, position.y, ui_state.image_rect)
+                        {
+                            let rect_walls = rect_wall_set.p0();
+                            let rect_walls = rect_walls.iter().map(|(_, w)| w).collect::<Vec<_>>();
+                            let circ_walls = circ_wall_set.p0();
+                            let circ_walls = circ_walls.iter().map(|(_, w)| w).collect::<Vec<_>>();
+                            if let Some((wall_id, edge)) =
+                                VibratingSurface::wall_edge_at(x, y, &rect_walls, &circ_walls)
+                            {
+                                commands.spawn(VibratingSurface::new(
+                                    wall_id,
+                                    edge,
+                                    component_ids.get_new_surface_id(),
+                                ));
+                            }
+                        }
+                    }
                 },
                 ToolType::Edit => {
                     // This should only allow for one object to be selected
@@ -234,6 +329,36 @@ pub fn button_input(
                                 }
                             }
 
+                            for (entity, line_array) in line_array_set.p0().iter() {
+                                let (a_x, a_y) = (line_array.x, line_array.y);
+                                if a_x.abs_diff(x) <= 10 && a_y.abs_diff(y) <= 10 {
+                                    commands.entity(entity).insert((Move, Selected));
+                                    break 'outer; // only drag one at a time
+                                }
+                            }
+
+                            for (entity, plane_wave) in plane_wave_set.p0().iter() {
+                                if plane_wave.contains_pointer(x, y) {
+                                    commands.entity(entity).insert((Move, Selected));
+                                    break 'outer; // only drag one at a time
+                                }
+                            }
+
+                            {
+                                let rect_walls = rect_wall_set.p0();
+                                let rect_walls =
+                                    rect_walls.iter().map(|(_, w)| w).collect::<Vec<_>>();
+                                let circ_walls = circ_wall_set.p0();
+                                let circ_walls =
+                                    circ_walls.iter().map(|(_, w)| w).collect::<Vec<_>>();
+                                for (entity, surface) in surface_set.p0().iter() {
+                                    if surface.contains_pointer(x, y, &rect_walls, &circ_walls) {
+                                        commands.entity(entity).insert((Move, Selected));
+                                        break 'outer; // only drag one at a time
+                                    }
+                                }
+                            }
+
                             for (entity, wall) in rect_wall_set.p0().iter() {
                                 for resize_type in [
                                     WResize::TopLeft,
@@ -293,6 +418,15 @@ pub fn button_input(
         mic_set.p2().iter_mut().for_each(|(entity, _)| {
             commands.entity(entity).remove::<Move>();
         });
+        line_array_set.p2().iter_mut().for_each(|(entity, _)| {
+            commands.entity(entity).remove::<Move>();
+        });
+        plane_wave_set.p2().iter_mut().for_each(|(entity, _)| {
+            commands.entity(entity).remove::<Move>();
+        });
+        surface_set.p2().iter_mut().for_each(|(entity, _)| {
+            commands.entity(entity).remove::<Move>();
+        });
         rect_wall_set
             .p0()
             .iter_mut()
@@ -341,6 +475,33 @@ pub fn button_input(
                             mic.x = x;
                             mic.y = y;
                         });
+                        line_array_set
+                            .p2()
+                            .iter_mut()
+                            .for_each(|(_, mut line_array)| {
+                                line_array.x = x;
+                                line_array.y = y;
+                            });
+                        // a plane wave is turned towards the pointer
+                        plane_wave_set
+                            .p2()
+                            .iter_mut()
+                            .for_each(|(_, mut plane_wave)| plane_wave.point_to(x, y));
+                        // a surface moves to the wall and edge under the pointer
+                        {
+                            let rect_walls = rect_wall_set.p0();
+                            let rect_walls = rect_walls.iter().map(|(_, w)| w).collect::<Vec<_>>();
+                            let circ_walls = circ_wall_set.p0();
+                            let circ_walls = circ_walls.iter().map(|(_, w)| w).collect::<Vec<_>>();
+                            if let Some((wall_id, edge)) =
+                                VibratingSurface::wall_edge_at(x, y, &rect_walls, &circ_walls)
+                            {
+                                surface_set.p2().iter_mut().for_each(|(_, mut surface)| {
+                                    surface.wall_id = wall_id;
+                                    surface.edge = edge;
+                                });
+                            }
+                        }
 
                         // resize walls
                         rect_wall_set
@@ -394,6 +555,26 @@ pub fn button_input(
                                 source.y = y;
                             });
 
+                            // snap line array center
+                            line_array_set
+                                .p2()
+                                .iter_mut()
+                                .for_each(|(_, mut line_array)| {
+                                    let x = (line_array.x as f32 / 10.).round() as u32 * 10;
+                                    let y = (line_array.y as f32 / 10.).round() as u32 * 10;
+                                    line_array.x = x;
+                                    line_array.y = y;
+                                });
+
+                            // snap plane wave angle
+                            plane_wave_set
+                                .p2()
+                                .iter_mut()
+                                .for_each(|(_, mut plane_wave)| {
+                                    plane_wave.angle =
+                                        (plane_wave.angle / 15.).round() * 15. % 360.;
+                                });
+
                             // snap all four corners to grid (during resize)
                             rect_wall_set.p3().iter_mut().for_each(|(_, _, mut wall)| {
                                 let min = UVec2 {
@@ -0,0 +1,273 @@
+use std::f32::consts::PI;
+use std::fmt;
+
+use super::calibration::pressure_to_db_spl;
+use super::fft::Complex;
+use super::filter::{Biquad, BiquadState};
+
+/// Nominal center frequencies of the octave bands (in Hz)
+pub const OCTAVE_BANDS: [f32; 10] = [
+    31.5, 63., 125., 250., 500., 1000., 2000., 4000., 8000., 16000.,
+];
+
+/// Nominal center frequencies of the third octave bands (in Hz)
+pub const THIRD_OCTAVE_BANDS: [f32; 30] = [
+    25., 31.5, 40., 50., 63., 80., 100., 125., 160., 200., 250., 315., 400., 500., 630., 800.,
+    1000., 1250., 1600., 2000., 2500., 3150., 4000., 5000., 6300., 8000., 10000., 12500., 16000.,
+    20000.,
+];
+
+/// The order of the butterworth low-pass prototype of the band filters, the band-passes have
+/// twice the order
+pub const BAND_FILTER_ORDER: usize = 4;
+
+/// The width of the bands of a filter bank
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum BandResolution {
+    #[default]
+    Octave,
+    ThirdOctave,
+}
+
+impl BandResolution {
+    /// Returns the nominal center frequencies of the bands (in Hz).
+    pub fn centers(&self) -> &'static [f32] {
+        match self {
+            BandResolution::Octave => &OCTAVE_BANDS,
+            BandResolution::ThirdOctave => &THIRD_OCTAVE_BANDS,
+        }
+    }
+
+    /// Returns the bandwidth of the bands in octaves.
+    pub fn bandwidth(&self) -> f32 {
+        match self {
+            BandResolution::Octave => 1.,
+            BandResolution::ThirdOctave => 1. / 3.,
+        }
+    }
+}
+
+impl fmt::Display for BandResolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BandResolution::Octave => write!(f, "Octave"),
+            BandResolution::ThirdOctave => write!(f, "Third octave"),
+        }
+    }
+}
+
+/// A frequency weighting according to IEC 61672-1
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum FrequencyWeighting {
+    A,
+    C,
+    /// no weighting
+    #[default]
+    Z,
+}
+
+impl FrequencyWeighting {
+    /// Returns the gain of the weighting at a frequency (in dB).
+    pub fn gain(&self, frequency: f32) -> f32 {
+        let f2 = frequency.powi(2);
+        match self {
+            FrequencyWeighting::A => {
+                let r = 12194_f32.powi(2) * f2.powi(2)
+                    / ((f2 + 20.6_f32.powi(2))
+                        * ((f2 + 107.7_f32.powi(2)) * (f2 + 737.9_f32.powi(2))).sqrt()
+                        * (f2 + 12194_f32.powi(2)));
+                20. * r.log10() + 2.
+            }
+            FrequencyWeighting::C => {
+                let r =
+                    12194_f32.powi(2) * f2 / ((f2 + 20.6_f32.powi(2)) * (f2 + 12194_f32.powi(2)));
+                20. * r.log10() + 0.062
+            }
+            FrequencyWeighting::Z => 0.,
+        }
+    }
+}
+
+impl fmt::Display for FrequencyWeighting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrequencyWeighting::A => write!(f, "A"),
+            FrequencyWeighting::C => write!(f, "C"),
+            FrequencyWeighting::Z => write!(f, "Z (none)"),
+        }
+    }
+}
+
+/// Calculates the weighted sound pressure levels (in dB SPL) of a signal (in Pa) in the bands of
+/// a filter bank. Returns the nominal center frequency and the level of every band whose upper
+/// edge is below the Nyquist frequency.
+/// * `sample_rate` - The sample rate of the signal (in Hz).
+pub fn band_levels(
+    samples: &[f32],
+    resolution: BandResolution,
+    weighting: FrequencyWeighting,
+    sample_rate: f32,
+) -> Vec<(f32, f32)> {
+    let bandwidth = resolution.bandwidth();
+    resolution
+        .centers()
+        .iter()
+        .map(|nominal| (*nominal, exact_center(*nominal, bandwidth)))
+        .filter(|(_, center)| center * 2_f32.powf(bandwidth / 2.) < sample_rate / 2.)
+        .map(|(nominal, center)| {
+            let filtered = band_filter(samples, center, bandwidth, sample_rate);
+            let mean_square =
+                filtered.iter().map(|p| p * p).sum::<f32>() / filtered.len().max(1) as f32;
+            (
+                nominal,
+                pressure_to_db_spl(mean_square.sqrt()) + weighting.gain(center),
+            )
+        })
+        .collect()
+}
+
+/// Returns the exact center frequency of a band with a nominal center frequency (in Hz), the
+/// centers are spaced by the bandwidth (in octaves) around 1 kHz.
+pub fn exact_center(nominal: f32, bandwidth: f32) -> f32 {
+    1000. * 2_f32.powf(((nominal / 1000.).log2() / bandwidth).round() * bandwidth)
+}
+
+/// Filters a signal with a band-pass of the given bandwidth.
+///
+/// The filter is a Butterworth band-pass of order [`BAND_FILTER_ORDER`], so it is 3 dB down at
+/// the band edges, realized as a cascade of second order band-passes.
+/// * `center` - The center frequency of the band (in Hz).
+/// * `bandwidth` - The bandwidth of the band in octaves, e.g. 1/3 for third octave bands.
+/// * `sample_rate` - The sample rate of the signal (in Hz).
+pub fn band_filter(samples: &[f32], center: f32, bandwidth: f32, sample_rate: f32) -> Vec<f32> {
+    let (sections, gain) = band_sections(center, bandwidth, sample_rate);
+
+    let mut states = vec![BiquadState::default(); sections.len()];
+    samples
+        .iter()
+        .map(|sample| {
+            sections
+                .iter()
+                .zip(&mut states)
+                .fold(gain * sample, |x, (biquad, state)| state.process(biquad, x))
+        })
+        .collect()
+}
+
+/// Returns the second order sections of the band-pass of [`band_filter`] and the gain that scales
+/// the cascade to 0 dB at the center frequency.
+///
+/// Every pole of the low-pass prototype is transformed to a pair of band-pass poles, each
+/// conjugate pair forms one section. The band edges are prewarped for the bilinear transform.
+fn band_sections(center: f32, bandwidth: f32, sample_rate: f32) -> (Vec<Biquad>, f32) {
+    let warp = |frequency: f32| (PI * frequency.min(0.49 * sample_rate) / sample_rate).tan();
+    let lower = warp(center * 2_f32.powf(-bandwidth / 2.));
+    let upper = warp(center * 2_f32.powf(bandwidth / 2.));
+    let (center_squared, width) = (lower * upper, upper - lower);
+
+    let mut gain = width.powi(BAND_FILTER_ORDER as i32);
+    let sections = (0..BAND_FILTER_ORDER)
+        .map(|index| {
+            let angle =
+                PI * (2 * index + BAND_FILTER_ORDER + 1) as f32 / (2 * BAND_FILTER_ORDER) as f32;
+            let pole = Complex::from_polar(width, angle);
+            // the band-pass poles are the roots of s² - p·B·s + ω0²
+            let discriminant = pole * pole - Complex::new(4. * center_squared, 0.);
+            let root = Complex::from_polar(discriminant.abs().sqrt(), discriminant.arg() / 2.);
+            let (first, second) = ((pole + root) * 0.5, (pole - root) * 0.5);
+            let pole = if first.im >= second.im { first } else { second };
+
+            // s / (s² + a·s + b) is a band-pass with the center √b, a quality factor of √b / a
+            // and a gain of 1 / a at its center
+            let (a, b) = (-2. * pole.re, pole.norm_sqr());
+            gain /= a;
+            Biquad::band_pass(
+                b.sqrt().atan() * sample_rate / PI,
+                b.sqrt() / a,
+                sample_rate,
+            )
+        })
+        .collect();
+    (sections, gain)
+}
+
+/// Formats the center frequency of a band, e.g. "125 Hz" or "2 kHz".
+pub fn band_name(center: f32) -> String {
+    if center >= 1000. {
+        format!("{} kHz", center / 1000.)
+    } else {
+        format!("{} Hz", center)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{SQRT_2, TAU};
+
+    use super::*;
+
+    #[test]
+    fn weightings_at_known_frequencies() {
+        for (weighting, frequency, expected) in [
+            (FrequencyWeighting::A, 1000., 0.),
+            (FrequencyWeighting::C, 1000., 0.),
+            (FrequencyWeighting::A, 100., -19.1),
+            (FrequencyWeighting::C, 31.5, -3.),
+        ] {
+            let gain = weighting.gain(frequency);
+            assert!(
+                (gain - expected).abs() < 0.1,
+                "{} weighting at {} Hz is {} dB",
+                weighting,
+                frequency,
+                gain
+            );
+        }
+        assert_eq!(FrequencyWeighting::Z.gain(100.), 0.);
+    }
+
+    #[test]
+    fn sinusoid_is_in_its_band() {
+        let sample_rate = 48_000.;
+        let amplitude = 2.;
+        let samples = (0..48_000)
+            .map(|index| amplitude * (TAU * 1000. * index as f32 / sample_rate).sin())
+            .collect::<Vec<_>>();
+
+        let levels = band_levels(
+            &samples,
+            BandResolution::Octave,
+            FrequencyWeighting::Z,
+            sample_rate,
+        );
+        assert_eq!(levels.len(), OCTAVE_BANDS.len());
+        let level = |center: f32| levels.iter().find(|band| band.0 == center).unwrap().1;
+        let expected = pressure_to_db_spl(amplitude / SQRT_2);
+        assert!(
+            (level(1000.) - expected).abs() < 0.2,
+            "level of the 1 kHz band is {} dB instead of {} dB",
+            level(1000.),
+            expected
+        );
+        for neighbour in [500., 2000.] {
+            assert!(
+                level(neighbour) < expected - 20.,
+                "level of the {} Hz band is {} dB",
+                neighbour,
+                level(neighbour)
+            );
+        }
+
+        // the weighting is applied at the exact center of each band
+        let weighted = band_levels(
+            &samples,
+            BandResolution::Octave,
+            FrequencyWeighting::A,
+            sample_rate,
+        );
+        for ((center, level), (_, weighted)) in levels.iter().zip(&weighted) {
+            let gain = FrequencyWeighting::A.gain(exact_center(*center, 1.));
+            assert!((weighted - level - gain).abs() < 1e-3, "{} Hz", center);
+        }
+    }
+}
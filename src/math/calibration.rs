@@ -0,0 +1,193 @@
+use std::f32::consts::{PI, TAU};
+
+use super::constants::*;
+
+/// Calculates the sound pressure level (in dB SPL re 20 µPa) of an RMS sound pressure (in Pa).
+pub fn pressure_to_db_spl(rms_pressure: f32) -> f32 {
+    20. * (rms_pressure / REFERENCE_PRESSURE).log10()
+}
+
+/// Calculates the RMS sound pressure (in Pa) of a sound pressure level (in dB SPL re 20 µPa).
+pub fn db_spl_to_pressure(level: f32) -> f32 {
+    REFERENCE_PRESSURE * 10_f32.powf(level / 20.)
+}
+
+/// Calculates the sound power per meter (in W/m) that is radiated by a source with
+/// the given RMS sound pressure (in Pa) at a distance of 1 m.
+///
+/// Since the simulation is two dimensional, every source is a line source and its sound power
+/// is given per meter of its length.
+pub fn sound_power_per_length(rms_pressure: f32) -> f32 {
+    TAU * rms_pressure.powi(2) / (AIR_DENSITY * SPEED_OF_SOUND)
+}
+
+/// Calculates the sound intensity (in W/m²) of a plane wave with the given RMS sound pressure (in Pa).
+pub fn plane_wave_intensity(rms_pressure: f32) -> f32 {
+    rms_pressure.powi(2) / (AIR_DENSITY * SPEED_OF_SOUND)
+}
+
+/// Calculates the sound pressure (in Pa) of a plane wave with the given particle velocity (in m/s).
+pub fn velocity_to_pressure(velocity: f32) -> f32 {
+    AIR_DENSITY * SPEED_OF_SOUND * velocity
+}
+
+/// Calculates the factor a source value has to be scaled with before it is injected into the grid,
+/// so that a harmonic signal with an amplitude of 1 Pa results in a free field amplitude of 1 Pa
+/// at a distance of 1 m.
+///
+/// A value injected into the grid results in a pressure of `value * ω * Δt * |H0(k * 1 m)|`
+/// at a distance of 1 m, where `H0` is the hankel function of order zero.
+/// * `frequency` - The frequency the source is calibrated at (in Hz).
+/// * `delta_t` - The time step of the simulation (in s).
+pub fn source_gain(frequency: f32, delta_t: f32) -> f32 {
+    let frequency = frequency.max(1.);
+    let angular_frequency = TAU * frequency;
+    let wave_number = angular_frequency / SPEED_OF_SOUND;
+
+    1. / (angular_frequency * delta_t * hankel_magnitude(wave_number))
+}
+
+/// Magnitude of the hankel function of order zero `sqrt(J0(x)² + Y0(x)²)` for `x > 0`.
+///
+/// Uses the polynomial approximations 9.4.1 to 9.4.3 from Abramowitz and Stegun.
+fn hankel_magnitude(x: f32) -> f32 {
+    if x >= 3. {
+        let y = 3. / x;
+        let modulus = 0.797_884_6
+            + y * (-0.000_000_77
+                + y * (-0.005_527_40
+                    + y * (-0.000_095_12
+                        + y * (0.001_372_37 + y * (-0.000_728_05 + y * 0.000_144_76)))));
+        return modulus / x.sqrt();
+    }
+
+    let y = (x / 3.).powi(2);
+    let j0 = 1.
+        + y * (-2.249_999_7
+            + y * (1.265_620_8
+                + y * (-0.316_386_6 + y * (0.044_447_9 + y * (-0.003_944_4 + y * 0.000_210_0)))));
+    let y0 = 2. / PI * (x / 2.).ln() * j0
+        + 0.367_466_9
+        + y * (0.605_593_7
+            + y * (-0.743_503_84
+                + y * (0.253_001_17
+                    + y * (-0.042_612_14 + y * (0.004_279_16 - y * 0.000_248_46)))));
+
+    (j0.powi(2) + y0.powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+    use bevy::prelude::*;
+
+    use super::*;
+    use crate::components::source::{Source, SourceType};
+    use crate::components::source_group::SourceGroup;
+    use crate::simulation::grid::testing::{
+        coarse_grid, inject, peak, settled_pressure, ONE_METER,
+    };
+    use crate::simulation::noise_control::NoiseControl;
+    use crate::simulation::plugin::{FilterStates, MicrophoneSignals, SampleTables, WaveSamples};
+
+    #[test]
+    fn hankel_magnitude_matches_bessel_functions() {
+        // (x, J0(x), Y0(x))
+        let references = [
+            (0.5, 0.938_469_8, -0.444_518_7),
+            (1., 0.765_197_7, 0.088_257_0),
+            (2., 0.223_890_8, 0.510_375_7),
+            (3., -0.260_052, 0.376_85),
+            (5., -0.177_596_8, -0.308_517_6),
+            (10., -0.245_935_8, 0.055_671_2),
+            (20., 0.167_024_7, 0.062_640_6),
+        ];
+        for (x, j0, y0) in references {
+            let expected = f32::hypot(j0, y0);
+            let magnitude = hankel_magnitude(x);
+            assert!(
+                (magnitude - expected).abs() < 1e-5 * expected,
+                "|H0({})| = {}, expected {}",
+                x,
+                magnitude,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn source_gain_calibrates_amplitude_at_one_meter() {
+        let frequency = 1000.;
+        let mut grid = coarse_grid();
+        let gain = source_gain(frequency, grid.delta_t);
+
+        let center = SIMULATION_WIDTH / 2;
+        let pressure =
+            settled_pressure(&mut grid, &[(center + ONE_METER, center)], |grid, step| {
+                let value = gain * (TAU * frequency * step as f32 * grid.delta_t).sin();
+                inject(grid, center, center, [1.; 4], value);
+            });
+
+        let peak = peak(&pressure[0]);
+        assert!(
+            (peak - 1.).abs() < 0.05,
+            "peak amplitude at 1 m is {} Pa",
+            peak
+        );
+    }
+
+    #[test]
+    fn sources_radiate_their_amplitude_at_one_meter() {
+        let amplitude = 2.;
+        let mut grid = coarse_grid();
+
+        let center = SIMULATION_WIDTH / 2;
+        let mut world = World::new();
+        world.spawn(Source::new(
+            center,
+            center,
+            SourceType::Sin {
+                phase: 0.,
+                frequency: 1000.,
+                amplitude,
+            },
+            0,
+        ));
+        let mut state = SystemState::<(Query<&Source>, Query<&SourceGroup>)>::new(&mut world);
+        let (sources, groups) = state.get(&world);
+        let mut filter_states = FilterStates::default();
+
+        let pressure =
+            settled_pressure(&mut grid, &[(center + ONE_METER, center)], |grid, step| {
+                grid.apply_sources(
+                    step as f32 * grid.delta_t,
+                    step,
+                    &sources,
+                    &groups,
+                    INIT_BOUNDARY_WIDTH,
+                    &WaveSamples::default(),
+                    &SampleTables::default(),
+                    &mut filter_states,
+                    &MicrophoneSignals::default(),
+                    &NoiseControl::default(),
+                );
+            });
+
+        let peak = peak(&pressure[0]);
+        assert!(
+            (peak - amplitude).abs() < 0.05 * amplitude,
+            "peak amplitude at 1 m is {} Pa",
+            peak
+        );
+        let mean_square =
+            pressure[0].iter().map(|p| p.powi(2)).sum::<f32>() / pressure[0].len() as f32;
+        let level = pressure_to_db_spl(mean_square.sqrt());
+        let expected = pressure_to_db_spl(amplitude / std::f32::consts::SQRT_2);
+        assert!(
+            (level - expected).abs() < 0.5,
+            "level at 1 m is {} dB SPL, expected {} dB SPL",
+            level,
+            expected
+        );
+    }
+}
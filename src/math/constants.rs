@@ -4,8 +4,20 @@ pub const SIMULATION_WIDTH: u32 = 700;
 /// Amount of simulated pixels in the y direction
 pub const SIMULATION_HEIGHT: u32 = 700;
 
+/// Speed of sound in air (m/s)
+pub const SPEED_OF_SOUND: f32 = 343.2;
+
 /// Propagation speed of a sound wave in air (m/s) (* sqrt(2) to compensate for TLM-Error)
-pub const PROPAGATION_SPEED: f32 = 343.2 * std::f32::consts::SQRT_2;
+pub const PROPAGATION_SPEED: f32 = SPEED_OF_SOUND * std::f32::consts::SQRT_2;
+
+/// Density of air (kg/m³)
+pub const AIR_DENSITY: f32 = 1.204;
+
+/// Reference sound pressure for sound pressure levels (Pa)
+pub const REFERENCE_PRESSURE: f32 = 20e-6;
+
+/// Frequency at which sources without a fundamental frequency are calibrated (Hz)
+pub const CALIBRATION_FREQUENCY: f32 = 1000.;
 
 /// Width of the boundary in pixels
 pub const INIT_BOUNDARY_WIDTH: u32 = 50;
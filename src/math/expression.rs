@@ -0,0 +1,612 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Maximum nesting depth of an expression, deeper expressions are rejected while parsing
+const MAX_DEPTH: usize = 64;
+
+/// A mathematical expression in the time variable `t` (in s).
+///
+/// The expression is parsed once when its text is set and can then be evaluated without panicking.
+/// Supported are numbers, the constants `pi`, `tau` and `e`, the operators `+ - * / % ^`,
+/// parentheses and the functions listed in [`Function`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct Expression {
+    text: String,
+    parsed: Result<Node, String>,
+}
+
+impl Expression {
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self {
+            parsed: Parser::new(&text).parse(),
+            text,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text of the expression and parses it again
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        *self = Expression::new(text);
+    }
+
+    /// Returns the error message if the expression could not be parsed
+    pub fn error(&self) -> Option<&str> {
+        self.parsed.as_ref().err().map(|err| err.as_str())
+    }
+
+    /// Evaluates the expression at the given time (in s).
+    ///
+    /// Returns 0 if the expression is invalid or the result is not finite.
+    pub fn eval(&self, time: f32) -> f32 {
+        match &self.parsed {
+            Ok(node) => {
+                let value = node.eval(time as f64);
+                if value.is_finite() {
+                    value as f32
+                } else {
+                    0.
+                }
+            }
+            Err(_) => 0.,
+        }
+    }
+}
+
+impl From<String> for Expression {
+    fn from(text: String) -> Self {
+        Expression::new(text)
+    }
+}
+
+impl From<Expression> for String {
+    fn from(expression: Expression) -> Self {
+        expression.text
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+/// Functions that can be called in an [`Expression`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Exp,
+    /// natural logarithm
+    Ln,
+    /// logarithm to base 10
+    Log,
+    Sqrt,
+    Abs,
+    Sign,
+    Floor,
+    Ceil,
+    Round,
+    /// heaviside step function, 1 for arguments >= 0
+    Step,
+    Min,
+    Max,
+    Atan2,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "sin" => Function::Sin,
+            "cos" => Function::Cos,
+            "tan" => Function::Tan,
+            "asin" => Function::Asin,
+            "acos" => Function::Acos,
+            "atan" => Function::Atan,
+            "sinh" => Function::Sinh,
+            "cosh" => Function::Cosh,
+            "tanh" => Function::Tanh,
+            "exp" => Function::Exp,
+            "ln" => Function::Ln,
+            "log" => Function::Log,
+            "sqrt" => Function::Sqrt,
+            "abs" => Function::Abs,
+            "sign" => Function::Sign,
+            "floor" => Function::Floor,
+            "ceil" => Function::Ceil,
+            "round" => Function::Round,
+            "step" => Function::Step,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            "atan2" => Function::Atan2,
+            _ => return None,
+        })
+    }
+
+    fn arity(&self) -> usize {
+        match self {
+            Function::Min | Function::Max | Function::Atan2 => 2,
+            _ => 1,
+        }
+    }
+
+    fn apply(&self, args: &[f64]) -> f64 {
+        let x = args[0];
+        match self {
+            Function::Sin => x.sin(),
+            Function::Cos => x.cos(),
+            Function::Tan => x.tan(),
+            Function::Asin => x.asin(),
+            Function::Acos => x.acos(),
+            Function::Atan => x.atan(),
+            Function::Sinh => x.sinh(),
+            Function::Cosh => x.cosh(),
+            Function::Tanh => x.tanh(),
+            Function::Exp => x.exp(),
+            Function::Ln => x.ln(),
+            Function::Log => x.log10(),
+            Function::Sqrt => x.sqrt(),
+            Function::Abs => x.abs(),
+            Function::Sign => {
+                if x == 0. {
+                    0.
+                } else {
+                    x.signum()
+                }
+            }
+            Function::Floor => x.floor(),
+            Function::Ceil => x.ceil(),
+            Function::Round => x.round(),
+            Function::Step => {
+                if x >= 0. {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            Function::Min => x.min(args[1]),
+            Function::Max => x.max(args[1]),
+            Function::Atan2 => x.atan2(args[1]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Time,
+    Negate(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+impl Node {
+    fn eval(&self, time: f64) -> f64 {
+        match self {
+            Node::Number(value) => *value,
+            Node::Time => time,
+            Node::Negate(node) => -node.eval(time),
+            Node::Binary(operator, lhs, rhs) => {
+                let lhs = lhs.eval(time);
+                let rhs = rhs.eval(time);
+                match operator {
+                    Operator::Add => lhs + rhs,
+                    Operator::Sub => lhs - rhs,
+                    Operator::Mul => lhs * rhs,
+                    Operator::Div => lhs / rhs,
+                    Operator::Rem => lhs % rhs,
+                    Operator::Pow => lhs.powf(rhs),
+                }
+            }
+            Node::Call(function, args) => {
+                // functions take at most two arguments
+                let mut values = [0.; 2];
+                for (value, arg) in values.iter_mut().zip(args) {
+                    *value = arg.eval(time);
+                }
+                function.apply(&values)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Operator(Operator),
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+/// A recursive descent parser for [`Expression`]s
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+    error: Option<String>,
+}
+
+impl Parser {
+    fn new(text: &str) -> Self {
+        let mut parser = Self {
+            tokens: vec![],
+            position: 0,
+            depth: 0,
+            error: None,
+        };
+        match tokenize(text) {
+            Ok(tokens) => parser.tokens = tokens,
+            Err(err) => parser.error = Some(err),
+        }
+        parser
+    }
+
+    fn parse(mut self) -> Result<Node, String> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        if self.tokens.is_empty() {
+            return Err("the expression is empty".to_string());
+        }
+        let node = self.expression()?;
+        match self.peek() {
+            None => Ok(node),
+            Some(token) => Err(format!("unexpected {}", describe(token))),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn enter(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err("the expression is nested too deeply".to_string());
+        }
+        Ok(())
+    }
+
+    /// expression = term (("+" | "-") term)*
+    fn expression(&mut self) -> Result<Node, String> {
+        self.enter()?;
+        let mut node = self.term()?;
+        while let Some(Token::Operator(operator @ (Operator::Add | Operator::Sub))) = self.peek() {
+            let operator = *operator;
+            self.next();
+            node = Node::Binary(operator, Box::new(node), Box::new(self.term()?));
+        }
+        self.depth -= 1;
+        Ok(node)
+    }
+
+    /// term = unary (("*" | "/" | "%") unary)*
+    fn term(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        while let Some(Token::Operator(
+            operator @ (Operator::Mul | Operator::Div | Operator::Rem),
+        )) = self.peek()
+        {
+            let operator = *operator;
+            self.next();
+            node = Node::Binary(operator, Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    /// unary = ("-" | "+") unary | power
+    fn unary(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Some(Token::Operator(Operator::Sub)) => {
+                self.next();
+                self.enter()?;
+                let node = Node::Negate(Box::new(self.unary()?));
+                self.depth -= 1;
+                Ok(node)
+            }
+            Some(Token::Operator(Operator::Add)) => {
+                self.next();
+                self.enter()?;
+                let node = self.unary()?;
+                self.depth -= 1;
+                Ok(node)
+            }
+            _ => self.power(),
+        }
+    }
+
+    /// power = atom ("^" unary)?
+    fn power(&mut self) -> Result<Node, String> {
+        let node = self.atom()?;
+        if let Some(Token::Operator(Operator::Pow)) = self.peek() {
+            self.next();
+            self.enter()?;
+            let exponent = self.unary()?;
+            self.depth -= 1;
+            return Ok(Node::Binary(
+                Operator::Pow,
+                Box::new(node),
+                Box::new(exponent),
+            ));
+        }
+        Ok(node)
+    }
+
+    /// atom = number | constant | "t" | function "(" arguments ")" | "(" expression ")"
+    fn atom(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Node::Number(value)),
+            Some(Token::Identifier(name)) => match name.as_str() {
+                "t" => Ok(Node::Time),
+                "pi" => Ok(Node::Number(std::f64::consts::PI)),
+                "tau" => Ok(Node::Number(std::f64::consts::TAU)),
+                "e" => Ok(Node::Number(std::f64::consts::E)),
+                _ => {
+                    let Some(function) = Function::from_name(&name) else {
+                        return Err(format!("unknown name \"{}\"", name));
+                    };
+                    if self.next() != Some(Token::LeftParen) {
+                        return Err(format!("expected \"(\" after \"{}\"", name));
+                    }
+                    let mut args = vec![self.expression()?];
+                    while let Some(Token::Comma) = self.peek() {
+                        self.next();
+                        args.push(self.expression()?);
+                    }
+                    if self.next() != Some(Token::RightParen) {
+                        return Err(format!(
+                            "expected \")\" after the arguments of \"{}\"",
+                            name
+                        ));
+                    }
+                    if args.len() != function.arity() {
+                        return Err(format!(
+                            "\"{}\" takes {} argument(s) but {} were given",
+                            name,
+                            function.arity(),
+                            args.len()
+                        ));
+                    }
+                    Ok(Node::Call(function, args))
+                }
+            },
+            Some(Token::LeftParen) => {
+                let node = self.expression()?;
+                if self.next() != Some(Token::RightParen) {
+                    return Err("expected \")\"".to_string());
+                }
+                Ok(node)
+            }
+            Some(token) => Err(format!("unexpected {}", describe(&token))),
+            None => Err("unexpected end of the expression".to_string()),
+        }
+    }
+}
+
+/// Splits the text of an expression into tokens
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = text.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                // allow exponents like 1e-3
+                let is_exponent_sign =
+                    (c == '-' || c == '+') && text[start..i].ends_with(['e', 'E']);
+                if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || is_exponent_sign {
+                    end = i + c.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let number = &text[start..end];
+            tokens.push(Token::Number(
+                number
+                    .parse()
+                    .map_err(|_| format!("invalid number \"{}\"", number))?,
+            ));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' {
+                    end = i + c.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Identifier(text[start..end].to_string()));
+        } else {
+            tokens.push(match c {
+                '+' => Token::Operator(Operator::Add),
+                '-' => Token::Operator(Operator::Sub),
+                '*' => Token::Operator(Operator::Mul),
+                '/' => Token::Operator(Operator::Div),
+                '%' => Token::Operator(Operator::Rem),
+                '^' => Token::Operator(Operator::Pow),
+                '(' => Token::LeftParen,
+                ')' => Token::RightParen,
+                ',' => Token::Comma,
+                _ => return Err(format!("unexpected character \"{}\"", c)),
+            });
+            chars.next();
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(value) => format!("number \"{}\"", value),
+        Token::Identifier(name) => format!("name \"{}\"", name),
+        Token::Operator(operator) => format!(
+            "operator \"{}\"",
+            match operator {
+                Operator::Add => "+",
+                Operator::Sub => "-",
+                Operator::Mul => "*",
+                Operator::Div => "/",
+                Operator::Rem => "%",
+                Operator::Pow => "^",
+            }
+        ),
+        Token::LeftParen => "\"(\"".to_string(),
+        Token::RightParen => "\")\"".to_string(),
+        Token::Comma => "\",\"".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(text: &str) -> f32 {
+        let expression = Expression::new(text);
+        assert_eq!(expression.error(), None, "\"{}\" should parse", text);
+        expression.eval(0.)
+    }
+
+    fn error(text: &str) -> String {
+        Expression::new(text)
+            .error()
+            .unwrap_or_else(|| panic!("\"{}\" should not parse", text))
+            .to_string()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), 7.);
+        assert_eq!(eval("(1 + 2) * 3"), 9.);
+        assert_eq!(eval("7 % 4 * 2"), 6.);
+        assert_eq!(eval("2 * 3 ^ 2"), 18.);
+    }
+
+    #[test]
+    fn associativity() {
+        assert_eq!(eval("8 - 4 - 2"), 2.);
+        assert_eq!(eval("8 / 4 / 2"), 1.);
+        // the power is right associative
+        assert_eq!(eval("2 ^ 3 ^ 2"), 512.);
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(eval("-3"), -3.);
+        assert_eq!(eval("--3"), 3.);
+        assert_eq!(eval("+3"), 3.);
+        assert_eq!(eval("2 * -3"), -6.);
+        assert_eq!(eval("2 ^ -1"), 0.5);
+        // the power binds stronger than the unary minus
+        assert_eq!(eval("-2 ^ 2"), -4.);
+        assert_eq!(eval("(-2) ^ 2"), 4.);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(eval("1e-3"), 1e-3);
+        assert_eq!(eval("2.5E+2"), 250.);
+        assert_eq!(eval(".5"), 0.5);
+        assert_eq!(eval("1e3 - 1"), 999.);
+        assert!(error("1.2.3").contains("invalid number"));
+    }
+
+    #[test]
+    fn time_constants_and_functions() {
+        let expression = Expression::new("2 * t + max(1, sin(pi / 2))");
+        assert_eq!(expression.eval(0.5), 2.);
+        assert!((eval("tau") - std::f32::consts::TAU).abs() < 1e-6);
+        assert!((eval("ln(e)") - 1.).abs() < 1e-6);
+        assert_eq!(eval("step(0) + step(-1)"), 1.);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(error("foo(1)").contains("unknown name"));
+        assert!(error("2 * x").contains("unknown name"));
+        assert!(error("sin(1, 2)").contains("takes 1 argument(s) but 2 were given"));
+        assert!(error("max(1)").contains("takes 2 argument(s) but 1 were given"));
+        assert!(error("sin 1").contains("expected \"(\""));
+        assert!(error("(1 + 2").contains("expected \")\""));
+        assert!(error("1 +").contains("unexpected end"));
+        assert!(error("1 2").contains("unexpected number"));
+        assert!(error("1 $ 2").contains("unexpected character"));
+        assert!(error("").contains("empty"));
+    }
+
+    #[test]
+    fn invalid_expression_evaluates_to_zero() {
+        assert_eq!(Expression::new("sin(").eval(1.), 0.);
+        assert_eq!(Expression::new("1 / t").eval(0.), 0.);
+    }
+
+    #[test]
+    fn max_depth() {
+        let nested = |open: &str, close: &str, depth: usize| {
+            format!("{}1{}", open.repeat(depth), close.repeat(depth))
+        };
+
+        assert_eq!(eval(&nested("(", ")", MAX_DEPTH - 1)), 1.);
+        assert_eq!(eval(&nested("-", "", MAX_DEPTH - 1)).abs(), 1.);
+
+        for text in [
+            nested("(", ")", 100_000),
+            nested("-", "", 100_000),
+            nested("1^", "", 100_000),
+            nested("sin(", ")", 100_000),
+        ] {
+            assert!(error(&text).contains("nested too deeply"));
+        }
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let expression = Expression::new("sin(2*pi*440*t)");
+        let json = serde_json::to_string(&expression).unwrap();
+        assert_eq!(json, "\"sin(2*pi*440*t)\"");
+        assert_eq!(
+            serde_json::from_str::<Expression>(&json).unwrap(),
+            expression
+        );
+
+        // invalid expressions keep their text, so they can be fixed after loading
+        let invalid = serde_json::from_str::<Expression>("\"sin(\"").unwrap();
+        assert_eq!(invalid.text(), "sin(");
+        assert!(invalid.error().is_some());
+    }
+}
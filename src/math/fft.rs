@@ -1,58 +1,812 @@
-use spectrum_analyzer::scaling::{scale_20_times_log10, scale_to_zero_to_one};
-use spectrum_analyzer::windows::hann_window;
-use spectrum_analyzer::{samples_fft_to_spectrum, FrequencyLimit};
+use std::f32::consts::TAU;
+use std::ops::{Add, AddAssign, Div, Mul, Sub};
 
 use crate::components::microphone::Microphone;
+use crate::math::calibration::pressure_to_db_spl;
+use crate::math::constants::REFERENCE_PRESSURE;
+use crate::math::window::SpectralWindow;
 use crate::ui::state::FftScaling;
 
+/// The spectrum of a [`Microphone`]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Spectrum {
+    /// frequencies of the bins between 0 Hz and 20 kHz, without 0 Hz (in Hz)
+    pub frequencies: Vec<f32>,
+    /// levels of the bins, scaled as requested
+    pub levels: Vec<f32>,
+    /// phases of the bins in the latest block (in degrees, between -180° and 180°)
+    pub phases: Vec<f32>,
+}
+
+/// The power spectra of the blocks of a recording summed up for Welch's method, so a growing
+/// recording only needs the spectra of its new blocks to be calculated.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WelchSum {
+    /// sum of the power spectra of the blocks
+    power: Vec<f32>,
+    /// number of summed blocks
+    blocks: usize,
+    /// start of the next block in the record
+    next_start: usize,
+    /// time of the first sample of the record the blocks were taken from (in s)
+    first_time: f64,
+}
+
 /// Calculate the spectrum of a [`Microphone`] based on the record field.
 ///
-/// The spectrum is calculated using the FFT algorithm and a Hann window. The corresponding window size is specified in [`FFT_WINDOW_SIZE`].
-/// The spectrum is then mapped to a logarithmic scale on the frequency axis and returned.
+/// Without averaging the spectrum of the latest `fft_size` samples is calculated, with averaging
+/// the power spectra of all blocks of the record overlapping by half are averaged
+/// (Welch's method). The phase is always the one of the latest block. The levels are scaled so
+/// that a sinusoid at a bin has its RMS level, independent of the window.
 /// * `microphone` - The microphone to calculate the spectrum for.
 /// * `scaling` - The scaling to apply to the spectrum on the amplitude axis.
-/// * `delta_t` - The time between each sample in the record.
-/// * `fft_size` - The size of the FFT Window to use.
+/// * `fft_size` - The size of the FFT Window to use, has to be a power of two.
+/// * `window` - The window applied to every block.
+/// * `averaging` - Whether the spectra of all blocks are averaged.
+/// * `zero_padding` - The factor the blocks are extended with zeros by, has to be a power of two.
+/// * `welch` - The blocks of the record averaged so far, has to be reset when the block size,
+///   the window or the zero padding change.
 pub fn calc_mic_spectrum(
     microphone: &Microphone,
     scaling: FftScaling,
     delta_t: f32,
     fft_size: usize,
-) -> Vec<[f64; 2]> {
-    let samples = if microphone.record.len() < fft_size {
-        let mut s = microphone.record.clone();
-        s.resize(fft_size, [0.0, 0.0]);
-        s
+    window: SpectralWindow,
+    averaging: bool,
+    zero_padding: usize,
+    welch: &mut WelchSum,
+) -> Spectrum {
+    let record = &microphone.record;
+    let fft_len = fft_size * zero_padding.max(1);
+    let coefficients = window.coefficients(fft_size);
+    let coherent_gain = coefficients.iter().sum::<f32>() / fft_size as f32;
+    // records shorter than a block are padded with zeros
+    let block_spectrum = |start: usize| {
+        let mut buffer = coefficients
+            .iter()
+            .enumerate()
+            .map(|(index, window)| {
+                let sample = record
+                    .get(start + index)
+                    .map_or(0., |record| record[1] as f32);
+                Complex::new(sample * window, 0.)
+            })
+            .collect::<Vec<_>>();
+        buffer.resize(fft_len, Complex::default());
+        fft(&mut buffer);
+        buffer
+    };
+
+    let latest = block_spectrum(record.len().saturating_sub(fft_size));
+    let power = if averaging && record.len() >= fft_size {
+        let first_time = record[0][0];
+        if welch.power.len() != fft_len / 2 + 1
+            || welch.next_start > record.len()
+            || welch.first_time != first_time
+        {
+            *welch = WelchSum {
+                power: vec![0.; fft_len / 2 + 1],
+                first_time,
+                ..Default::default()
+            };
+        }
+        for start in (welch.next_start..=record.len() - fft_size).step_by(fft_size / 2) {
+            let spectrum = block_spectrum(start);
+            for (power, bin) in welch.power.iter_mut().zip(spectrum) {
+                *power += bin.norm_sqr();
+            }
+            welch.blocks += 1;
+            welch.next_start = start + fft_size / 2;
+        }
+        welch
+            .power
+            .iter()
+            .map(|power| power / welch.blocks as f32)
+            .collect()
     } else {
-        microphone.record[microphone.record.len() - fft_size..].to_vec()
+        latest.iter().map(|bin| bin.norm_sqr()).collect::<Vec<_>>()
     };
 
-    let hann_window = hann_window(&samples.iter().map(|x| x[1] as f32).collect::<Vec<_>>());
-    // always returns frequencies up to sampling_rate/2
-    let spectrum_hann_window = samples_fft_to_spectrum(
-        &hann_window,
-        // TODO: if this only returns up to sampling_rate/2 freqs, then why did we experience a mirrored spectrum?? cannot replicate
-        (1. / delta_t) as u32,
-        FrequencyLimit::All,
-        match scaling {
-            FftScaling::Normalized => Some(&scale_to_zero_to_one),
-            FftScaling::Decibels => Some(&scale_20_times_log10),
-        },
-    )
-    .unwrap();
-
-    let mapped_spectrum = spectrum_hann_window
-        .data()
+    let sample_rate = 1. / delta_t;
+    let bins = (1..=fft_len / 2)
+        .take_while(|bin| (*bin as f32 * sample_rate / fft_len as f32) < 20_000.)
+        .collect::<Vec<_>>();
+    // the peak amplitude of a sinusoid at a bin is 2 * |X| / (N * coherent gain)
+    let rms = bins
         .iter()
-        .filter_map(|(x, y)| {
-            // only return values between 0 and 20_000 Hz
-            if x.val() > 0. && x.val() < 20_000. {
-                Some([x.val().log10() as f64, y.val() as f64])
+        .map(|bin| {
+            2. * power[*bin].sqrt() / (fft_size as f32 * coherent_gain * std::f32::consts::SQRT_2)
+        })
+        .collect::<Vec<_>>();
+    let max_rms = rms.iter().copied().fold(0., f32::max);
+
+    Spectrum {
+        frequencies: bins
+            .iter()
+            .map(|bin| *bin as f32 * sample_rate / fft_len as f32)
+            .collect(),
+        levels: rms
+            .iter()
+            .map(|rms| match scaling {
+                FftScaling::Normalized if max_rms > 0. => rms / max_rms,
+                FftScaling::Normalized => 0.,
+                FftScaling::Decibels => pressure_to_db_spl(rms.max(REFERENCE_PRESSURE * 1e-3)),
+            })
+            .collect(),
+        phases: bins
+            .iter()
+            .map(|bin| latest[*bin].arg().to_degrees())
+            .collect(),
+    }
+}
+
+/// A peak of a [`Spectrum`]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Peak {
+    /// frequency of the peak, interpolated between the bins (in Hz)
+    pub frequency: f32,
+    /// level of the peak bin, scaled like the spectrum
+    pub level: f32,
+}
+
+/// Finds the most prominent peaks of a spectrum, sorted by their frequency.
+///
+/// The prominence of a local maximum is its height above the higher of the two lowest levels
+/// between it and the next higher level on either side. The frequency of a peak is refined by
+/// fitting a parabola through the levels (in dB) of the peak bin and its neighbours.
+/// * `scaling` - The scaling of the levels of the spectrum.
+/// * `count` - The maximum number of peaks.
+/// * `min_prominence` - The minimum prominence of a peak (in dB).
+pub fn find_peaks(
+    spectrum: &Spectrum,
+    scaling: FftScaling,
+    count: usize,
+    min_prominence: f32,
+) -> Vec<Peak> {
+    let levels = match scaling {
+        FftScaling::Decibels => spectrum.levels.clone(),
+        FftScaling::Normalized => spectrum
+            .levels
+            .iter()
+            .map(|level| 20. * level.max(1e-12).log10())
+            .collect(),
+    };
+
+    // the lowest level on one side of a bin until a higher level or the end of the spectrum
+    let lowest_until_higher = |index: usize, bins: &mut dyn Iterator<Item = usize>| {
+        let mut lowest = levels[index];
+        for bin in bins {
+            if levels[bin] > levels[index] {
+                break;
+            }
+            lowest = lowest.min(levels[bin]);
+        }
+        lowest
+    };
+
+    let mut peaks = (1..levels.len().saturating_sub(1))
+        .filter(|index| levels[*index] > levels[index - 1] && levels[*index] >= levels[index + 1])
+        .map(|index| {
+            let left = lowest_until_higher(index, &mut (0..index).rev());
+            let right = lowest_until_higher(index, &mut (index + 1..levels.len()));
+            (index, levels[index] - left.max(right))
+        })
+        .filter(|(_, prominence)| *prominence >= min_prominence)
+        .collect::<Vec<_>>();
+    peaks.sort_by(|a, b| b.1.total_cmp(&a.1));
+    peaks.truncate(count);
+
+    let mut peaks = peaks
+        .into_iter()
+        .map(|(index, _)| {
+            let (before, peak, after) = (levels[index - 1], levels[index], levels[index + 1]);
+            let denominator = before - 2. * peak + after;
+            let offset = if denominator != 0. {
+                (0.5 * (before - after) / denominator).clamp(-0.5, 0.5)
             } else {
-                None
+                0.
+            };
+            // the bins are spaced evenly
+            let bin_width = spectrum.frequencies[index + 1] - spectrum.frequencies[index];
+            Peak {
+                frequency: spectrum.frequencies[index] + offset * bin_width,
+                level: spectrum.levels[index],
             }
         })
         .collect::<Vec<_>>();
+    peaks.sort_by(|a, b| a.frequency.total_cmp(&b.frequency));
+    peaks
+}
+
+/// Calculate the spectrogram of the latest samples of a [`Microphone`] with a short-time FFT.
+///
+/// Each frame is Hann windowed and scaled like the spectrum in [`calc_mic_spectrum`] with
+/// [`FftScaling::Decibels`]. Returns the frames from the oldest to the newest, each with the time
+/// of its center (in s) and the levels (in dB SPL) of the frequency bins from 0 Hz up to the
+/// Nyquist frequency. Samples that are not finite, e.g. of an unstable simulation, are treated
+/// as silence.
+/// * `delta_t` - The time between each sample in the record.
+/// * `window_size` - The number of samples of a frame, has to be a power of two.
+/// * `hop` - The number of samples between the starts of two frames.
+/// * `max_frames` - The maximum number of frames, older frames are omitted.
+pub fn calc_mic_spectrogram(
+    microphone: &Microphone,
+    window_size: usize,
+    hop: usize,
+    max_frames: usize,
+) -> Vec<(f64, Vec<f32>)> {
+    let record = &microphone.record;
+    let hop = hop.max(1);
+    if record.len() < window_size {
+        return vec![];
+    }
+    let frames = ((record.len() - window_size) / hop + 1).min(max_frames);
+    let last_start = record.len() - window_size;
+    let coefficients = SpectralWindow::Hann.coefficients(window_size);
+    let coherent_gain = coefficients.iter().sum::<f32>() / window_size as f32;
+
+    (0..frames)
+        .rev()
+        .map(|frame| {
+            let start = last_start - frame * hop;
+            let mut buffer = record[start..start + window_size]
+                .iter()
+                .zip(&coefficients)
+                .map(|(record, window)| {
+                    let sample = record[1] as f32;
+                    Complex::new(
+                        if sample.is_finite() {
+                            sample * window
+                        } else {
+                            0.
+                        },
+                        0.,
+                    )
+                })
+                .collect::<Vec<_>>();
+            fft(&mut buffer);
+            // the peak amplitude of a sinusoid at a bin is 2 * |X| / (N * coherent gain)
+            let levels = buffer[..=window_size / 2]
+                .iter()
+                .map(|bin| {
+                    let rms = 2. * bin.abs()
+                        / (window_size as f32 * coherent_gain * std::f32::consts::SQRT_2);
+                    pressure_to_db_spl(rms.max(REFERENCE_PRESSURE * 1e-3))
+                })
+                .collect();
+            (record[start + window_size / 2][0], levels)
+        })
+        .collect()
+}
+
+/// A complex number, e.g. a bin of a discrete Fourier transform
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    pub fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    /// Returns the complex number with the given magnitude and phase (in rad).
+    pub fn from_polar(magnitude: f32, phase: f32) -> Self {
+        Self::new(magnitude * phase.cos(), magnitude * phase.sin())
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    /// Returns the squared magnitude.
+    pub fn norm_sqr(self) -> f32 {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn abs(self) -> f32 {
+        self.norm_sqr().sqrt()
+    }
+
+    /// Returns the phase (in rad, between -π and π).
+    pub fn arg(self) -> f32 {
+        self.im.atan2(self.re)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl AddAssign for Complex {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl Mul<f32> for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self {
+        Self::new(self.re * rhs, self.im * rhs)
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        self * rhs.conj() * (1. / rhs.norm_sqr())
+    }
+}
+
+/// Calculates the discrete Fourier transform of a buffer in place with the iterative radix-2
+/// Cooley-Tukey algorithm. The length of the buffer has to be a power of two.
+pub fn fft(buffer: &mut [Complex]) {
+    let len = buffer.len();
+    assert!(
+        len.is_power_of_two(),
+        "the FFT length has to be a power of two"
+    );
+
+    // bit reversal permutation
+    let mut j = 0;
+    for i in 1..len {
+        let mut bit = len >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            buffer.swap(i, j);
+        }
+    }
+
+    // the twiddle factors of all stages are every (len / size)-th one of the last stage, each is
+    // calculated directly so the rounding errors do not add up
+    let twiddles = (0..len / 2)
+        .map(|k| Complex::from_polar(1., -TAU * k as f32 / len as f32))
+        .collect::<Vec<_>>();
+
+    let mut size = 2;
+    while size <= len {
+        let stride = len / size;
+        for start in (0..len).step_by(size) {
+            for k in 0..size / 2 {
+                let even = buffer[start + k];
+                let odd = buffer[start + k + size / 2] * twiddles[k * stride];
+                buffer[start + k] = even + odd;
+                buffer[start + k + size / 2] = even - odd;
+            }
+        }
+        size *= 2;
+    }
+}
+
+/// Transfer function between two signals estimated with Welch averaging
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TransferFunction {
+    /// frequencies of the bins, without 0 Hz and bins without a reference signal (in Hz)
+    pub frequencies: Vec<f32>,
+    /// magnitude of the H1 estimate (in dB)
+    pub magnitude: Vec<f32>,
+    /// unwrapped phase of the H1 estimate (in degrees)
+    pub phase: Vec<f32>,
+    /// group delay of the H1 estimate (in s)
+    pub group_delay: Vec<f32>,
+    /// magnitude squared coherence (from 0 to 1)
+    pub coherence: Vec<f32>,
+    /// number of averaged segments
+    pub averages: usize,
+}
+
+/// Power of the reference relative to its strongest bin below which a bin of a transfer function
+/// is left out (-100 dB)
+const REFERENCE_FLOOR: f32 = 1e-10;
+
+/// Estimates the transfer function from a reference to a measurement signal.
+///
+/// The signals are split into Hann windowed segments overlapping by half, whose auto and cross
+/// spectra are averaged (Welch's method). The H1 estimate is the cross spectrum divided by the
+/// auto spectrum of the reference, bins without a reference signal (below [`REFERENCE_FLOOR`])
+/// are left out. Returns `None`
+/// if the signals are shorter than a segment.
+/// * `sample_rate` - The sample rate of the signals (in Hz).
+/// * `segment_size` - The number of samples of a segment, has to be a power of two.
+pub fn calc_transfer_function(
+    reference: &[f32],
+    measurement: &[f32],
+    sample_rate: f32,
+    segment_size: usize,
+) -> Option<TransferFunction> {
+    let len = reference.len().min(measurement.len());
+    if len < segment_size || segment_size < 4 {
+        return None;
+    }
+
+    let window = SpectralWindow::Hann.coefficients(segment_size);
+    let bins = segment_size / 2 + 1;
+    let mut reference_power = vec![0_f32; bins];
+    let mut measurement_power = vec![0_f32; bins];
+    let mut cross = vec![Complex::default(); bins];
+    let mut averages = 0;
+    let spectrum = |samples: &[f32]| {
+        let mut buffer = samples
+            .iter()
+            .zip(&window)
+            .map(|(sample, window)| Complex::new(sample * window, 0.))
+            .collect::<Vec<_>>();
+        fft(&mut buffer);
+        buffer
+    };
+    for start in (0..=len - segment_size).step_by(segment_size / 2) {
+        let x = spectrum(&reference[start..start + segment_size]);
+        let y = spectrum(&measurement[start..start + segment_size]);
+        for bin in 0..bins {
+            reference_power[bin] += x[bin].norm_sqr();
+            measurement_power[bin] += y[bin].norm_sqr();
+            cross[bin] += x[bin].conj() * y[bin];
+        }
+        averages += 1;
+    }
+
+    let mut result = TransferFunction {
+        averages,
+        ..Default::default()
+    };
+    let mut previous_phase: Option<f32> = None;
+    let mut offset = 0.;
+    // bins far below the strongest bin of the reference only hold leakage and rounding errors
+    let reference_floor = REFERENCE_FLOOR * reference_power.iter().fold(0_f32, |a, b| a.max(*b));
+    for bin in 1..bins {
+        // the transfer function is undefined without a reference signal, these bins are left out
+        // so they do not disturb the phase unwrapping
+        if reference_power[bin] <= reference_floor {
+            continue;
+        }
+        let h1 = cross[bin] / Complex::new(reference_power[bin], 0.);
+        result
+            .frequencies
+            .push(bin as f32 * sample_rate / segment_size as f32);
+        result.magnitude.push(20. * h1.abs().log10());
+
+        // unwrap the phase by removing jumps of more than π between neighbouring bins
+        let phase = h1.arg();
+        if let Some(previous) = previous_phase {
+            let difference = phase + offset - previous;
+            offset -= TAU * (difference / TAU).round();
+        }
+        previous_phase = Some(phase + offset);
+        result.phase.push((phase + offset).to_degrees());
+
+        let power = reference_power[bin] * measurement_power[bin];
+        result.coherence.push(if power > 0. {
+            cross[bin].norm_sqr() / power
+        } else {
+            0.
+        });
+    }
+
+    // group delay from the central difference of the phase, bins may have been left out
+    let (phase, frequencies) = (&result.phase, &result.frequencies);
+    result.group_delay = (0..phase.len())
+        .map(|index| {
+            let before = index.saturating_sub(1);
+            let after = (index + 1).min(phase.len() - 1);
+            if after == before {
+                return 0.;
+            }
+            -(phase[after] - phase[before]).to_radians()
+                / (TAU * (frequencies[after] - frequencies[before]))
+        })
+        .collect();
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::noise::white_noise;
+
+    /// Discrete Fourier transform calculated from its definition
+    fn dft(samples: &[Complex]) -> Vec<Complex> {
+        let len = samples.len();
+        (0..len)
+            .map(|k| {
+                samples
+                    .iter()
+                    .enumerate()
+                    .fold(Complex::default(), |sum, (n, sample)| {
+                        let phase = -std::f64::consts::TAU * ((k * n) % len) as f64 / len as f64;
+                        sum + *sample * Complex::from_polar(1., phase as f32)
+                    })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fft_matches_dft() {
+        for len in [1, 2, 4, 8, 64, 1024] {
+            let samples = (0..len)
+                .map(|n| Complex::new((n as f32 * 0.7).sin(), (n as f32 * 1.3).cos() * 0.5))
+                .collect::<Vec<_>>();
+            let mut buffer = samples.clone();
+            fft(&mut buffer);
+            for (bin, expected) in buffer.iter().zip(dft(&samples)) {
+                assert!(
+                    (*bin - expected).abs() < 1e-3 * len as f32,
+                    "{len}: {bin:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fft_of_known_signals() {
+        // an impulse has a flat spectrum
+        let mut buffer = vec![Complex::default(); 16];
+        buffer[0] = Complex::new(1., 0.);
+        fft(&mut buffer);
+        assert!(buffer
+            .iter()
+            .all(|bin| (*bin - Complex::new(1., 0.)).abs() < 1e-6));
+
+        // a cosine at bin 3 has half of its amplitude times the length at bins 3 and len - 3
+        let len = 4096;
+        let mut buffer = (0..len)
+            .map(|n| Complex::new((TAU * 3. * n as f32 / len as f32).cos(), 0.))
+            .collect::<Vec<_>>();
+        fft(&mut buffer);
+        for (index, bin) in buffer.iter().enumerate() {
+            let expected = if index == 3 || index == len - 3 {
+                len as f32 / 2.
+            } else {
+                0.
+            };
+            assert!((bin.abs() - expected).abs() < 1e-2, "{index}: {bin:?}");
+        }
+    }
+
+    #[test]
+    fn averaged_spectrum_of_growing_record() {
+        let mut mic = Microphone::new(0, 0, 0);
+        let mut growing = WelchSum::default();
+        for index in 0..4000 {
+            mic.record.push([
+                index as f64 / 48_000.,
+                ((index as f32 * 0.2).sin() + (index as f32 * 0.05).cos()) as f64,
+            ]);
+            if index % 700 == 0 {
+                calc_mic_spectrum(
+                    &mic,
+                    FftScaling::Decibels,
+                    1. / 48_000.,
+                    256,
+                    SpectralWindow::Hann,
+                    true,
+                    2,
+                    &mut growing,
+                );
+            }
+        }
+
+        let calc = |welch: &mut WelchSum| {
+            calc_mic_spectrum(
+                &mic,
+                FftScaling::Decibels,
+                1. / 48_000.,
+                256,
+                SpectralWindow::Hann,
+                true,
+                2,
+                welch,
+            )
+        };
+        let expected = calc(&mut WelchSum::default());
+        let spectrum = calc(&mut growing);
+        assert_eq!(spectrum.frequencies, expected.frequencies);
+        for (level, expected) in spectrum.levels.iter().zip(&expected.levels) {
+            assert!((level - expected).abs() < 1e-3, "{level} != {expected}");
+        }
+    }
+
+    #[test]
+    fn spectrogram_of_a_sinusoid() {
+        let sample_rate = 48_000.;
+        let amplitude = 2.;
+        let mut mic = Microphone::new(0, 0, 0);
+        // a sinusoid at bin 16 of a 256 point frame, followed by samples of an unstable run
+        for index in 0..1024 {
+            let time = index as f32 / sample_rate;
+            mic.record
+                .push([time as f64, (amplitude * (TAU * 3000. * time).sin()) as f64]);
+        }
+        mic.record.extend([[0.1, f64::NAN], [0.2, f64::INFINITY]]);
+
+        let frames = calc_mic_spectrogram(&mic, 256, 128, 4);
+
+        assert_eq!(frames.len(), 4);
+        assert!(frames.windows(2).all(|frames| frames[0].0 < frames[1].0));
+        let expected = pressure_to_db_spl(amplitude / std::f32::consts::SQRT_2);
+        let (_, levels) = &frames[0];
+        assert_eq!(levels.len(), 129);
+        assert!((levels[16] - expected).abs() < 0.01, "{}", levels[16]);
+        assert!(frames
+            .iter()
+            .all(|(_, levels)| levels.iter().all(|level| level.is_finite())));
+    }
+
+    #[test]
+    fn peaks_of_two_sinusoids() {
+        let sample_rate = 48_000.;
+        // (frequency in Hz, amplitude in Pa), both between two bins
+        let tones = [(1000., 1.), (5123.4, 0.1)];
+        let mut mic = Microphone::new(0, 0, 0);
+        for index in 0..4096 {
+            let time = index as f32 / sample_rate;
+            let pressure = tones
+                .iter()
+                .map(|(frequency, amplitude)| amplitude * (TAU * frequency * time).sin())
+                .sum::<f32>();
+            mic.record.push([time as f64, pressure as f64]);
+        }
+        let spectrum = calc_mic_spectrum(
+            &mic,
+            FftScaling::Decibels,
+            1. / sample_rate,
+            4096,
+            SpectralWindow::Hann,
+            false,
+            1,
+            &mut WelchSum::default(),
+        );
+        let bin_width = sample_rate / 4096.;
+
+        let peaks = find_peaks(&spectrum, FftScaling::Decibels, 5, 40.);
+
+        assert_eq!(peaks.len(), 2, "{:?}", peaks);
+        for (peak, (frequency, amplitude)) in peaks.iter().zip(tones) {
+            assert!(
+                (peak.frequency - frequency).abs() < 0.1 * bin_width,
+                "peak at {} Hz instead of {} Hz",
+                peak.frequency,
+                frequency
+            );
+            // the level of the peak bin is at most the scalloping loss of the window below
+            let level = pressure_to_db_spl(amplitude / std::f32::consts::SQRT_2);
+            assert!(
+                peak.level <= level + 0.01 && peak.level > level - 1.5,
+                "peak level is {} dB instead of {} dB",
+                peak.level,
+                level
+            );
+        }
+
+        // the count keeps only the most prominent peaks
+        let most_prominent = find_peaks(&spectrum, FftScaling::Decibels, 1, 40.);
+        assert_eq!(most_prominent.len(), 1);
+        assert!(peaks.contains(&most_prominent[0]));
+        assert!(find_peaks(&spectrum, FftScaling::Decibels, 5, 200.).is_empty());
+    }
+
+    #[test]
+    fn transfer_function_skips_bins_without_reference() {
+        let measurement = (0..1024)
+            .map(|index| (index as f32 * 0.3).sin())
+            .collect::<Vec<_>>();
+        // a cosine at a quarter of the sample rate
+        let reference = (0..1024)
+            .map(|index| match index % 4 {
+                0 => 1.,
+                2 => -1.,
+                _ => 0.,
+            })
+            .collect::<Vec<_>>();
+
+        let silent = calc_transfer_function(&vec![0.; 1024], &measurement, 48_000., 256).unwrap();
+        assert!(silent.frequencies.is_empty() && silent.phase.is_empty());
+
+        let transfer_function =
+            calc_transfer_function(&reference, &measurement, 48_000., 256).unwrap();
+        // only the bin of the cosine and its neighbours within the main lobe of the window are
+        // above the leakage of the reference
+        assert_eq!(transfer_function.frequencies, [11_812.5, 12_000., 12_187.5]);
+        for values in [
+            &transfer_function.magnitude,
+            &transfer_function.phase,
+            &transfer_function.group_delay,
+            &transfer_function.coherence,
+        ] {
+            assert_eq!(values.len(), transfer_function.frequencies.len());
+            assert!(values.iter().all(|value| !value.is_nan()));
+        }
+    }
+
+    #[test]
+    fn transfer_function_of_a_delayed_and_scaled_copy() {
+        let sample_rate = 48_000.;
+        let (gain, delay) = (0.5, 12);
+        let reference = (0..16_384)
+            .map(|sample| white_noise(5, sample))
+            .collect::<Vec<_>>();
+        let measurement = (0..reference.len())
+            .map(|index| {
+                index
+                    .checked_sub(delay)
+                    .map_or(0., |index| gain * reference[index])
+            })
+            .collect::<Vec<_>>();
+
+        let transfer_function =
+            calc_transfer_function(&reference, &measurement, sample_rate, 1024).unwrap();
 
-    mapped_spectrum
+        assert_eq!(transfer_function.averages, 31);
+        assert_eq!(transfer_function.frequencies.len(), 512);
+        let delay_time = delay as f32 / sample_rate;
+        // the estimates of single bins scatter with the averaged noise, their mean does not
+        for (index, frequency) in transfer_function.frequencies.iter().enumerate() {
+            let magnitude = transfer_function.magnitude[index];
+            assert!(
+                (magnitude - 20. * gain.log10()).abs() < 0.25,
+                "magnitude at {} Hz is {} dB",
+                frequency,
+                magnitude
+            );
+            // the unwrapped phase falls linearly with the frequency
+            let phase = transfer_function.phase[index];
+            assert!(
+                (phase + 360. * frequency * delay_time).abs() < 2.5,
+                "phase at {} Hz is {}°",
+                frequency,
+                phase
+            );
+            let group_delay = transfer_function.group_delay[index];
+            assert!(
+                (group_delay - delay_time).abs() < 0.5 * delay_time,
+                "group delay at {} Hz is {} s",
+                frequency,
+                group_delay
+            );
+            let coherence = transfer_function.coherence[index];
+            assert!(
+                coherence > 0.95,
+                "coherence at {} Hz is {}",
+                frequency,
+                coherence
+            );
+        }
+        let mean_group_delay = transfer_function.group_delay.iter().sum::<f32>()
+            / transfer_function.group_delay.len() as f32;
+        assert!(
+            (mean_group_delay - delay_time).abs() < 0.01 * delay_time,
+            "mean group delay is {} s",
+            mean_group_delay
+        );
+    }
 }
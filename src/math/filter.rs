@@ -0,0 +1,139 @@
+use std::f32::consts::PI;
+
+/// The normalized coefficients of a second order IIR filter.
+///
+/// The coefficients follow the "Audio EQ Cookbook" by Robert Bristow-Johnson.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Biquad {
+    /// Second order low-pass filter with the cutoff `frequency` (in Hz).
+    /// * `q` - The quality factor of the filter, `1/√2` results in a Butterworth filter.
+    /// * `sample_rate` - The sample rate of the filtered signal (in Hz).
+    pub fn low_pass(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let (cos, alpha) = Self::intermediates(frequency, q, sample_rate);
+        Self::normalized(
+            (1. - cos) / 2.,
+            1. - cos,
+            (1. - cos) / 2.,
+            1. + alpha,
+            -2. * cos,
+            1. - alpha,
+        )
+    }
+
+    /// Second order high-pass filter with the cutoff `frequency` (in Hz).
+    /// * `q` - The quality factor of the filter, `1/√2` results in a Butterworth filter.
+    /// * `sample_rate` - The sample rate of the filtered signal (in Hz).
+    pub fn high_pass(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let (cos, alpha) = Self::intermediates(frequency, q, sample_rate);
+        Self::normalized(
+            (1. + cos) / 2.,
+            -(1. + cos),
+            (1. + cos) / 2.,
+            1. + alpha,
+            -2. * cos,
+            1. - alpha,
+        )
+    }
+
+    /// Second order band-pass filter with a gain of 0 dB at the center `frequency` (in Hz).
+    /// * `q` - The quality factor of the filter, the ratio of the center frequency to the bandwidth.
+    /// * `sample_rate` - The sample rate of the filtered signal (in Hz).
+    pub fn band_pass(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let (cos, alpha) = Self::intermediates(frequency, q, sample_rate);
+        Self::normalized(alpha, 0., -alpha, 1. + alpha, -2. * cos, 1. - alpha)
+    }
+
+    /// Returns the cosine of the normalized angular frequency and the bandwidth term alpha.
+    /// The frequency is limited to just below the nyquist frequency.
+    fn intermediates(frequency: f32, q: f32, sample_rate: f32) -> (f32, f32) {
+        let frequency = frequency.clamp(1., 0.49 * sample_rate);
+        let omega = 2. * PI * frequency / sample_rate;
+        (omega.cos(), omega.sin() / (2. * q.max(0.01)))
+    }
+
+    fn normalized(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+}
+
+/// The delayed samples of a [`Biquad`] in direct form I.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BiquadState {
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl BiquadState {
+    /// Filters the next sample with the given coefficients.
+    pub fn process(&mut self, biquad: &Biquad, x: f32) -> f32 {
+        let y = biquad.b0 * x + biquad.b1 * self.x1 + biquad.b2 * self.x2
+            - biquad.a1 * self.y1
+            - biquad.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_1_SQRT_2, SQRT_2, TAU};
+
+    use super::*;
+
+    /// Returns the gain (in dB) of a filter for a sinusoid, measured after the filter settled.
+    fn gain_at(biquad: &Biquad, frequency: f32, sample_rate: f32) -> f32 {
+        let mut state = BiquadState::default();
+        let output = (0..sample_rate as usize)
+            .map(|index| {
+                state.process(biquad, (TAU * frequency * index as f32 / sample_rate).sin())
+            })
+            .collect::<Vec<_>>();
+        // the second half holds whole periods of the settled output
+        let settled = &output[output.len() / 2..];
+        let mean_square = settled.iter().map(|y| y * y).sum::<f32>() / settled.len() as f32;
+        20. * (mean_square.sqrt() * SQRT_2).log10()
+    }
+
+    #[test]
+    fn low_pass_is_3_db_down_at_the_cutoff() {
+        let sample_rate = 48_000.;
+        let low_pass = Biquad::low_pass(1000., FRAC_1_SQRT_2, sample_rate);
+
+        let cutoff = gain_at(&low_pass, 1000., sample_rate);
+        assert!(
+            (cutoff + 3.01).abs() < 0.1,
+            "gain at the cutoff is {} dB",
+            cutoff
+        );
+        let pass_band = gain_at(&low_pass, 100., sample_rate);
+        assert!(pass_band.abs() < 0.1, "gain at 100 Hz is {} dB", pass_band);
+    }
+
+    #[test]
+    fn high_pass_removes_dc() {
+        let high_pass = Biquad::high_pass(100., FRAC_1_SQRT_2, 48_000.);
+        let mut state = BiquadState::default();
+
+        let last = (0..48_000).fold(1., |_, _| state.process(&high_pass, 1.));
+        assert!(last.abs() < 1e-4, "output after 1 s is {}", last);
+    }
+}
@@ -1,4 +1,12 @@
+pub mod bands;
+pub mod calibration;
 pub mod constants;
+pub mod expression;
 pub mod fft;
+pub mod filter;
+pub mod noise;
 pub mod rect;
+pub mod resample;
+pub mod room_acoustics;
 pub mod transformations;
+pub mod window;
@@ -0,0 +1,54 @@
+use std::f64::consts::TAU;
+
+/// Calculates a normal distributed value with a mean of 0 and a standard deviation of 1
+/// for the given sample of a noise signal.
+///
+/// The value only depends on `seed` and `sample`, so delayed copies of a noise signal are the same
+/// noise. Both are hashed with SplitMix64 and transformed with the Box-Muller transform.
+pub fn white_noise(seed: u64, sample: usize) -> f32 {
+    let first = split_mix_64(seed.rotate_left(32) ^ sample as u64);
+    let second = split_mix_64(first);
+
+    // uniform values in (0, 1] and [0, 1) from the upper 53 bits
+    let radius = ((first >> 11) as f64 + 1.) / (1u64 << 53) as f64;
+    let angle = (second >> 11) as f64 / (1u64 << 53) as f64;
+
+    ((-2. * radius.ln()).sqrt() * (TAU * angle).cos()) as f32
+}
+
+/// Finalizer of the SplitMix64 generator, maps every input to a well mixed output
+fn split_mix_64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn white_noise_is_deterministic() {
+        assert_eq!(white_noise(3, 1000), white_noise(3, 1000));
+        assert_ne!(white_noise(3, 1000), white_noise(3, 1001));
+        assert_ne!(white_noise(3, 1000), white_noise(4, 1000));
+    }
+
+    #[test]
+    fn white_noise_is_standard_normal() {
+        let samples = (0..100_000)
+            .map(|sample| white_noise(7, sample) as f64)
+            .collect::<Vec<_>>();
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let variance =
+            samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+
+        assert!(mean.abs() < 0.02, "mean is {}", mean);
+        assert!((variance - 1.).abs() < 0.02, "variance is {}", variance);
+        // neighbouring samples are uncorrelated
+        let correlation =
+            samples.windows(2).map(|x| x[0] * x[1]).sum::<f64>() / samples.len() as f64 / variance;
+        assert!(correlation.abs() < 0.02, "correlation is {}", correlation);
+    }
+}
@@ -0,0 +1,59 @@
+use std::f32::consts::PI;
+
+/// Number of zero crossings of the sinc kernel on each side of the interpolated sample
+const KERNEL_ZERO_CROSSINGS: f32 = 16.;
+
+/// Resamples a signal from one sample rate to another using windowed sinc interpolation.
+///
+/// The cutoff of the interpolation kernel is placed at the lower of the two nyquist frequencies,
+/// so downsampling does not introduce aliasing.
+/// * `samples` - The samples of the signal.
+/// * `from_rate` - The sample rate of `samples` (in Hz).
+/// * `to_rate` - The sample rate of the returned signal (in Hz).
+pub fn resample(samples: &[f32], from_rate: f32, to_rate: f32) -> Vec<f32> {
+    if samples.is_empty() || from_rate <= 0. || to_rate <= 0. {
+        return vec![];
+    }
+    if from_rate == to_rate {
+        return samples.to_vec();
+    }
+
+    let ratio = to_rate / from_rate;
+    // cutoff relative to the input nyquist frequency
+    let cutoff = ratio.min(1.);
+    let half_width = KERNEL_ZERO_CROSSINGS / cutoff;
+    let output_len = (samples.len() as f32 * ratio).round() as usize;
+
+    (0..output_len)
+        .map(|n| {
+            let position = n as f32 / ratio;
+            let first = (position - half_width).ceil().max(0.) as usize;
+            let last = ((position + half_width).floor() as usize).min(samples.len() - 1);
+
+            let mut value = 0.;
+            for (k, sample) in samples.iter().enumerate().take(last + 1).skip(first) {
+                let x = k as f32 - position;
+                value += sample * cutoff * sinc(cutoff * x) * hann(x / half_width);
+            }
+            value
+        })
+        .collect()
+}
+
+/// Normalized sinc function
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        1.
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Hann window on the interval [-1, 1]
+fn hann(x: f32) -> f32 {
+    if x.abs() >= 1. {
+        0.
+    } else {
+        0.5 * (1. + (PI * x).cos())
+    }
+}
@@ -0,0 +1,219 @@
+/// Length of the windows the energy at the start and the end of an impulse response is averaged
+/// over to find the level at which it is truncated (in s)
+const ENERGY_WINDOW: f32 = 0.01;
+
+/// Distance the end of the evaluation range of a reverberation time has to keep to the level at
+/// which the impulse response is truncated (in dB)
+const TRUNCATION_HEADROOM: f32 = 10.;
+
+/// Room acoustic parameters of an impulse response according to ISO 3382.
+///
+/// Parameters that cannot be evaluated, e.g. because the decay does not reach the evaluation
+/// range, are `None`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RoomParameters {
+    /// reverberation time from the decay between -5 dB and -25 dB (in s)
+    pub t20: Option<f32>,
+    /// reverberation time from the decay between -5 dB and -35 dB (in s)
+    pub t30: Option<f32>,
+    /// early decay time from the decay between 0 dB and -10 dB (in s)
+    pub edt: Option<f32>,
+    /// clarity of speech, energy ratio of the first 50 ms to the rest (in dB)
+    pub c50: Option<f32>,
+    /// clarity of music, energy ratio of the first 80 ms to the rest (in dB)
+    pub c80: Option<f32>,
+    /// definition, share of the energy in the first 50 ms (from 0 to 1)
+    pub d50: Option<f32>,
+    /// centre time, center of gravity of the squared impulse response (in s)
+    pub centre_time: Option<f32>,
+}
+
+impl RoomParameters {
+    /// Calculates the parameters of an impulse response (in Pa).
+    ///
+    /// The impulse response starts with the direct sound, which is found as the first sample
+    /// less than 20 dB below the maximum.
+    pub fn calc(impulse_response: &[f32], sample_rate: f32) -> Self {
+        let Some(onset) = onset(impulse_response) else {
+            return Self::default();
+        };
+        let impulse_response = &impulse_response[onset..];
+        let decay = schroeder_decay(impulse_response);
+        let energy = impulse_response.iter().map(|p| p * p).collect::<Vec<_>>();
+        let total = energy.iter().sum::<f32>();
+        let truncation = truncation_level(&energy, sample_rate);
+        let decay_time = |start: f32, end: f32| {
+            truncation
+                .filter(|truncation| end >= truncation + TRUNCATION_HEADROOM)
+                .and_then(|_| reverberation_time(&decay, start, end, sample_rate))
+        };
+
+        let early = |duration: f32| {
+            let samples = ((duration * sample_rate).round() as usize).min(energy.len());
+            // the impulse response has to be longer than the early part
+            (samples < energy.len()).then(|| energy[..samples].iter().sum::<f32>())
+        };
+        let clarity = |duration: f32| {
+            early(duration)
+                .filter(|early| total > *early)
+                .map(|early| 10. * (early / (total - early)).log10())
+        };
+
+        Self {
+            t20: decay_time(-5., -25.),
+            t30: decay_time(-5., -35.),
+            edt: decay_time(0., -10.),
+            c50: clarity(0.05),
+            c80: clarity(0.08),
+            d50: early(0.05).map(|early| early / total),
+            centre_time: Some(
+                energy
+                    .iter()
+                    .enumerate()
+                    .map(|(index, energy)| index as f32 / sample_rate * energy)
+                    .sum::<f32>()
+                    / total,
+            ),
+        }
+    }
+}
+
+/// Calculates the decay curve of an impulse response by Schroeder backward integration
+/// (in dB relative to the total energy). The curve is `-inf` after the last non-zero sample.
+pub fn schroeder_decay(impulse_response: &[f32]) -> Vec<f32> {
+    let mut remaining = impulse_response
+        .iter()
+        .rev()
+        .scan(0_f64, |sum, p| {
+            *sum += (*p as f64).powi(2);
+            Some(*sum)
+        })
+        .collect::<Vec<_>>();
+    remaining.reverse();
+
+    let total = remaining.first().copied().unwrap_or(0.);
+    remaining
+        .iter()
+        .map(|energy| (10. * (energy / total).log10()) as f32)
+        .collect()
+}
+
+/// Returns the index of the direct sound of an impulse response, the first sample less than
+/// 20 dB below the maximum.
+pub fn onset(impulse_response: &[f32]) -> Option<usize> {
+    let max = impulse_response
+        .iter()
+        .fold(0_f32, |max, p| max.max(p.abs()));
+    if max <= 0. {
+        return None;
+    }
+    impulse_response.iter().position(|p| p.abs() >= max / 10.)
+}
+
+/// Returns the level at which an impulse response is truncated (in dB), the mean energy at its
+/// end relative to the mean energy at its start.
+///
+/// The Schroeder decay of a truncated impulse response falls off towards the end, so a
+/// reverberation time is only evaluated if its range ends [`TRUNCATION_HEADROOM`] above this level
+/// (ISO 3382-1). The level is `-inf` if the impulse response decayed completely.
+/// * `energy` - The squared impulse response, starting with the direct sound.
+fn truncation_level(energy: &[f32], sample_rate: f32) -> Option<f32> {
+    let window = ((ENERGY_WINDOW * sample_rate).round() as usize).max(1);
+    if energy.len() < 2 * window {
+        return None;
+    }
+
+    let start = energy[..window].iter().sum::<f32>();
+    let end = energy[energy.len() - window..].iter().sum::<f32>();
+    (start > 0.).then(|| 10. * (end / start).log10())
+}
+
+/// Fits a line into the decay curve between `start` and `end` (in dB) and extrapolates it to
+/// a decay of 60 dB (in s).
+fn reverberation_time(decay: &[f32], start: f32, end: f32, sample_rate: f32) -> Option<f32> {
+    let first = decay.iter().position(|level| *level <= start)?;
+    let last = decay.iter().position(|level| *level <= end)?;
+    if last <= first + 1 {
+        return None;
+    }
+
+    // least squares fit of the level over the time
+    let count = (last - first) as f32;
+    let (sum_t, sum_l, sum_tt, sum_tl) = decay[first..last].iter().enumerate().fold(
+        (0., 0., 0., 0.),
+        |(sum_t, sum_l, sum_tt, sum_tl), (index, level)| {
+            let time = index as f32 / sample_rate;
+            (
+                sum_t + time,
+                sum_l + level,
+                sum_tt + time * time,
+                sum_tl + time * level,
+            )
+        },
+    );
+    let slope = (count * sum_tl - sum_t * sum_l) / (count * sum_tt - sum_t * sum_t);
+    (slope < 0.).then(|| -60. / slope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::noise::white_noise;
+
+    const SAMPLE_RATE: f32 = 48_000.;
+
+    /// White noise that decays exponentially with the given reverberation time (in s)
+    fn exponential_decay(reverberation_time: f32, duration: f32) -> Vec<f32> {
+        // the pressure decays by 60 dB (a factor of 1000) within the reverberation time
+        let decay_rate = 3. * 10_f32.ln() / reverberation_time;
+        (0..(duration * SAMPLE_RATE) as usize)
+            .map(|n| white_noise(1, n) * (-decay_rate * n as f32 / SAMPLE_RATE).exp())
+            .collect()
+    }
+
+    fn assert_close(value: Option<f32>, expected: f32, tolerance: f32) {
+        let value = value.expect("parameter is not evaluated");
+        assert!(
+            (value - expected).abs() <= tolerance,
+            "{} is not within {} of {}",
+            value,
+            tolerance,
+            expected
+        );
+    }
+
+    #[test]
+    fn reverberation_times_of_an_exponential_decay() {
+        let parameters = RoomParameters::calc(&exponential_decay(0.5, 1.), SAMPLE_RATE);
+
+        assert_close(parameters.t20, 0.5, 0.025);
+        assert_close(parameters.t30, 0.5, 0.025);
+        assert_close(parameters.edt, 0.5, 0.05);
+    }
+
+    #[test]
+    fn truncated_decay_is_not_evaluated() {
+        // the record is cut off 30 dB below the direct sound
+        let parameters = RoomParameters::calc(&exponential_decay(0.5, 0.25), SAMPLE_RATE);
+
+        assert_eq!(parameters.t20, None);
+        assert_eq!(parameters.t30, None);
+        assert_close(parameters.edt, 0.5, 0.05);
+    }
+
+    #[test]
+    fn clarity_splits_the_energy_at_50_and_80_ms() {
+        // reflections with an energy of 1 at 0 ms, 30 ms and 65 ms and 2 at 100 ms
+        let mut impulse_response = vec![0.; (0.2 * SAMPLE_RATE) as usize];
+        for (time, pressure) in [(0., 1.), (0.03, 1.), (0.065, 1.), (0.1, 2_f32.sqrt())] {
+            impulse_response[(time * SAMPLE_RATE).round() as usize] = pressure;
+        }
+
+        let parameters = RoomParameters::calc(&impulse_response, SAMPLE_RATE);
+
+        assert_close(parameters.c50, 10. * (2_f32 / 3.).log10(), 1e-4);
+        assert_close(parameters.c80, 10. * (3_f32 / 2.).log10(), 1e-4);
+        assert_close(parameters.d50, 0.4, 1e-6);
+        assert_close(parameters.centre_time, 0.059, 1e-5);
+    }
+}
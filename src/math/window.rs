@@ -0,0 +1,183 @@
+use std::f32::consts::{PI, TAU};
+use std::fmt;
+
+/// Calculates a Hann window of the given length without its zero valued end points,
+/// so that every element of the window contributes.
+pub fn hann(len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|n| (PI * (n + 1) as f32 / (len + 1) as f32).sin().powi(2))
+        .collect()
+}
+
+/// Calculates a Dolph-Chebyshev window of the given length, normalized to a maximum of 1.
+///
+/// All side lobes of the window are `attenuation` dB below the main lobe.
+pub fn chebyshev(len: usize, attenuation: f32) -> Vec<f32> {
+    if len < 2 {
+        return vec![1.; len];
+    }
+
+    let order = (len - 1) as f32;
+    let beta = ((10_f32.powf(attenuation.abs() / 20.)).acosh() / order).cosh();
+
+    // frequency response of the window, sampled at `len` points
+    let response = (0..len)
+        .map(|k| {
+            let x = beta * (PI * k as f32 / len as f32).cos();
+            if x > 1. {
+                (order * x.acosh()).cosh()
+            } else if x < -1. {
+                let sign = if len % 2 == 1 { 1. } else { -1. };
+                sign * (order * (-x).acosh()).cosh()
+            } else {
+                (order * x.acos()).cos()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // real part of the dft of the response (with a half sample shift for even lengths)
+    let shift = if len % 2 == 1 { 0. } else { PI / len as f32 };
+    let dft = |n: usize| {
+        response
+            .iter()
+            .enumerate()
+            .map(|(k, value)| {
+                value * (shift * k as f32 - 2. * PI * (k * n) as f32 / len as f32).cos()
+            })
+            .sum::<f32>()
+    };
+
+    let half = if len % 2 == 1 {
+        len.div_ceil(2)
+    } else {
+        len / 2 + 1
+    };
+    let values = (0..half).map(dft).collect::<Vec<_>>();
+
+    // mirror the values around the center of the window
+    let mut window = values[1..].iter().rev().copied().collect::<Vec<_>>();
+    if len % 2 == 1 {
+        window.extend_from_slice(&values);
+    } else {
+        window.extend_from_slice(&values[1..]);
+    }
+
+    let max = window.iter().copied().fold(f32::MIN, f32::max);
+    window.iter().map(|value| value / max).collect()
+}
+
+/// A window for the spectrum analysis of a block of samples
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum SpectralWindow {
+    Rectangular,
+    #[default]
+    Hann,
+    Hamming,
+    /// four term Blackman-Harris window with side lobes 92 dB below the main lobe
+    BlackmanHarris,
+    /// flat top window for accurate amplitudes of sinusoids between the bins
+    FlatTop,
+}
+
+impl SpectralWindow {
+    /// Calculates the periodic window of the given length.
+    pub fn coefficients(&self, len: usize) -> Vec<f32> {
+        let terms: &[f32] = match self {
+            SpectralWindow::Rectangular => &[1.],
+            SpectralWindow::Hann => &[0.5, 0.5],
+            SpectralWindow::Hamming => &[0.54, 0.46],
+            SpectralWindow::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+            SpectralWindow::FlatTop => {
+                &[0.21557895, 0.41663158, 0.27726316, 0.083578947, 0.006947368]
+            }
+        };
+
+        // sum of cosines with alternating signs
+        (0..len)
+            .map(|n| {
+                terms
+                    .iter()
+                    .enumerate()
+                    .map(|(k, a)| {
+                        let sign = if k % 2 == 0 { 1. } else { -1. };
+                        sign * a * (TAU * (k * n) as f32 / len as f32).cos()
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for SpectralWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpectralWindow::Rectangular => write!(f, "Rectangular"),
+            SpectralWindow::Hann => write!(f, "Hann"),
+            SpectralWindow::Hamming => write!(f, "Hamming"),
+            SpectralWindow::BlackmanHarris => write!(f, "Blackman-Harris"),
+            SpectralWindow::FlatTop => write!(f, "Flat top"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Magnitude response (in dB relative to the maximum) of a window, zero padded to `len` points
+    fn response_db(window: &[f32], len: usize) -> Vec<f32> {
+        let magnitudes = (0..len / 2)
+            .map(|k| {
+                let (re, im) = window
+                    .iter()
+                    .enumerate()
+                    .fold((0., 0.), |(re, im), (n, value)| {
+                        let angle = TAU * (k * n) as f32 / len as f32;
+                        (re + value * angle.cos(), im - value * angle.sin())
+                    });
+                f32::hypot(re, im)
+            })
+            .collect::<Vec<_>>();
+        let max = magnitudes.iter().copied().fold(0., f32::max);
+        magnitudes
+            .iter()
+            .map(|magnitude| 20. * (magnitude / max).log10())
+            .collect()
+    }
+
+    #[test]
+    fn chebyshev_is_symmetric() {
+        for len in [2, 7, 16, 31] {
+            let window = chebyshev(len, 60.);
+            assert_eq!(window.len(), len);
+            for n in 0..len {
+                assert!((window[n] - window[len - 1 - n]).abs() < 1e-5);
+            }
+            assert!((window.iter().copied().fold(0., f32::max) - 1.).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn chebyshev_side_lobes_have_the_attenuation() {
+        for (len, attenuation) in [(16, 40.), (31, 60.), (32, 60.)] {
+            let response = response_db(&chebyshev(len, attenuation), 4096);
+            // the main lobe ends at the first minimum of the response
+            let main_lobe_end = response
+                .windows(2)
+                .position(|values| values[1] > values[0])
+                .unwrap();
+            let side_lobes = response[main_lobe_end..]
+                .iter()
+                .copied()
+                .fold(f32::MIN, f32::max);
+
+            assert!(
+                (side_lobes + attenuation).abs() < 0.5,
+                "side lobes of a window of length {} are at {} dB instead of -{} dB",
+                len,
+                side_lobes,
+                attenuation
+            );
+        }
+    }
+}
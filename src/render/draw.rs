@@ -10,14 +10,18 @@ use crate::components::states::Move;
 use crate::components::wall::{CircWall, RectWall, WResize, Wall};
 use crate::math::constants::{SIMULATION_HEIGHT, SIMULATION_WIDTH};
 use crate::math::transformations::{coords_to_index, map_range};
+use crate::simulation::frequency_maps::FrequencyMaps;
 use crate::simulation::grid::Grid;
-use crate::ui::state::UiState;
+use crate::simulation::levels::PressureLevels;
+use crate::ui::state::{RenderMode, UiState};
 
 pub fn draw_pixels(
     mut pixel_buffer: QueryPixelBuffer,
     grid: Res<Grid>,
     gradient: Res<Gradient>,
     ui_state: Res<UiState>,
+    levels: Res<PressureLevels>,
+    frequency_maps: Res<FrequencyMaps>,
 ) {
     let abc_boundary_width = if ui_state.render_abc_area {
         0
@@ -52,14 +56,55 @@ pub fn draw_pixels(
             };
         }
 
-        let p = grid.pressure[current_index];
-
-        let [r, g, b] = gradient.at(p, ui_state.min_gradient, ui_state.max_gradient);
+        let [r, g, b] = cell_color(
+            current_index,
+            &grid,
+            &levels,
+            &frequency_maps,
+            &ui_state,
+            &gradient,
+        );
 
         Pixel { r, g, b, a: 255 }
     });
 }
 
+/// Returns the color of a cell that is not a wall in the current render mode.
+pub fn cell_color(
+    index: usize,
+    grid: &Grid,
+    levels: &PressureLevels,
+    frequency_maps: &FrequencyMaps,
+    ui_state: &UiState,
+    gradient: &Gradient,
+) -> [u8; 3] {
+    let (value, min, max) = match ui_state.render_mode {
+        RenderMode::Pressure => (
+            grid.pressure[index],
+            ui_state.min_gradient,
+            ui_state.max_gradient,
+        ),
+        RenderMode::RmsLevel => (
+            levels.rms_level(index),
+            ui_state.min_level_gradient,
+            ui_state.max_level_gradient,
+        ),
+        RenderMode::PeakLevel => (
+            levels.peak_level(index),
+            ui_state.min_level_gradient,
+            ui_state.max_level_gradient,
+        ),
+        RenderMode::Magnitude => (
+            frequency_maps.magnitude_level(index),
+            ui_state.min_level_gradient,
+            ui_state.max_level_gradient,
+        ),
+        RenderMode::Phase => (frequency_maps.phase(index), -180., 180.),
+    };
+
+    gradient.at(value, min, max)
+}
+
 type RectWallsResizeOrMove<'w, 's> =
     Query<'w, 's, &'static RectWall, Or<(With<WResize>, With<Move>)>>;
 type CircWallsResizeOrMove<'w, 's> =
@@ -1,16 +1,21 @@
 use bevy::ecs::system::Commands;
 use bevy_file_dialog::FileDialogExt;
 
+use super::draw::cell_color;
 use super::gradient::Gradient;
 use crate::math::constants::{SIMULATION_HEIGHT, SIMULATION_WIDTH};
 use crate::math::transformations::coords_to_index;
+use crate::simulation::frequency_maps::FrequencyMaps;
 use crate::simulation::grid::Grid;
+use crate::simulation::levels::PressureLevels;
 use crate::ui::loading::SceneSaveFileContents;
 use crate::ui::state::UiState;
 
 pub fn screenshot_grid(
     ui_state: &UiState,
     grid: &Grid,
+    levels: &PressureLevels,
+    frequency_maps: &FrequencyMaps,
     gradient: &Gradient,
     commands: &mut Commands,
 ) {
@@ -28,9 +33,14 @@ pub fn screenshot_grid(
                 pixels.push((reflection_factor * 255.) as u8);
                 pixels.push((reflection_factor * 255.) as u8);
             } else {
-                let pressure = grid.pressure[current_index];
-
-                let [r, g, b] = gradient.at(pressure, ui_state.min_gradient, ui_state.max_gradient);
+                let [r, g, b] = cell_color(
+                    current_index,
+                    grid,
+                    levels,
+                    frequency_maps,
+                    ui_state,
+                    gradient,
+                );
 
                 // inverse gamma correction to match the brightness/contrast of the simulation
                 pixels.push(((r as f32 / 255.).powf(1. / 2.2) * 255.) as u8);
@@ -0,0 +1,162 @@
+use std::f64::consts::TAU;
+
+use bevy::prelude::*;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
+
+use crate::math::calibration::pressure_to_db_spl;
+use crate::math::constants::REFERENCE_PRESSURE;
+use crate::math::fft::Complex;
+
+/// The sound pressure of every cell of the grid at single frequencies, calculated with a running
+/// discrete Fourier transform. Shows the steady state interference patterns and room modes of
+/// all frequencies of a broadband excitation at once.
+#[derive(Resource, Debug)]
+pub struct FrequencyMaps {
+    /// frequencies of the maps (in Hz)
+    pub frequencies: Vec<f32>,
+    /// index of the frequency that is shown
+    pub selected: usize,
+    /// sum of the pressures multiplied with the complex exponential per frequency and cell
+    sums: Vec<Vec<Complex>>,
+    /// number of accumulated samples per frequency
+    samples: Vec<usize>,
+}
+
+impl Default for FrequencyMaps {
+    fn default() -> Self {
+        Self {
+            frequencies: vec![1000.],
+            selected: 0,
+            sums: vec![],
+            samples: vec![],
+        }
+    }
+}
+
+impl FrequencyMaps {
+    /// Adds the current sound pressure of all cells (in Pa) to the transforms. A map starts over
+    /// if the size of the grid changed.
+    /// * `sample` - The number of time steps since the start of the simulation.
+    /// * `delta_t` - The duration of a time step (in s).
+    pub fn accumulate(&mut self, pressure: &[f32], sample: usize, delta_t: f32) {
+        self.sums.resize(self.frequencies.len(), vec![]);
+        self.samples.resize(self.frequencies.len(), 0);
+
+        for ((frequency, sums), samples) in self
+            .frequencies
+            .iter()
+            .zip(self.sums.iter_mut())
+            .zip(self.samples.iter_mut())
+        {
+            if sums.len() != pressure.len() {
+                *sums = vec![Complex::default(); pressure.len()];
+                *samples = 0;
+            }
+
+            // only the fraction of the periods is kept, so the phase stays exact for long runs
+            let periods = (*frequency as f64 * delta_t as f64 * sample as f64).fract();
+            let phasor = Complex::from_polar(1., (-TAU * periods) as f32);
+            sums.par_iter_mut().enumerate().for_each(|(index, sum)| {
+                *sum += phasor * pressure[index];
+            });
+            *samples += 1;
+        }
+    }
+
+    /// Adds a map at the given frequency (in Hz) and shows it.
+    pub fn add(&mut self, frequency: f32) {
+        self.frequencies.push(frequency);
+        self.selected = self.frequencies.len() - 1;
+    }
+
+    /// Removes the map that is shown.
+    pub fn remove_selected(&mut self) {
+        let selected = self.selected;
+        self.frequencies.remove(selected);
+        if selected < self.sums.len() {
+            self.sums.remove(selected);
+            self.samples.remove(selected);
+        }
+        self.selected = selected.min(self.frequencies.len().saturating_sub(1));
+    }
+
+    /// Starts the map at the given index over, e.g. after its frequency was changed.
+    pub fn reset_map(&mut self, index: usize) {
+        if let (Some(sums), Some(samples)) = (self.sums.get_mut(index), self.samples.get_mut(index))
+        {
+            sums.clear();
+            *samples = 0;
+        }
+    }
+
+    /// Returns the complex amplitude of a cell at the selected frequency (in Pa).
+    fn amplitude(&self, index: usize) -> Complex {
+        match self
+            .sums
+            .get(self.selected)
+            .and_then(|sums| sums.get(index))
+        {
+            // a sinusoid with the amplitude A results in a sum of A * N / 2
+            Some(sum) if self.samples() > 0 => *sum * (2. / self.samples() as f32),
+            _ => Complex::default(),
+        }
+    }
+
+    /// Returns the RMS level of a cell at the selected frequency (in dB SPL).
+    pub fn magnitude_level(&self, index: usize) -> f32 {
+        let rms = self.amplitude(index).abs() / std::f32::consts::SQRT_2;
+        pressure_to_db_spl(rms.max(REFERENCE_PRESSURE * 1e-3))
+    }
+
+    /// Returns the phase of a cell at the selected frequency relative to a cosine starting at
+    /// the start of the simulation (in degrees, between -180° and 180°).
+    pub fn phase(&self, index: usize) -> f32 {
+        self.amplitude(index).arg().to_degrees()
+    }
+
+    /// Returns the selected frequency (in Hz).
+    pub fn selected_frequency(&self) -> Option<f32> {
+        self.frequencies.get(self.selected).copied()
+    }
+
+    /// Returns the number of accumulated samples of the selected frequency.
+    pub fn samples(&self) -> usize {
+        self.samples.get(self.selected).copied().unwrap_or_default()
+    }
+
+    pub fn reset(&mut self) {
+        self.sums.clear();
+        self.samples.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sinusoid_gives_its_amplitude_and_phase() {
+        let frequency = 1000.;
+        let delta_t = 1. / 48_000.;
+        let amplitude = 2.;
+        let phase = 60_f32;
+        let mut maps = FrequencyMaps::default();
+
+        // 20 whole periods of a cosine in the first cell, the second cell stays silent
+        for sample in 0..960 {
+            let time = sample as f32 * delta_t;
+            let pressure =
+                amplitude * (std::f32::consts::TAU * frequency * time + phase.to_radians()).cos();
+            maps.accumulate(&[pressure, 0.], sample, delta_t);
+        }
+
+        assert!((maps.amplitude(0).abs() - amplitude).abs() < 1e-3);
+        assert!(
+            (maps.magnitude_level(0) - pressure_to_db_spl(amplitude / std::f32::consts::SQRT_2))
+                .abs()
+                < 1e-3
+        );
+        assert!((maps.phase(0) - phase).abs() < 0.01, "{}", maps.phase(0));
+        assert_eq!(maps.amplitude(1).abs(), 0.);
+    }
+}
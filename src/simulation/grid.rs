@@ -1,12 +1,21 @@
+use std::collections::HashMap;
 use std::f32::consts::TAU;
 
 use bevy::prelude::*;
 use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
 
-use super::plugin::WaveSamples;
+use super::noise_control::NoiseControl;
+use super::plugin::{FilterStates, MicrophoneSignals, SampleTables, WaveSamples};
+use crate::components::line_array::{ArrayElement, LineArray};
 use crate::components::microphone::Microphone;
-use crate::components::source::Source;
+use crate::components::plane_wave::PlaneWave;
+use crate::components::source::{Source, SourceType};
+use crate::components::source_group::SourceGroup;
+use crate::components::vibrating_surface::{
+    SurfaceEdge, SurfaceElement, SurfaceMode, VibratingSurface,
+};
 use crate::components::wall::{CircWall, RectWall, Wall, WallCell};
+use crate::math::calibration::source_gain;
 use crate::math::constants::*;
 use crate::math::transformations::{coords_to_index, index_to_coords};
 use crate::ui::state::UiState;
@@ -19,6 +28,33 @@ pub struct Cell {
     pub right: f32,
 }
 
+/// The elements of a line array, only calculated again when the array or the cell size changes
+#[derive(Debug)]
+struct LineArrayCells {
+    line_array: LineArray,
+    delta_l: f32,
+    elements: Vec<ArrayElement>,
+}
+
+/// The cells excited by a plane wave, only calculated again when the angle of the wave
+/// or the cell size changes
+#[derive(Debug)]
+struct PlaneWaveCells {
+    angle: f32,
+    delta_l: f32,
+    elements: Vec<ArrayElement>,
+}
+
+/// The wall cells of a vibrating surface, only calculated again when the surface changes or the
+/// walls are updated
+#[derive(Debug)]
+struct SurfaceCells {
+    wall_id: usize,
+    edge: SurfaceEdge,
+    mode: SurfaceMode,
+    elements: Vec<SurfaceElement>,
+}
+
 #[derive(Debug, Resource)]
 pub struct Grid {
     /// Grid cells
@@ -27,6 +63,12 @@ pub struct Grid {
     pub pressure: Vec<f32>,
     pub wall_cache: Vec<WallCell>,
     boundary_cache: Vec<[f32; 4]>,
+    /// elements of the line arrays, keyed by their id
+    line_array_cache: HashMap<usize, LineArrayCells>,
+    /// cells excited by the plane waves, keyed by their id
+    plane_wave_cache: HashMap<usize, PlaneWaveCells>,
+    /// wall cells of the vibrating surfaces, keyed by their id
+    surface_cache: HashMap<usize, SurfaceCells>,
     /// Delta t in seconds
     pub delta_t: f32,
 }
@@ -64,6 +106,9 @@ impl Default for Grid {
                     * (SIMULATION_HEIGHT + 2 * INIT_BOUNDARY_WIDTH))
                     as usize
             ],
+            line_array_cache: HashMap::new(),
+            plane_wave_cache: HashMap::new(),
+            surface_cache: HashMap::new(),
             // set to result in a sample rate of 48kHz
             delta_t: 0.00715 / PROPAGATION_SPEED,
         }
@@ -131,6 +176,9 @@ impl Grid {
         circ_walls: &Query<&CircWall>,
         boundary_width: u32,
     ) {
+        // the walls of the surfaces could have been moved or resized
+        self.surface_cache.clear();
+
         self.wall_cache.par_iter_mut().for_each(|wall_cell| {
             wall_cell.is_wall = false;
         });
@@ -174,60 +222,37 @@ impl Grid {
             });
 
         for wall in circ_walls {
-            let mut b_x = 0i32;
-            let mut b_y = wall.radius as i32;
-            let mut d = 1 - wall.radius as i32;
-            while b_x <= b_y {
-                for (x, y) in [
-                    (wall.center.x as i32 + b_x, wall.center.y as i32 + b_y),
-                    (wall.center.x as i32 + b_x, wall.center.y as i32 - b_y),
-                    (wall.center.x as i32 - b_x, wall.center.y as i32 + b_y),
-                    (wall.center.x as i32 - b_x, wall.center.y as i32 - b_y),
-                    (wall.center.x as i32 + b_y, wall.center.y as i32 + b_x),
-                    (wall.center.x as i32 + b_y, wall.center.y as i32 - b_x),
-                    (wall.center.x as i32 - b_y, wall.center.y as i32 + b_x),
-                    (wall.center.x as i32 - b_y, wall.center.y as i32 - b_x),
-                ] {
-                    let x = (x + boundary_width as i32) as u32;
-                    let y = (y + boundary_width as i32) as u32;
-                    if x < SIMULATION_WIDTH + 2 * boundary_width
-                        && y < SIMULATION_HEIGHT + 2 * boundary_width
+            for IVec2 { x, y } in wall.circle_points() {
+                let x = (x + boundary_width as i32) as u32;
+                let y = (y + boundary_width as i32) as u32;
+                if x < SIMULATION_WIDTH + 2 * boundary_width
+                    && y < SIMULATION_HEIGHT + 2 * boundary_width
+                {
+                    // angle in [0, 2pi)
+                    let mut angle =
+                        if (y as i32 - wall.center.y as i32 - boundary_width as i32) <= 0 {
+                            ((x as f32 - wall.center.x as f32 - boundary_width as f32)
+                                / wall.radius as f32)
+                                .acos()
+                        } else {
+                            TAU - ((x as f32 - wall.center.x as f32 - boundary_width as f32)
+                                / wall.radius as f32)
+                                .acos()
+                        };
+
+                    angle = (angle + wall.rotation_angle.to_radians()) % TAU;
+
+                    if angle >= wall.open_circ_segment.to_radians() / 2.
+                        && angle <= TAU - wall.open_circ_segment.to_radians() / 2.
+                        || !wall.is_hollow
                     {
-                        // angle in [0, 2pi)
-                        let mut angle =
-                            if (y as i32 - wall.center.y as i32 - boundary_width as i32) <= 0 {
-                                ((x as f32 - wall.center.x as f32 - boundary_width as f32)
-                                    / wall.radius as f32)
-                                    .acos()
-                            } else {
-                                TAU - ((x as f32 - wall.center.x as f32 - boundary_width as f32)
-                                    / wall.radius as f32)
-                                    .acos()
-                            };
-
-                        angle = (angle + wall.rotation_angle.to_radians()) % TAU;
-
-                        if angle >= wall.open_circ_segment.to_radians() / 2.
-                            && angle <= TAU - wall.open_circ_segment.to_radians() / 2.
-                            || !wall.is_hollow
-                        {
-                            let index = coords_to_index(x, y, boundary_width);
-                            self.wall_cache[index].is_wall = true;
-                            self.wall_cache[index].reflection_factor = wall.get_reflection_factor();
-                            self.wall_cache[index].draw_reflection_factor =
-                                wall.get_reflection_factor();
-                        }
+                        let index = coords_to_index(x, y, boundary_width);
+                        self.wall_cache[index].is_wall = true;
+                        self.wall_cache[index].reflection_factor = wall.get_reflection_factor();
+                        self.wall_cache[index].draw_reflection_factor =
+                            wall.get_reflection_factor();
                     }
                 }
-
-                if d < 0 {
-                    d = d + 2 * b_x + 3;
-                    b_x += 1;
-                } else {
-                    d = d + 2 * (b_x - b_y) + 5;
-                    b_x += 1;
-                    b_y -= 1;
-                }
             }
         }
     }
@@ -311,20 +336,286 @@ impl Grid {
         time_since_start: f32,
         samples_since_start: usize,
         sources: &Query<&Source>,
+        groups: &Query<&SourceGroup>,
         boundary_width: u32,
         wave_samples: &WaveSamples,
+        sample_tables: &SampleTables,
+        filter_states: &mut FilterStates,
+        mic_signals: &MicrophoneSignals,
+        noise_control: &NoiseControl,
     ) {
-        for source in sources.iter() {
-            let calc = source.calc(time_since_start, samples_since_start, wave_samples);
+        let any_solo = sources.iter().any(|source| source.enabled && source.solo);
+        for source in sources.iter().filter(|source| source.is_audible(any_solo)) {
+            // members of a group play the signal of the group, shifted by their phase offset
+            let (source_type, noise_seed, time) = match source
+                .group
+                .and_then(|id| groups.iter().find(|group| group.id == id))
+            {
+                Some(group) => (
+                    &group.source_type,
+                    group.noise_seed(),
+                    time_since_start - group.source_type.phase_delay(source.phase_offset),
+                ),
+                None => (&source.source_type, source.noise_seed(), time_since_start),
+            };
+
+            let calc = if noise_control.enabled && noise_control.secondary_source == source.id {
+                source_gain(CALIBRATION_FREQUENCY, self.delta_t) * noise_control.output()
+            } else if let SourceType::Microphone {
+                mic_id,
+                delay,
+                gain,
+            } = *source_type
+            {
+                source_gain(CALIBRATION_FREQUENCY, self.delta_t)
+                    * gain
+                    * mic_signals.delayed(mic_id, (delay.max(0.) / self.delta_t).round() as usize)
+            } else {
+                source_type.calc(
+                    time,
+                    samples_since_start,
+                    noise_seed,
+                    self.delta_t,
+                    wave_samples,
+                    sample_tables,
+                )
+            };
+            let calc = filter_states.process(source, calc, 1. / self.delta_t);
             let source_pos = coords_to_index(
                 source.x + boundary_width,
                 source.y + boundary_width,
                 boundary_width,
             );
-            self.next_cells[source_pos].bottom += calc;
-            self.next_cells[source_pos].left += calc;
-            self.next_cells[source_pos].top += calc;
-            self.next_cells[source_pos].right += calc;
+            let [bottom, left, top, right] = source.directivity.port_factors(source.orientation);
+            self.next_cells[source_pos].bottom += bottom * calc;
+            self.next_cells[source_pos].left += left * calc;
+            self.next_cells[source_pos].top += top * calc;
+            self.next_cells[source_pos].right += right * calc;
+        }
+    }
+
+    /// Write the outputs of all line array elements into cell reflection pulses
+    pub fn apply_line_arrays(
+        &mut self,
+        time_since_start: f32,
+        samples_since_start: usize,
+        line_arrays: &Query<&LineArray>,
+        boundary_width: u32,
+        wave_samples: &WaveSamples,
+        sample_tables: &SampleTables,
+    ) {
+        let delta_l = self.delta_t * PROPAGATION_SPEED;
+        // the ids of deleted arrays can be used again
+        self.line_array_cache
+            .retain(|id, _| line_arrays.iter().any(|line_array| line_array.id == *id));
+        for line_array in line_arrays.iter() {
+            let cached = self
+                .line_array_cache
+                .get(&line_array.id)
+                .is_some_and(|cells| cells.line_array == *line_array && cells.delta_l == delta_l);
+            if !cached {
+                self.line_array_cache.insert(
+                    line_array.id,
+                    LineArrayCells {
+                        line_array: line_array.clone(),
+                        delta_l,
+                        elements: line_array.calc_elements(delta_l),
+                    },
+                );
+            }
+
+            for element in &self.line_array_cache[&line_array.id].elements {
+                let delay_samples = (element.delay / self.delta_t).round() as usize;
+                if time_since_start < element.delay || samples_since_start < delay_samples {
+                    continue;
+                }
+
+                let calc = element.gain
+                    * line_array.source_type.calc(
+                        time_since_start - element.delay,
+                        samples_since_start - delay_samples,
+                        line_array.noise_seed(),
+                        self.delta_t,
+                        wave_samples,
+                        sample_tables,
+                    );
+                let source_pos = coords_to_index(
+                    element.x + boundary_width,
+                    element.y + boundary_width,
+                    boundary_width,
+                );
+                self.next_cells[source_pos].bottom += calc;
+                self.next_cells[source_pos].left += calc;
+                self.next_cells[source_pos].top += calc;
+                self.next_cells[source_pos].right += calc;
+            }
+        }
+    }
+
+    /// Write the excitation of all plane waves into the cell reflection pulses on the edges
+    pub fn apply_plane_waves(
+        &mut self,
+        time_since_start: f32,
+        samples_since_start: usize,
+        plane_waves: &Query<&PlaneWave>,
+        boundary_width: u32,
+        wave_samples: &WaveSamples,
+        sample_tables: &SampleTables,
+    ) {
+        let delta_l = self.delta_t * PROPAGATION_SPEED;
+        // the ids of deleted plane waves can be used again
+        self.plane_wave_cache
+            .retain(|id, _| plane_waves.iter().any(|plane_wave| plane_wave.id == *id));
+        for plane_wave in plane_waves.iter() {
+            let cells = self
+                .plane_wave_cache
+                .entry(plane_wave.id)
+                .or_insert_with(|| PlaneWaveCells {
+                    angle: f32::NAN,
+                    delta_l,
+                    elements: vec![],
+                });
+            if cells.angle != plane_wave.angle || cells.delta_l != delta_l {
+                *cells = PlaneWaveCells {
+                    angle: plane_wave.angle,
+                    delta_l,
+                    elements: plane_wave.calc_elements(delta_l),
+                };
+            }
+
+            for element in &cells.elements {
+                let source_pos = coords_to_index(
+                    element.x + boundary_width,
+                    element.y + boundary_width,
+                    boundary_width,
+                );
+                if self.wall_cache[source_pos].is_wall {
+                    continue;
+                }
+                let delay_samples = (element.delay / self.delta_t).round() as usize;
+                if time_since_start < element.delay || samples_since_start < delay_samples {
+                    continue;
+                }
+
+                let calc = element.gain
+                    * plane_wave.source_type.signal(
+                        time_since_start - element.delay,
+                        samples_since_start - delay_samples,
+                        plane_wave.noise_seed(),
+                        wave_samples,
+                        sample_tables,
+                    );
+                self.next_cells[source_pos].bottom += calc;
+                self.next_cells[source_pos].left += calc;
+                self.next_cells[source_pos].top += calc;
+                self.next_cells[source_pos].right += calc;
+            }
+        }
+    }
+
+    /// Write the velocity of all vibrating surfaces into the outward facing reflection pulses
+    /// of their wall cells
+    pub fn apply_vibrating_surfaces(
+        &mut self,
+        time_since_start: f32,
+        samples_since_start: usize,
+        surfaces: &Query<&VibratingSurface>,
+        rect_walls: &Query<&RectWall>,
+        circ_walls: &Query<&CircWall>,
+        boundary_width: u32,
+        wave_samples: &WaveSamples,
+        sample_tables: &SampleTables,
+    ) {
+        for surface in surfaces.iter() {
+            let cached = self.surface_cache.get(&surface.id).is_some_and(|cells| {
+                cells.wall_id == surface.wall_id
+                    && cells.edge == surface.edge
+                    && cells.mode == surface.mode
+            });
+            if !cached {
+                let rect_walls = rect_walls.iter().collect::<Vec<_>>();
+                let circ_walls = circ_walls.iter().collect::<Vec<_>>();
+                self.surface_cache.insert(
+                    surface.id,
+                    SurfaceCells {
+                        wall_id: surface.wall_id,
+                        edge: surface.edge,
+                        mode: surface.mode,
+                        elements: surface.calc_elements(&rect_walls, &circ_walls),
+                    },
+                );
+            }
+
+            let velocity = surface.source_type.signal(
+                time_since_start,
+                samples_since_start,
+                surface.noise_seed(),
+                wave_samples,
+                sample_tables,
+            );
+            for element in &self.surface_cache[&surface.id].elements {
+                let x = element.x + boundary_width;
+                let y = element.y + boundary_width;
+                // only radiate into cells that are not part of a wall
+                let radiates = |x: u32, y: u32| {
+                    !self.wall_cache[coords_to_index(x, y, boundary_width)].is_wall
+                };
+                let [bottom, left, top, right] = element.port_factors;
+                let cell = coords_to_index(x, y, boundary_width);
+                if bottom != 0. && radiates(x, y + 1) {
+                    self.next_cells[cell].bottom += bottom * velocity;
+                }
+                if left != 0. && radiates(x - 1, y) {
+                    self.next_cells[cell].left += left * velocity;
+                }
+                if top != 0. && radiates(x, y - 1) {
+                    self.next_cells[cell].top += top * velocity;
+                }
+                if right != 0. && radiates(x + 1, y) {
+                    self.next_cells[cell].right += right * velocity;
+                }
+            }
+        }
+    }
+
+    /// Returns the sound pressure (in Pa) of the cell at the given position of the simulation area.
+    pub fn pressure_at(&self, x: u32, y: u32, boundary_width: u32) -> f32 {
+        self.pressure[coords_to_index(x + boundary_width, y + boundary_width, boundary_width)]
+    }
+
+    /// Records the sound pressure at all microphones that sources or source groups are routed
+    /// from, keeping as many samples as the longest delay of these signals needs.
+    pub fn record_routed_microphones(
+        &self,
+        sources: &Query<&Source>,
+        groups: &Query<&SourceGroup>,
+        microphones: &Query<&mut Microphone>,
+        boundary_width: u32,
+        mic_signals: &mut MicrophoneSignals,
+    ) {
+        for mic in microphones.iter() {
+            let length = sources
+                .iter()
+                .map(|source| &source.source_type)
+                .chain(groups.iter().map(|group| &group.source_type))
+                .filter_map(|source_type| match *source_type {
+                    SourceType::Microphone { mic_id, delay, .. } if mic_id == mic.id => {
+                        Some((delay.max(0.) / self.delta_t).round() as usize + 1)
+                    }
+                    _ => None,
+                })
+                .max();
+            if let Some(length) = length {
+                mic_signals.push(
+                    mic.id,
+                    self.pressure[coords_to_index(
+                        mic.x + boundary_width,
+                        mic.y + boundary_width,
+                        boundary_width,
+                    )],
+                    length,
+                );
+            }
         }
     }
 
@@ -410,3 +701,345 @@ impl Grid {
         1.0 - (distance as f32 / boundary_width as f32).powi(power_order as i32)
     }
 }
+
+/// Helpers for tests that run the simulation on a grid
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+
+    /// Cell size of the test grids (in m), coarse so the wave reaches a distance of 1 m after a
+    /// few steps
+    pub const DELTA_L: f32 = 0.025;
+    /// A distance of 1 m in cells of [`DELTA_L`]
+    pub const ONE_METER: u32 = 40;
+
+    /// Returns a silent grid with cells of [`DELTA_L`].
+    pub fn coarse_grid() -> Grid {
+        let mut grid = Grid::default();
+        grid.update_delta_t(DELTA_L);
+        grid.cache_boundaries(INIT_BOUNDARY_WIDTH);
+        grid
+    }
+
+    /// Runs the grid for 300 steps and returns the sound pressure at the `positions` (in cells)
+    /// during the last 100 steps. The wave travels 1 m in about 60 steps and settles after a few
+    /// periods, 100 steps are about 5 periods at 1 kHz.
+    /// * `excite` - Writes the sources of a step into the next cells.
+    pub fn settled_pressure(
+        grid: &mut Grid,
+        positions: &[(u32, u32)],
+        mut excite: impl FnMut(&mut Grid, usize),
+    ) -> Vec<Vec<f32>> {
+        let mut pressure = vec![Vec::new(); positions.len()];
+        for step in 0..300 {
+            grid.calc_cells(INIT_BOUNDARY_WIDTH);
+            excite(grid, step);
+            grid.update_cells();
+
+            if step >= 200 {
+                for (pressure, &(x, y)) in pressure.iter_mut().zip(positions) {
+                    pressure.push(grid.pressure_at(x, y, INIT_BOUNDARY_WIDTH));
+                }
+            }
+        }
+        pressure
+    }
+
+    /// Adds a value to the ports of a cell, weighted by the factors of the bottom, left, top and
+    /// right port.
+    pub fn inject(grid: &mut Grid, x: u32, y: u32, factors: [f32; 4], value: f32) {
+        let [bottom, left, top, right] = factors;
+        let cell = &mut grid.next_cells[coords_to_index(
+            x + INIT_BOUNDARY_WIDTH,
+            y + INIT_BOUNDARY_WIDTH,
+            INIT_BOUNDARY_WIDTH,
+        )];
+        cell.bottom += bottom * value;
+        cell.left += left * value;
+        cell.top += top * value;
+        cell.right += right * value;
+    }
+
+    /// Returns the largest magnitude of a signal.
+    pub fn peak(signal: &[f32]) -> f32 {
+        signal.iter().fold(0., |peak, value| peak.max(value.abs()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use super::testing::{coarse_grid, peak, settled_pressure, ONE_METER};
+    use super::*;
+
+    #[test]
+    fn routed_microphones_are_delayed_and_scaled() {
+        let mut grid = Grid::default();
+        grid.cache_boundaries(INIT_BOUNDARY_WIDTH);
+        let gain = 0.5;
+        let routed = |delay: usize| SourceType::Microphone {
+            mic_id: 0,
+            delay: delay as f32 * grid.delta_t,
+            gain,
+        };
+
+        let mut world = World::new();
+        world.spawn(Microphone::new(10, 10, 0));
+        world.spawn(Source::new(50, 50, routed(2), 0));
+        // the group needs the longest delay, its member plays it instead of the own signal
+        world.spawn(SourceGroup {
+            id: 0,
+            source_type: routed(4),
+        });
+        let mut member = Source::new(100, 100, SourceType::default_sin(), 1);
+        member.group = Some(0);
+        world.spawn(member);
+        let mut state =
+            SystemState::<(Query<&Source>, Query<&SourceGroup>, Query<&mut Microphone>)>::new(
+                &mut world,
+            );
+        let (sources, groups, microphones) = state.get_mut(&mut world);
+
+        let mut mic_signals = MicrophoneSignals::default();
+        let mic = coords_to_index(
+            10 + INIT_BOUNDARY_WIDTH,
+            10 + INIT_BOUNDARY_WIDTH,
+            INIT_BOUNDARY_WIDTH,
+        );
+        for step in 1..=6 {
+            grid.pressure[mic] = step as f32;
+            grid.record_routed_microphones(
+                &sources,
+                &groups,
+                &microphones,
+                INIT_BOUNDARY_WIDTH,
+                &mut mic_signals,
+            );
+        }
+        assert_eq!(mic_signals.delayed(0, 0), 6.);
+        assert_eq!(mic_signals.delayed(0, 4), 2.);
+        // only as many samples as the longest delay needs are kept
+        assert_eq!(mic_signals.delayed(0, 5), 0.);
+
+        grid.apply_sources(
+            0.,
+            0,
+            &sources,
+            &groups,
+            INIT_BOUNDARY_WIDTH,
+            &WaveSamples::default(),
+            &SampleTables::default(),
+            &mut FilterStates::default(),
+            &mic_signals,
+            &NoiseControl::default(),
+        );
+        let source_gain = source_gain(CALIBRATION_FREQUENCY, grid.delta_t);
+        for (x, y, expected) in [(50, 50, 4.), (100, 100, 2.)] {
+            let expected = source_gain * gain * expected;
+            let cell = grid.next_cells[coords_to_index(
+                x + INIT_BOUNDARY_WIDTH,
+                y + INIT_BOUNDARY_WIDTH,
+                INIT_BOUNDARY_WIDTH,
+            )];
+            assert!(
+                (cell.bottom - expected).abs() < 1e-6 * expected,
+                "source at ({}, {}) injects {} instead of {}",
+                x,
+                y,
+                cell.bottom,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn group_members_play_the_group_signal_shifted_by_their_phase() {
+        let mut grid = Grid::default();
+        grid.cache_boundaries(INIT_BOUNDARY_WIDTH);
+        let group = SourceGroup {
+            id: 0,
+            source_type: SourceType::Sin {
+                phase: 0.,
+                frequency: 1000.,
+                amplitude: 1.,
+            },
+        };
+
+        let mut world = World::new();
+        world.spawn(group.clone());
+        for (id, phase_offset) in [(0, 0.), (1, 90.)] {
+            // the own signal of a member is not played
+            let mut member =
+                Source::new(100 + 50 * id as u32, 100, SourceType::default_noise(), id);
+            member.group = Some(0);
+            member.phase_offset = phase_offset;
+            world.spawn(member);
+        }
+        let mut state = SystemState::<(Query<&Source>, Query<&SourceGroup>)>::new(&mut world);
+        let (sources, groups) = state.get(&world);
+
+        let step = 100;
+        let time = step as f32 * grid.delta_t;
+        grid.apply_sources(
+            time,
+            step,
+            &sources,
+            &groups,
+            INIT_BOUNDARY_WIDTH,
+            &WaveSamples::default(),
+            &SampleTables::default(),
+            &mut FilterStates::default(),
+            &MicrophoneSignals::default(),
+            &NoiseControl::default(),
+        );
+
+        let injected = |x: u32| {
+            grid.next_cells[coords_to_index(
+                x + INIT_BOUNDARY_WIDTH,
+                100 + INIT_BOUNDARY_WIDTH,
+                INIT_BOUNDARY_WIDTH,
+            )]
+            .bottom
+        };
+        let expected = |phase_offset: f32| {
+            group.source_type.calc(
+                time - group.source_type.phase_delay(phase_offset),
+                step,
+                group.noise_seed(),
+                grid.delta_t,
+                &WaveSamples::default(),
+                &SampleTables::default(),
+            )
+        };
+        // a quarter period of 1 kHz
+        assert_eq!(group.source_type.phase_delay(90.), 0.00025);
+        assert_eq!(injected(100), expected(0.));
+        assert_eq!(injected(150), expected(90.));
+        assert_ne!(injected(100), injected(150));
+    }
+
+    #[test]
+    fn plane_waves_have_their_amplitude_inside() {
+        // 1 m from the edges the wave enters through
+        for (angle, position) in [
+            (0., (ONE_METER, SIMULATION_HEIGHT / 2)),
+            (45., (ONE_METER, SIMULATION_HEIGHT - 1 - ONE_METER)),
+        ] {
+            let mut grid = coarse_grid();
+            let mut world = World::new();
+            world.spawn(PlaneWave {
+                id: 0,
+                source_type: SourceType::Sin {
+                    phase: 0.,
+                    frequency: 1000.,
+                    amplitude: 1.,
+                },
+                angle,
+            });
+            let mut state = SystemState::<Query<&PlaneWave>>::new(&mut world);
+            let plane_waves = state.get(&world);
+
+            let pressure = settled_pressure(&mut grid, &[position], |grid, step| {
+                grid.apply_plane_waves(
+                    step as f32 * grid.delta_t,
+                    step,
+                    &plane_waves,
+                    INIT_BOUNDARY_WIDTH,
+                    &WaveSamples::default(),
+                    &SampleTables::default(),
+                );
+            });
+
+            let peak = peak(&pressure[0]);
+            assert!(
+                (peak - 1.).abs() < 0.1,
+                "peak amplitude of the wave at {}° is {} Pa",
+                angle,
+                peak
+            );
+        }
+    }
+
+    /// Runs a grid with a vibrating surface on an edge of a wall with a 1 kHz velocity of
+    /// 0.01 m/s and returns the settled sound pressure at the `positions`.
+    fn surface_pressure(
+        wall: RectWall,
+        edge: SurfaceEdge,
+        mode: SurfaceMode,
+        positions: &[(u32, u32)],
+    ) -> Vec<Vec<f32>> {
+        let mut grid = coarse_grid();
+        let mut world = World::new();
+        let mut surface = VibratingSurface::new(wall.id, edge, 0);
+        surface.source_type = SourceType::Sin {
+            phase: 0.,
+            frequency: 1000.,
+            amplitude: 0.01,
+        };
+        surface.mode = mode;
+        world.spawn(surface);
+        world.spawn(wall);
+        let mut state =
+            SystemState::<(Query<&VibratingSurface>, Query<&RectWall>, Query<&CircWall>)>::new(
+                &mut world,
+            );
+        let (surfaces, rect_walls, circ_walls) = state.get(&world);
+        grid.update_walls(&rect_walls, &circ_walls, INIT_BOUNDARY_WIDTH);
+
+        settled_pressure(&mut grid, positions, |grid, step| {
+            grid.apply_vibrating_surfaces(
+                step as f32 * grid.delta_t,
+                step,
+                &surfaces,
+                &rect_walls,
+                &circ_walls,
+                INIT_BOUNDARY_WIDTH,
+                &WaveSamples::default(),
+                &SampleTables::default(),
+            );
+        })
+    }
+
+    #[test]
+    fn piston_radiates_rho_c_v() {
+        // a surface over the whole height of the simulation area radiates a plane wave
+        let wall = RectWall::new(0, 0, 10, SIMULATION_HEIGHT - 1, false, 1., 0);
+        let pressure = surface_pressure(
+            wall,
+            SurfaceEdge::Right,
+            SurfaceMode::Piston,
+            &[(10 + ONE_METER, SIMULATION_HEIGHT / 2)],
+        );
+
+        let expected = AIR_DENSITY * SPEED_OF_SOUND * 0.01;
+        let peak = peak(&pressure[0]);
+        assert!(
+            (peak - expected).abs() < 0.1 * expected,
+            "peak sound pressure of the piston is {} Pa instead of {} Pa",
+            peak,
+            expected
+        );
+    }
+
+    #[test]
+    fn second_flexural_mode_is_silent_on_its_centre_line() {
+        // 399 cells symmetric to the row 349, where the mode shape changes its sign
+        let wall = RectWall::new(0, 150, 10, 548, false, 1., 0);
+        let pressure = surface_pressure(
+            wall,
+            SurfaceEdge::Right,
+            SurfaceMode::Flexural { order: 2 },
+            &[(10 + ONE_METER, 349), (10 + ONE_METER, 249)],
+        );
+
+        let (centre, antinode) = (peak(&pressure[0]), peak(&pressure[1]));
+        assert!(antinode > 0.);
+        assert!(
+            centre < 1e-3 * antinode,
+            "sound pressure on the centre line is {} Pa, {} Pa in front of the antinode",
+            centre,
+            antinode
+        );
+    }
+}
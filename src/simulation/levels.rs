@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
+
+use crate::math::calibration::pressure_to_db_spl;
+use crate::math::constants::REFERENCE_PRESSURE;
+
+/// The sound pressure of every cell of the grid accumulated over time, used to show standing
+/// waves, coverage and shadow zones.
+#[derive(Resource, Debug, Default)]
+pub struct PressureLevels {
+    /// sum of the squared sound pressures per cell (in Pa²)
+    sum_squares: Vec<f32>,
+    /// highest absolute sound pressure per cell (in Pa)
+    peaks: Vec<f32>,
+    /// number of accumulated samples
+    samples: usize,
+}
+
+impl PressureLevels {
+    /// Adds the current sound pressure of all cells (in Pa). The levels start over if the size
+    /// of the grid changed.
+    pub fn accumulate(&mut self, pressure: &[f32]) {
+        if self.sum_squares.len() != pressure.len() {
+            self.sum_squares = vec![0.; pressure.len()];
+            self.peaks = vec![0.; pressure.len()];
+            self.samples = 0;
+        }
+
+        self.sum_squares
+            .par_iter_mut()
+            .zip(self.peaks.par_iter_mut())
+            .enumerate()
+            .for_each(|(index, (sum_square, peak))| {
+                *sum_square += pressure[index].powi(2);
+                *peak = peak.max(pressure[index].abs());
+            });
+        self.samples += 1;
+    }
+
+    /// Returns the sound pressure level of the RMS pressure of a cell (in dB SPL).
+    pub fn rms_level(&self, index: usize) -> f32 {
+        let mean_square = match self.sum_squares.get(index) {
+            Some(sum_square) if self.samples > 0 => sum_square / self.samples as f32,
+            _ => 0.,
+        };
+        pressure_to_db_spl(mean_square.sqrt().max(REFERENCE_PRESSURE * 1e-3))
+    }
+
+    /// Returns the level of the peak pressure of a cell (in dB re 20 µPa).
+    pub fn peak_level(&self, index: usize) -> f32 {
+        let peak = self.peaks.get(index).copied().unwrap_or(0.);
+        pressure_to_db_spl(peak.max(REFERENCE_PRESSURE * 1e-3))
+    }
+
+    /// Returns the number of accumulated samples.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    pub fn reset(&mut self) {
+        self.sum_squares.clear();
+        self.peaks.clear();
+        self.samples = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_of_a_sinusoid() {
+        let amplitude = 2.;
+        let mut levels = PressureLevels::default();
+
+        // 10 whole periods with 48 samples each
+        for sample in 0..480 {
+            let pressure = amplitude * (std::f32::consts::TAU * sample as f32 / 48.).sin();
+            levels.accumulate(&[pressure, 0.]);
+        }
+
+        assert_eq!(levels.samples(), 480);
+        assert!(
+            (levels.rms_level(0) - pressure_to_db_spl(amplitude / std::f32::consts::SQRT_2)).abs()
+                < 1e-3
+        );
+        assert!((levels.peak_level(0) - pressure_to_db_spl(amplitude)).abs() < 1e-3);
+        // silent cells are clamped 60 dB below the reference pressure
+        assert_eq!(
+            levels.rms_level(1),
+            pressure_to_db_spl(REFERENCE_PRESSURE * 1e-3)
+        );
+    }
+}
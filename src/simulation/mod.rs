@@ -1,3 +1,6 @@
+pub mod frequency_maps;
 pub mod grid;
+pub mod levels;
+pub mod noise_control;
 pub mod plugin;
 pub mod systems;
@@ -0,0 +1,298 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use bevy::prelude::*;
+
+use crate::math::calibration::pressure_to_db_spl;
+use crate::math::noise::white_noise;
+
+/// Duration of the blocks the level at the error microphone is averaged over (in s)
+const LEVEL_BLOCK_DURATION: f32 = 0.0005;
+/// Seed of the identification noise, distinct from the seeds of all scene objects
+const NOISE_SEED: u64 = 4 << 24;
+/// Regularization of the normalized step sizes
+const REGULARIZATION: f32 = 1e-9;
+
+/// The stage of the active noise control experiment
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum NoiseControlStage {
+    /// the secondary source plays white noise to estimate the secondary path
+    #[default]
+    Identification,
+    /// the control filter is adapted to cancel the noise at the error microphone
+    Control,
+}
+
+impl fmt::Display for NoiseControlStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NoiseControlStage::Identification => write!(f, "Secondary path identification"),
+            NoiseControlStage::Control => write!(f, "Control"),
+        }
+    }
+}
+
+/// An active noise control experiment using the filtered-x LMS algorithm.
+///
+/// An adaptive FIR filter drives the secondary source with the signal of the reference
+/// microphone and minimizes the sound pressure at the error microphone. The gradient is
+/// calculated with the reference filtered by an estimate of the secondary path (from the
+/// secondary source to the error microphone), which is identified with white noise first.
+#[derive(Resource, Debug)]
+pub struct NoiseControl {
+    pub enabled: bool,
+    pub reference_mic: usize,
+    pub error_mic: usize,
+    /// id of the [`Source`](crate::components::source::Source) that is driven by the controller
+    pub secondary_source: usize,
+    pub stage: NoiseControlStage,
+    /// number of taps of the control filter
+    pub filter_length: usize,
+    /// number of taps of the secondary path estimate
+    pub path_length: usize,
+    /// normalized step size of the control filter
+    pub step_size: f32,
+    /// normalized step size of the secondary path estimate
+    pub identification_step_size: f32,
+    /// RMS amplitude of the identification noise (sound pressure at 1 m in Pa)
+    pub identification_amplitude: f32,
+    /// duration of the identification, after which the control starts (in s)
+    pub identification_duration: f32,
+    weights: Vec<f32>,
+    secondary_path: Vec<f32>,
+    /// the latest samples of the reference microphone, newest first
+    reference: VecDeque<f32>,
+    /// the reference filtered with the secondary path estimate, newest first
+    filtered_reference: VecDeque<f32>,
+    /// the latest outputs of the secondary source, newest first
+    outputs: VecDeque<f32>,
+    /// output of the secondary source for the next sample (sound pressure at 1 m in Pa)
+    output: f32,
+    identified_time: f32,
+    block_sum: f32,
+    block_samples: usize,
+    /// (time in s, sound pressure level at the error microphone in dB SPL) pairs
+    pub error_levels: Vec<[f64; 2]>,
+    /// the time the control started at (in s)
+    pub control_start: Option<f64>,
+}
+
+impl Default for NoiseControl {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            reference_mic: 0,
+            error_mic: 1,
+            secondary_source: 0,
+            stage: NoiseControlStage::default(),
+            filter_length: 128,
+            path_length: 256,
+            step_size: 0.01,
+            identification_step_size: 0.05,
+            identification_amplitude: 0.5,
+            identification_duration: 0.05,
+            weights: vec![],
+            secondary_path: vec![],
+            reference: VecDeque::new(),
+            filtered_reference: VecDeque::new(),
+            outputs: VecDeque::new(),
+            output: 0.,
+            identified_time: 0.,
+            block_sum: 0.,
+            block_samples: 0,
+            error_levels: vec![],
+            control_start: None,
+        }
+    }
+}
+
+impl NoiseControl {
+    /// Returns the output of the secondary source for the current sample
+    /// (sound pressure at 1 m in Pa).
+    pub fn output(&self) -> f32 {
+        self.output
+    }
+
+    /// Silences the secondary source, e.g. while a microphone of the controller is missing.
+    pub fn mute(&mut self) {
+        self.output = 0.;
+    }
+
+    /// Returns the current estimate of the impulse response of the secondary path.
+    pub fn secondary_path(&self) -> &[f32] {
+        &self.secondary_path
+    }
+
+    /// Adapts the filters to the latest sound pressure at the microphones (in Pa) and calculates
+    /// the output of the secondary source for the next sample.
+    /// * `time` - The time since the start of the simulation (in s).
+    /// * `cur_sample` - The number of samples since the start of the simulation.
+    /// * `delta_t` - The duration of a sample (in s).
+    pub fn update(
+        &mut self,
+        reference: f32,
+        error: f32,
+        time: f32,
+        cur_sample: usize,
+        delta_t: f32,
+    ) {
+        self.resize();
+        push_front(&mut self.outputs, self.output, self.path_length);
+
+        match self.stage {
+            NoiseControlStage::Identification => {
+                // model the error microphone with the secondary path and the played noise
+                let model_error = error - dot(&self.secondary_path, &self.outputs);
+                let step =
+                    self.identification_step_size / (dot_self(&self.outputs) + REGULARIZATION);
+                for (tap, output) in self.secondary_path.iter_mut().zip(self.outputs.iter()) {
+                    *tap += step * model_error * output;
+                }
+
+                self.identified_time += delta_t;
+                self.output = if self.identified_time < self.identification_duration {
+                    white_noise(NOISE_SEED, cur_sample) * self.identification_amplitude
+                } else {
+                    self.stage = NoiseControlStage::Control;
+                    0.
+                };
+            }
+            NoiseControlStage::Control => {
+                if self.control_start.is_none() {
+                    self.control_start = Some(time as f64);
+                }
+                // the last output was calculated from the filtered reference before this sample
+                let step = self.step_size / (dot_self(&self.filtered_reference) + REGULARIZATION);
+                for (weight, filtered) in
+                    self.weights.iter_mut().zip(self.filtered_reference.iter())
+                {
+                    *weight -= step * error * filtered;
+                }
+
+                let reference_length = self.reference_length();
+                push_front(&mut self.reference, reference, reference_length);
+                let filtered = dot(&self.secondary_path, &self.reference);
+                push_front(&mut self.filtered_reference, filtered, self.filter_length);
+                self.output = dot(&self.weights, &self.reference);
+            }
+        }
+
+        self.block_sum += error.powi(2);
+        self.block_samples += 1;
+        if self.block_samples as f32 * delta_t >= LEVEL_BLOCK_DURATION {
+            self.error_levels.push([
+                time as f64,
+                pressure_to_db_spl((self.block_sum / self.block_samples as f32).sqrt()) as f64,
+            ]);
+            self.block_sum = 0.;
+            self.block_samples = 0;
+        }
+    }
+
+    /// Clears the control filter and the recorded levels, the secondary path estimate is kept
+    /// unless the identification is not finished yet.
+    pub fn reset(&mut self) {
+        self.weights.clear();
+        self.reference.clear();
+        self.filtered_reference.clear();
+        self.outputs.clear();
+        self.output = 0.;
+        self.block_sum = 0.;
+        self.block_samples = 0;
+        self.error_levels.clear();
+        self.control_start = None;
+        if self.stage == NoiseControlStage::Identification {
+            self.secondary_path.clear();
+            self.identified_time = 0.;
+        }
+    }
+
+    /// Discards the secondary path estimate and starts a new identification.
+    pub fn identify(&mut self) {
+        self.stage = NoiseControlStage::Identification;
+        self.reset();
+    }
+
+    /// The reference is needed for the control filter and the filtered reference.
+    fn reference_length(&self) -> usize {
+        self.filter_length.max(self.path_length)
+    }
+
+    fn resize(&mut self) {
+        self.weights.resize(self.filter_length, 0.);
+        self.secondary_path.resize(self.path_length, 0.);
+    }
+}
+
+fn push_front(samples: &mut VecDeque<f32>, sample: f32, length: usize) {
+    samples.push_front(sample);
+    samples.truncate(length);
+}
+
+fn dot(taps: &[f32], samples: &VecDeque<f32>) -> f32 {
+    taps.iter()
+        .zip(samples.iter())
+        .map(|(tap, sample)| tap * sample)
+        .sum()
+}
+
+fn dot_self(samples: &VecDeque<f32>) -> f32 {
+    samples.iter().map(|sample| sample * sample).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn controller_cancels_the_noise_of_a_pure_delay() {
+        let delta_t = 1. / 48_000.;
+        // the secondary source reaches the error microphone after 3 samples at half the
+        // amplitude, the noise after 10 samples
+        let (path_delay, path_gain, noise_delay) = (3, 0.5, 10);
+        let mut noise_control = NoiseControl {
+            enabled: true,
+            path_length: 16,
+            filter_length: 32,
+            // small steps average out the noise at the error microphone during the identification
+            identification_step_size: 0.01,
+            identification_duration: 0.2,
+            ..Default::default()
+        };
+
+        let reference = |sample: usize| 0.2 * white_noise(1, sample);
+        let mut outputs = VecDeque::new();
+        for sample in 0..48_000_usize {
+            push_front(&mut outputs, noise_control.output(), path_delay + 1);
+            let secondary = outputs.get(path_delay).copied().unwrap_or(0.);
+            let noise = sample.checked_sub(noise_delay).map_or(0., reference);
+            noise_control.update(
+                reference(sample),
+                noise + path_gain * secondary,
+                sample as f32 * delta_t,
+                sample,
+                delta_t,
+            );
+        }
+
+        assert_eq!(noise_control.stage, NoiseControlStage::Control);
+        for (tap, value) in noise_control.secondary_path().iter().enumerate() {
+            let expected = if tap == path_delay { path_gain } else { 0. };
+            assert!(
+                (value - expected).abs() < 0.05 * path_gain,
+                "tap {} of the secondary path is {}",
+                tap,
+                value
+            );
+        }
+
+        let noise_level = pressure_to_db_spl(0.2) as f64;
+        let final_level = noise_control.error_levels.last().unwrap()[1];
+        assert!(
+            final_level < noise_level - 20.,
+            "level at the error microphone is {} dB SPL, without control {} dB SPL",
+            final_level,
+            noise_level
+        );
+    }
+}
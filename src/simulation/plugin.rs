@@ -1,10 +1,22 @@
-use bevy::app::{App, FixedUpdate, Plugin};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use bevy::app::{App, FixedUpdate, Plugin, Update};
 use bevy::ecs::schedule::IntoSystemConfigs;
 use bevy::ecs::system::Resource;
 
+use super::frequency_maps::FrequencyMaps;
 use super::grid::Grid;
-use super::systems::{apply_system, calc_system, update_system};
+use super::levels::PressureLevels;
+use super::noise_control::NoiseControl;
+use super::systems::{
+    apply_system, calc_system, frequency_maps_system, levels_system, resample_wave_files_system,
+    update_system, wave_files_resampled,
+};
+use crate::components::source::{Filter, Source, WaveChannel};
 use crate::math::constants::INIT_BOUNDARY_WIDTH;
+use crate::math::filter::{Biquad, BiquadState};
+use crate::math::resample::resample;
 
 pub struct GridPlugin;
 
@@ -16,17 +28,32 @@ impl Plugin for GridPlugin {
         app.insert_resource(grid)
             .init_resource::<ComponentIDs>()
             .init_resource::<WaveSamples>()
+            .init_resource::<WaveResampling>()
+            .init_resource::<SampleTables>()
+            .init_resource::<FilterStates>()
+            .init_resource::<MicrophoneSignals>()
+            .init_resource::<NoiseControl>()
+            .init_resource::<PressureLevels>()
+            .init_resource::<FrequencyMaps>()
             .add_systems(
                 FixedUpdate,
-                (calc_system, apply_system, update_system).chain(),
-            );
+                (
+                    calc_system,
+                    apply_system,
+                    update_system,
+                    levels_system,
+                    frequency_maps_system,
+                )
+                    .chain()
+                    .run_if(wave_files_resampled),
+            )
+            .add_systems(Update, resample_wave_files_system);
 
         #[cfg(debug_assertions)]
         {
             use bevy::app::Startup;
 
             use crate::components::microphone::Microphone;
-            use crate::components::source::Source;
             app.add_systems(
                 Startup,
                 (
@@ -44,6 +71,10 @@ pub struct ComponentIDs {
     current_mic_id: usize,
     current_source_id: usize,
     current_wall_id: usize,
+    current_line_array_id: usize,
+    current_plane_wave_id: usize,
+    current_surface_id: usize,
+    current_source_group_id: usize,
 }
 
 impl ComponentIDs {
@@ -68,6 +99,34 @@ impl ComponentIDs {
         current
     }
 
+    /// Get a new **valid** id for a line array
+    pub fn get_new_line_array_id(&mut self) -> usize {
+        let current = self.current_line_array_id;
+        self.current_line_array_id += 1;
+        current
+    }
+
+    /// Get a new **valid** id for a plane wave
+    pub fn get_new_plane_wave_id(&mut self) -> usize {
+        let current = self.current_plane_wave_id;
+        self.current_plane_wave_id += 1;
+        current
+    }
+
+    /// Get a new **valid** id for a vibrating surface
+    pub fn get_new_surface_id(&mut self) -> usize {
+        let current = self.current_surface_id;
+        self.current_surface_id += 1;
+        current
+    }
+
+    /// Get a new **valid** id for a source group
+    pub fn get_new_source_group_id(&mut self) -> usize {
+        let current = self.current_source_group_id;
+        self.current_source_group_id += 1;
+        current
+    }
+
     /// Decrements the current wall id
     pub fn decrement_wall_ids(&mut self) {
         self.current_wall_id -= 1;
@@ -78,14 +137,536 @@ impl ComponentIDs {
         self.current_mic_id = 0;
         self.current_source_id = 0;
         self.current_wall_id = 0;
+        self.current_line_array_id = 0;
+        self.current_plane_wave_id = 0;
+        self.current_surface_id = 0;
+        self.current_source_group_id = 0;
+    }
+}
+
+/// The decoded samples of a wave file
+///
+/// The decoded channels are kept at the sample rate of the file, so that every change of the
+/// simulation sample rate resamples the original and not an already resampled copy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaveData {
+    /// samples of every channel at the sample rate of the simulation, once [`WaveResampling`] is done
+    pub channels: Vec<Vec<f32>>,
+    /// sample rate of `channels` (in Hz)
+    pub sample_rate: f32,
+    /// decoded samples of every channel
+    original: Arc<Vec<Vec<f32>>>,
+    /// sample rate of the file (in Hz)
+    original_sample_rate: f32,
+}
+
+impl WaveData {
+    pub fn new(channels: Vec<Vec<f32>>, sample_rate: f32) -> Self {
+        Self {
+            original: Arc::new(channels.clone()),
+            original_sample_rate: sample_rate,
+            channels,
+            sample_rate,
+        }
+    }
+
+    /// Returns a job that resamples all decoded channels to the given sample rate (in Hz)
+    fn resampling_job(&self, sample_rate: f32) -> impl FnOnce() -> Vec<Vec<f32>> {
+        let original = self.original.clone();
+        let original_sample_rate = self.original_sample_rate;
+        move || {
+            original
+                .iter()
+                .map(|channel| resample(channel, original_sample_rate, sample_rate))
+                .collect()
+        }
+    }
+
+    /// Replaces the played back channels with the decoded channels at the given sample rate
+    pub fn set_resampled(&mut self, channels: Vec<Vec<f32>>, sample_rate: f32) {
+        self.channels = channels;
+        self.sample_rate = sample_rate;
+    }
+
+    /// Amount of samples per channel
+    pub fn len(&self) -> usize {
+        self.channels.first().map_or(0, |channel| channel.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the sample at `index` of the given channel, or the mean of all channels when downmixing
+    pub fn sample(&self, index: usize, channel: WaveChannel) -> f32 {
+        match channel {
+            WaveChannel::Downmix => {
+                self.channels
+                    .iter()
+                    .map(|channel| channel.get(index).unwrap_or(&0.))
+                    .sum::<f32>()
+                    / self.channels.len().max(1) as f32
+            }
+            WaveChannel::Channel(channel) => *self
+                .channels
+                .get(channel)
+                .and_then(|channel| channel.get(index))
+                .unwrap_or(&0.),
+        }
     }
 }
 
-#[derive(Resource)]
-pub struct WaveSamples(pub Vec<f32>);
+/// A resource holding the decoded wave files used by [`SourceType::WaveFile`] sources, keyed by their path
+///
+/// [`SourceType::WaveFile`]: crate::components::source::SourceType::WaveFile
+#[derive(Resource, Default)]
+pub struct WaveSamples(pub HashMap<String, WaveData>);
+
+impl WaveSamples {
+    /// Returns whether all wave files are at the given sample rate (in Hz)
+    pub fn is_resampled(&self, sample_rate: f32) -> bool {
+        self.0
+            .values()
+            .all(|wave_data| wave_data.sample_rate == sample_rate)
+    }
+}
+
+/// The result of a wave file that is resampled in the background
+type ResamplingResult = Arc<Mutex<Option<Vec<Vec<f32>>>>>;
+
+/// A resource holding the results of the wave files that are resampled in the background, keyed
+/// by their path and the bits of the sample rate they are resampled to
+///
+/// Resampling a long file takes a while, so it is done outside of the simulation step.
+#[derive(Resource, Default)]
+pub struct WaveResampling(HashMap<(String, u32), ResamplingResult>);
+
+impl WaveResampling {
+    /// Starts resampling every wave file that is not at the given sample rate (in Hz)
+    /// and is not already being resampled to it.
+    ///
+    /// Jobs for other sample rates or removed files are superseded, their results are dropped.
+    pub fn start(&mut self, wave_samples: &WaveSamples, sample_rate: f32) {
+        self.0.retain(|(path, rate), _| {
+            *rate == sample_rate.to_bits() && wave_samples.0.contains_key(path)
+        });
+
+        for (path, wave_data) in wave_samples.0.iter() {
+            let key = (path.clone(), sample_rate.to_bits());
+            if wave_data.sample_rate == sample_rate || self.0.contains_key(&key) {
+                continue;
+            }
+
+            let result = Arc::new(Mutex::new(None));
+            let job_result = result.clone();
+            let resample_channels = wave_data.resampling_job(sample_rate);
+            let job = move || {
+                *job_result.lock().unwrap() = Some(resample_channels());
+            };
+            // there are no threads to spawn the job on
+            #[cfg(target_arch = "wasm32")]
+            job();
+            #[cfg(not(target_arch = "wasm32"))]
+            rayon::spawn(job);
+
+            self.0.insert(key, result);
+        }
+    }
+
+    /// Replaces the channels of the wave files whose resampling is done with their resampled version
+    pub fn finish(&mut self, wave_samples: &mut WaveSamples) {
+        self.0
+            .retain(|(path, rate), result| match result.lock().unwrap().take() {
+                Some(channels) => {
+                    if let Some(wave_data) = wave_samples.0.get_mut(path) {
+                        wave_data.set_resampled(channels, f32::from_bits(*rate));
+                    }
+                    false
+                }
+                None => true,
+            });
+    }
+}
+
+/// A signal given as (time, value) pairs, e.g. a recorded drive signal imported from a CSV file
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleTable {
+    /// (time in s, value) pairs sorted by time
+    pub points: Vec<[f32; 2]>,
+    /// sample rate the table is sampled with (in Hz)
+    sample_rate: f32,
+}
+
+impl SampleTable {
+    pub fn new(points: Vec<[f32; 2]>) -> Self {
+        Self {
+            points,
+            sample_rate: 0.,
+        }
+    }
+
+    /// Sets the sample rate of the time steps passed to [`SampleTable::sample`] (in Hz)
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// Returns the value at time step `index` (starting at 0 s), linearly interpolated between
+    /// the surrounding points. Times outside of the table have the value of the closest point.
+    pub fn sample(&self, index: usize) -> f32 {
+        if self.sample_rate <= 0. {
+            return 0.;
+        }
+
+        interpolate(
+            &self.points,
+            (index as f64 / self.sample_rate as f64) as f32,
+        )
+    }
+}
+
+/// Returns the value of (time, value) pairs sorted by time at the given time (in s), linearly
+/// interpolated between the surrounding points. Times before the first point have the value of
+/// the first point, times after the last point the value of the last point.
+pub fn interpolate(points: &[[f32; 2]], time: f32) -> f32 {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return 0.;
+    };
+    if time < first[0] {
+        return first[1];
+    }
+    if time > last[0] {
+        return last[1];
+    }
+
+    // first point after the time, the segment starts at the point before it
+    let next = points.partition_point(|point| point[0] <= time);
+    let [t0, v0] = points[next - 1];
+    match points.get(next) {
+        Some(&[t1, v1]) if t1 > t0 => v0 + (v1 - v0) * (time - t0) / (t1 - t0),
+        _ => v0,
+    }
+}
+
+/// A resource holding the sample tables used by [`SourceType::Csv`] sources, keyed by their path
+///
+/// [`SourceType::Csv`]: crate::components::source::SourceType::Csv
+#[derive(Resource, Default)]
+pub struct SampleTables(pub HashMap<String, SampleTable>);
+
+impl SampleTables {
+    /// Sets the sample rate all sample tables are sampled with (in Hz)
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        for sample_table in self.0.values_mut() {
+            sample_table.set_sample_rate(sample_rate);
+        }
+    }
+}
+
+/// The state of a single [`Filter`] of a source
+#[derive(Debug, Clone)]
+enum FilterState {
+    Biquad {
+        /// the filter and the sample rate the coefficients were calculated for
+        design: (Filter, f32),
+        coefficients: Biquad,
+        state: BiquadState,
+    },
+    /// the delayed samples, oldest first
+    Delay(VecDeque<f32>),
+    Stateless,
+}
+
+impl FilterState {
+    fn new(filter: &Filter, sample_rate: f32) -> Self {
+        match (filter, biquad_coefficients(filter, sample_rate)) {
+            (_, Some(coefficients)) => FilterState::Biquad {
+                design: (*filter, sample_rate),
+                coefficients,
+                state: BiquadState::default(),
+            },
+            (Filter::Delay { .. }, _) => FilterState::Delay(VecDeque::new()),
+            _ => FilterState::Stateless,
+        }
+    }
+
+    /// Returns whether the state is the kind of state of the filter.
+    fn belongs_to(&self, filter: &Filter) -> bool {
+        matches!(
+            (filter, self),
+            (
+                Filter::LowPass { .. } | Filter::HighPass { .. } | Filter::BandPass { .. },
+                FilterState::Biquad { .. }
+            ) | (Filter::Delay { .. }, FilterState::Delay(_))
+                | (
+                    Filter::Gain { .. } | Filter::Polarity,
+                    FilterState::Stateless
+                )
+        )
+    }
+}
+
+/// Returns the coefficients of the filters that are second order IIR filters.
+fn biquad_coefficients(filter: &Filter, sample_rate: f32) -> Option<Biquad> {
+    match *filter {
+        Filter::LowPass { frequency, q } => Some(Biquad::low_pass(frequency, q, sample_rate)),
+        Filter::HighPass { frequency, q } => Some(Biquad::high_pass(frequency, q, sample_rate)),
+        Filter::BandPass { frequency, q } => Some(Biquad::band_pass(frequency, q, sample_rate)),
+        _ => None,
+    }
+}
+
+/// A resource holding the state of the filter chains of all sources, keyed by the source id.
+///
+/// The state is kept outside of the [`Source`] component, so that it is not part of the undo state.
+#[derive(Resource, Default)]
+pub struct FilterStates(HashMap<usize, Vec<FilterState>>);
+
+impl FilterStates {
+    /// Passes the next sample of a source through its filter chain.
+    /// * `sample_rate` - The sample rate of the simulation (in Hz).
+    pub fn process(&mut self, source: &Source, mut value: f32, sample_rate: f32) -> f32 {
+        if source.filters.is_empty() {
+            return value;
+        }
+
+        let states = self.0.entry(source.id).or_default();
+        // the chain was edited, start over with empty filters
+        if states.len() != source.filters.len()
+            || source
+                .filters
+                .iter()
+                .zip(states.iter())
+                .any(|(filter, state)| !state.belongs_to(filter))
+        {
+            *states = source
+                .filters
+                .iter()
+                .map(|filter| FilterState::new(filter, sample_rate))
+                .collect();
+        }
+
+        for (filter, state) in source.filters.iter().zip(states.iter_mut()) {
+            value = match (*filter, state) {
+                (
+                    _,
+                    FilterState::Biquad {
+                        design,
+                        coefficients,
+                        state,
+                    },
+                ) => {
+                    // the coefficients are only calculated again if the filter was changed
+                    if *design != (*filter, sample_rate) {
+                        if let Some(changed) = biquad_coefficients(filter, sample_rate) {
+                            *design = (*filter, sample_rate);
+                            *coefficients = changed;
+                        }
+                    }
+                    state.process(coefficients, value)
+                }
+                (Filter::Gain { gain }, _) => value * 10_f32.powf(gain / 20.),
+                (Filter::Delay { delay }, FilterState::Delay(samples)) => {
+                    let delay_samples = (delay.max(0.) * sample_rate).round() as usize;
+                    samples.push_back(value);
+                    while samples.len() > delay_samples + 1 {
+                        samples.pop_front();
+                    }
+                    if samples.len() > delay_samples {
+                        samples.pop_front().unwrap_or(0.)
+                    } else {
+                        0.
+                    }
+                }
+                (Filter::Polarity, _) => -value,
+                _ => value,
+            };
+        }
+        value
+    }
+
+    /// Clears the state of all filters
+    pub fn reset(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// A resource holding the recent sound pressure at the microphones that sources are routed from,
+/// keyed by the microphone id.
+#[derive(Resource, Default)]
+pub struct MicrophoneSignals(HashMap<usize, VecDeque<f32>>);
+
+impl MicrophoneSignals {
+    /// Returns the sound pressure (in Pa) recorded `delay` samples before the latest one,
+    /// or 0 if the microphone did not record that far back.
+    pub fn delayed(&self, mic_id: usize, delay: usize) -> f32 {
+        self.0.get(&mic_id).map_or(0., |samples| {
+            samples
+                .len()
+                .checked_sub(delay + 1)
+                .map_or(0., |index| samples[index])
+        })
+    }
+
+    /// Appends the latest sound pressure (in Pa) of a microphone, keeping the last `length` samples.
+    pub fn push(&mut self, mic_id: usize, pressure: f32, length: usize) {
+        let samples = self.0.entry(mic_id).or_default();
+        samples.push_back(pressure);
+        while samples.len() > length.max(1) {
+            samples.pop_front();
+        }
+    }
+
+    /// Clears the recorded signals of all microphones
+    pub fn reset(&mut self) {
+        self.0.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use super::*;
+    use crate::components::source::SourceType;
+
+    /// Resamples all wave files to the given sample rate and waits for the background jobs
+    fn resample_all(
+        resampling: &mut WaveResampling,
+        wave_samples: &mut WaveSamples,
+        sample_rate: f32,
+    ) {
+        while !wave_samples.is_resampled(sample_rate) {
+            resampling.finish(wave_samples);
+            resampling.start(wave_samples, sample_rate);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn resampling_always_starts_from_the_decoded_file() {
+        let sample_rate = 48_000.;
+        let samples = (0..4800)
+            .map(|n| (TAU * 15_000. * n as f32 / sample_rate).sin())
+            .collect::<Vec<_>>();
+        let mut wave_samples = WaveSamples::default();
+        wave_samples.0.insert(
+            "tone.wav".to_string(),
+            WaveData::new(vec![samples.clone()], sample_rate),
+        );
+        let mut resampling = WaveResampling::default();
+
+        // the tone is above the nyquist frequency of 16 kHz and removed by the first resampling
+        resample_all(&mut resampling, &mut wave_samples, 16_000.);
+        resample_all(&mut resampling, &mut wave_samples, sample_rate);
+
+        assert_eq!(wave_samples.0["tone.wav"].channels, vec![samples]);
+    }
+
+    #[test]
+    fn resampling_to_a_new_rate_supersedes_running_jobs() {
+        let mut wave_samples = WaveSamples::default();
+        wave_samples.0.insert(
+            "noise.wav".to_string(),
+            WaveData::new(vec![vec![0.5; 48_000]], 48_000.),
+        );
+        let mut resampling = WaveResampling::default();
+
+        resampling.start(&wave_samples, 16_000.);
+        resampling.start(&wave_samples, 32_000.);
+        assert_eq!(
+            resampling.0.keys().cloned().collect::<Vec<_>>(),
+            vec![("noise.wav".to_string(), 32_000_f32.to_bits())]
+        );
+
+        resample_all(&mut resampling, &mut wave_samples, 32_000.);
+        assert_eq!(wave_samples.0["noise.wav"].len(), 32_000);
+    }
+
+    #[test]
+    fn delay_filter_delays_by_whole_samples() {
+        let sample_rate = 48_000.;
+        let delay = 0.0011;
+        let mut source = Source::new(0, 0, SourceType::default_sin(), 0);
+        source.filters = vec![Filter::Delay { delay }];
+        let mut filter_states = FilterStates::default();
+
+        let impulse_response = (0..100)
+            .map(|index| {
+                filter_states.process(&source, if index == 0 { 1. } else { 0. }, sample_rate)
+            })
+            .collect::<Vec<_>>();
+
+        // 52.8 samples are rounded to 53
+        let delay_samples = (delay * sample_rate).round() as usize;
+        assert_eq!(delay_samples, 53);
+        assert_eq!(
+            impulse_response.iter().position(|value| *value != 0.),
+            Some(delay_samples)
+        );
+        assert_eq!(impulse_response[delay_samples], 1.);
+    }
+
+    #[test]
+    fn biquad_coefficients_follow_the_filter() {
+        let mut source = Source::new(0, 0, SourceType::default_sin(), 0);
+        source.filters = vec![Filter::LowPass {
+            frequency: 1000.,
+            q: 1.,
+        }];
+        let mut filter_states = FilterStates::default();
+        let coefficients = |filter_states: &FilterStates| match &filter_states.0[&0][0] {
+            FilterState::Biquad { coefficients, .. } => *coefficients,
+            state => panic!("unexpected state {:?}", state),
+        };
+
+        filter_states.process(&source, 1., 48_000.);
+        assert_eq!(
+            coefficients(&filter_states),
+            Biquad::low_pass(1000., 1., 48_000.)
+        );
+
+        source.filters[0] = Filter::HighPass {
+            frequency: 200.,
+            q: 1.,
+        };
+        filter_states.process(&source, 1., 24_000.);
+        assert_eq!(
+            coefficients(&filter_states),
+            Biquad::high_pass(200., 1., 24_000.)
+        );
+    }
+
+    #[test]
+    fn sample_tables_are_interpolated_linearly_and_clamped() {
+        let points = vec![[0.1, 1.], [0.2, 3.], [0.4, -1.]];
+        for (time, expected) in [
+            (0.15, 2.),
+            (0.2, 3.),
+            (0.3, 1.),
+            // before the first and after the last point
+            (0., 1.),
+            (1., -1.),
+        ] {
+            let value = interpolate(&points, time);
+            assert!(
+                (value - expected).abs() < 1e-5,
+                "value at {} s is {} instead of {}",
+                time,
+                value,
+                expected
+            );
+        }
+        assert_eq!(interpolate(&[], 0.), 0.);
 
-impl Default for WaveSamples {
-    fn default() -> Self {
-        Self(vec![0.])
+        let mut sample_table = SampleTable::new(points);
+        // without a sample rate there are no time steps
+        assert_eq!(sample_table.sample(3), 0.);
+        sample_table.set_sample_rate(10.);
+        assert_eq!(
+            (0..6)
+                .map(|index| (sample_table.sample(index) * 1e3).round() / 1e3)
+                .collect::<Vec<_>>(),
+            vec![1., 1., 3., 1., -1., -1.]
+        );
     }
 }
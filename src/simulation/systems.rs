@@ -1,9 +1,17 @@
 use bevy::prelude::*;
 
+use super::frequency_maps::FrequencyMaps;
 use super::grid::Grid;
-use super::plugin::WaveSamples;
+use super::levels::PressureLevels;
+use super::noise_control::NoiseControl;
+use super::plugin::{FilterStates, MicrophoneSignals, SampleTables, WaveResampling, WaveSamples};
+use crate::components::line_array::LineArray;
 use crate::components::microphone::Microphone;
+use crate::components::plane_wave::PlaneWave;
 use crate::components::source::Source;
+use crate::components::source_group::SourceGroup;
+use crate::components::vibrating_surface::VibratingSurface;
+use crate::components::wall::{CircWall, RectWall};
 use crate::ui::state::{SimTime, UiState};
 
 /// A system used to calculate reflection pulses per cell
@@ -19,23 +27,110 @@ pub fn calc_system(mut grid: ResMut<Grid>, ui_state: Res<UiState>) {
 pub fn apply_system(
     mut grid: ResMut<Grid>,
     sources: Query<&Source>,
+    groups: Query<&SourceGroup>,
+    line_arrays: Query<&LineArray>,
+    plane_waves: Query<&PlaneWave>,
+    surfaces: Query<&VibratingSurface>,
+    rect_walls: Query<&RectWall>,
+    circ_walls: Query<&CircWall>,
     microphones: Query<&mut Microphone>,
     sim_time: Res<SimTime>,
     ui_state: Res<UiState>,
     wave_samples: Res<WaveSamples>,
+    mut sample_tables: ResMut<SampleTables>,
+    mut filter_states: ResMut<FilterStates>,
+    mut mic_signals: ResMut<MicrophoneSignals>,
+    mut noise_control: ResMut<NoiseControl>,
 ) {
     if ui_state.is_running {
+        sample_tables.set_sample_rate(1. / grid.delta_t);
+        // record before applying the sources, so a delay of 0 routes the current sound pressure
+        grid.record_routed_microphones(
+            &sources,
+            &groups,
+            &microphones,
+            ui_state.boundary_width,
+            &mut mic_signals,
+        );
         grid.apply_sources(
             sim_time.time_since_start,
             sim_time.samples_since_start,
             &sources,
+            &groups,
             ui_state.boundary_width,
             &wave_samples,
+            &sample_tables,
+            &mut filter_states,
+            &mic_signals,
+            &noise_control,
         );
+        grid.apply_line_arrays(
+            sim_time.time_since_start,
+            sim_time.samples_since_start,
+            &line_arrays,
+            ui_state.boundary_width,
+            &wave_samples,
+            &sample_tables,
+        );
+        grid.apply_plane_waves(
+            sim_time.time_since_start,
+            sim_time.samples_since_start,
+            &plane_waves,
+            ui_state.boundary_width,
+            &wave_samples,
+            &sample_tables,
+        );
+        grid.apply_vibrating_surfaces(
+            sim_time.time_since_start,
+            sim_time.samples_since_start,
+            &surfaces,
+            &rect_walls,
+            &circ_walls,
+            ui_state.boundary_width,
+            &wave_samples,
+            &sample_tables,
+        );
+        if noise_control.enabled {
+            let pressure_at = |mic_id: usize| {
+                microphones
+                    .iter()
+                    .find(|mic| mic.id == mic_id)
+                    .map(|mic| grid.pressure_at(mic.x, mic.y, ui_state.boundary_width))
+            };
+            if let (Some(reference), Some(error)) = (
+                pressure_at(noise_control.reference_mic),
+                pressure_at(noise_control.error_mic),
+            ) {
+                noise_control.update(
+                    reference,
+                    error,
+                    sim_time.time_since_start,
+                    sim_time.samples_since_start,
+                    grid.delta_t,
+                );
+            } else {
+                noise_control.mute();
+            }
+        }
         grid.apply_microphones(microphones, &ui_state, sim_time.time_since_start as f64);
     }
 }
 
+/// A system used to resample wave files to the sample rate of the simulation in the background
+pub fn resample_wave_files_system(
+    grid: Res<Grid>,
+    mut wave_samples: ResMut<WaveSamples>,
+    mut resampling: ResMut<WaveResampling>,
+) {
+    resampling.finish(&mut wave_samples);
+    resampling.start(&wave_samples, 1. / grid.delta_t);
+}
+
+/// A run condition that holds the simulation until all wave files are resampled to its sample rate
+pub fn wave_files_resampled(grid: Res<Grid>, wave_samples: Res<WaveSamples>) -> bool {
+    wave_samples.is_resampled(1. / grid.delta_t)
+}
+
 /// A system used to write reflection pulses into the incident pulses,
 ///
 /// update delta t
@@ -53,3 +148,27 @@ pub fn update_system(
         sim_time.samples_since_start += 1;
     }
 }
+
+/// A system used to accumulate the pressure of every cell while a level is rendered
+pub fn levels_system(grid: Res<Grid>, ui_state: Res<UiState>, mut levels: ResMut<PressureLevels>) {
+    if ui_state.is_running && ui_state.render_mode.shows_levels() {
+        levels.accumulate(&grid.pressure);
+    }
+}
+
+/// A system used to transform the pressure of every cell while a frequency map is rendered
+pub fn frequency_maps_system(
+    grid: Res<Grid>,
+    ui_state: Res<UiState>,
+    sim_time: Res<SimTime>,
+    mut frequency_maps: ResMut<FrequencyMaps>,
+) {
+    if ui_state.is_running && ui_state.render_mode.shows_frequency_maps() {
+        // the pressure was calculated for the time step before the update
+        frequency_maps.accumulate(
+            &grid.pressure,
+            sim_time.samples_since_start.saturating_sub(1),
+            grid.delta_t,
+        );
+    }
+}
@@ -1,24 +1,40 @@
 use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
 use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy_file_dialog::FileDialogExt;
 use bevy_pixel_buffer::bevy_egui::egui::{Color32, Frame, Margin, Vec2};
 use bevy_pixel_buffer::bevy_egui::EguiContexts;
 use bevy_pixel_buffer::prelude::*;
 use egui::ImageSource;
 
+use super::export::{
+    encode_csv, encode_level_map, encode_wav, CsvExportContents, WavExportContents,
+};
 use super::keybinds::draw_keybinds;
 use super::preferences::draw_preferences;
-use super::tabs::{DockState, PlotTabs};
+use super::tabs::{DockState, PlotCache, PlotTabs};
 use crate::components::gizmo::GizmoComponent;
+use crate::components::line_array::{ArrayWindow, LineArray};
 use crate::components::microphone::*;
+use crate::components::plane_wave::PlaneWave;
 use crate::components::source::*;
+use crate::components::source_group::SourceGroup;
 use crate::components::states::{MenuSelected, Selected};
+use crate::components::vibrating_surface::{SurfaceEdge, SurfaceMode, VibratingSurface};
 use crate::components::wall::{CircWall, RectWall, WResize};
-use crate::events::{LoadScene, LoadWav, New, Reset, Save, UpdateWalls};
+use crate::events::{LoadCsv, LoadScene, LoadWav, New, Reset, Save, TimeReversal, UpdateWalls};
+use crate::math::calibration::{
+    db_spl_to_pressure, plane_wave_intensity, pressure_to_db_spl, sound_power_per_length,
+    velocity_to_pressure,
+};
 use crate::math::constants::*;
 use crate::render::gradient::Gradient;
 use crate::render::screenshot::screenshot_grid;
+use crate::simulation::frequency_maps::FrequencyMaps;
 use crate::simulation::grid::Grid;
+use crate::simulation::levels::PressureLevels;
+use crate::simulation::noise_control::NoiseControl;
+use crate::simulation::plugin::{ComponentIDs, WaveSamples};
 use crate::ui::state::*;
 use crate::undo::{UndoEvent, UndoRedo};
 
@@ -38,7 +54,20 @@ pub struct EventSystemParams<'w> {
     pub save_ev: EventWriter<'w, Save>,
     pub load_scene_ev: EventWriter<'w, LoadScene>,
     pub load_wav_ev: EventWriter<'w, LoadWav>,
+    pub load_csv_ev: EventWriter<'w, LoadCsv>,
     pub new_ev: EventWriter<'w, New>,
+    pub time_reversal_ev: EventWriter<'w, TimeReversal>,
+}
+
+#[derive(SystemParam)]
+pub struct ResourceSystemParams<'w> {
+    pub wave_samples: Res<'w, WaveSamples>,
+    pub error_message: ResMut<'w, ErrorMessage>,
+    pub noise_control: ResMut<'w, NoiseControl>,
+    pub ids: ResMut<'w, ComponentIDs>,
+    pub levels: ResMut<'w, PressureLevels>,
+    pub frequency_maps: ResMut<'w, FrequencyMaps>,
+    pub plot_cache: ResMut<'w, PlotCache>,
 }
 
 type AllRectWallsMut<'w, 's> = Query<'w, 's, (Entity, &'static mut RectWall)>;
@@ -65,6 +94,30 @@ type AllMicsMenuSelected<'w, 's> =
     Query<'w, 's, (Entity, &'static mut Microphone), With<MenuSelected>>;
 type AllMics<'w, 's> = Query<'w, 's, &'static Microphone>;
 
+type AllLineArraysMut<'w, 's> = Query<'w, 's, (Entity, &'static mut LineArray)>;
+type AllLineArraysSelected<'w, 's> =
+    Query<'w, 's, (Entity, &'static mut LineArray), With<Selected>>;
+type AllLineArraysMenuSelected<'w, 's> =
+    Query<'w, 's, (Entity, &'static mut LineArray), With<MenuSelected>>;
+type AllLineArrays<'w, 's> = Query<'w, 's, &'static LineArray>;
+
+type AllPlaneWavesMut<'w, 's> = Query<'w, 's, (Entity, &'static mut PlaneWave)>;
+type AllPlaneWavesSelected<'w, 's> =
+    Query<'w, 's, (Entity, &'static mut PlaneWave), With<Selected>>;
+type AllPlaneWavesMenuSelected<'w, 's> =
+    Query<'w, 's, (Entity, &'static mut PlaneWave), With<MenuSelected>>;
+type AllPlaneWaves<'w, 's> = Query<'w, 's, &'static PlaneWave>;
+
+type AllSurfacesMut<'w, 's> = Query<'w, 's, (Entity, &'static mut VibratingSurface)>;
+type AllSurfacesSelected<'w, 's> =
+    Query<'w, 's, (Entity, &'static mut VibratingSurface), With<Selected>>;
+type AllSurfacesMenuSelected<'w, 's> =
+    Query<'w, 's, (Entity, &'static mut VibratingSurface), With<MenuSelected>>;
+type AllSurfaces<'w, 's> = Query<'w, 's, &'static VibratingSurface>;
+
+type AllSourceGroupsMut<'w, 's> = Query<'w, 's, (Entity, &'static mut SourceGroup)>;
+type AllSourceGroups<'w, 's> = Query<'w, 's, &'static SourceGroup>;
+
 #[derive(SystemParam)]
 pub struct QuerySystemParams<'w, 's> {
     rect_wall_set: ParamSet<
@@ -107,6 +160,37 @@ pub struct QuerySystemParams<'w, 's> {
             AllMics<'w, 's>,
         ),
     >,
+    line_array_set: ParamSet<
+        'w,
+        's,
+        (
+            AllLineArraysMut<'w, 's>,
+            AllLineArraysSelected<'w, 's>,
+            AllLineArraysMenuSelected<'w, 's>,
+            AllLineArrays<'w, 's>,
+        ),
+    >,
+    plane_wave_set: ParamSet<
+        'w,
+        's,
+        (
+            AllPlaneWavesMut<'w, 's>,
+            AllPlaneWavesSelected<'w, 's>,
+            AllPlaneWavesMenuSelected<'w, 's>,
+            AllPlaneWaves<'w, 's>,
+        ),
+    >,
+    surface_set: ParamSet<
+        'w,
+        's,
+        (
+            AllSurfacesMut<'w, 's>,
+            AllSurfacesSelected<'w, 's>,
+            AllSurfacesMenuSelected<'w, 's>,
+            AllSurfaces<'w, 's>,
+        ),
+    >,
+    source_group_set: ParamSet<'w, 's, (AllSourceGroupsMut<'w, 's>, AllSourceGroups<'w, 's>)>,
 }
 
 pub const CTRL_KEY_TEXT: &str = if cfg!(target_os = "macos") {
@@ -130,6 +214,7 @@ pub fn draw_egui(
     mut grid: ResMut<Grid>,
     mut gradient: ResMut<Gradient>,
     mut events: EventSystemParams,
+    mut resources: ResourceSystemParams,
     sets: QuerySystemParams,
     mut dock_state: ResMut<DockState>,
     mut app_exit_events: ResMut<Events<bevy::app::AppExit>>,
@@ -147,6 +232,10 @@ pub fn draw_egui(
         mut circ_wall_set,
         mut source_set,
         mut mic_set,
+        mut line_array_set,
+        mut plane_wave_set,
+        mut surface_set,
+        mut source_group_set,
     } = sets;
 
     let ctx = egui_context.ctx_mut();
@@ -224,6 +313,24 @@ pub fn draw_egui(
         ui_state.read_epilepsy_warning = read_epilepsy_warning;
     }
 
+    if let Some(error_message) = resources.error_message.0.clone() {
+        let mut show_error = true;
+        egui::Window::new("Error")
+            .default_size(Vec2::new(400., 400.))
+            .resizable(false)
+            .collapsible(false)
+            .constrain(true)
+            .show(ctx, |ui| {
+                ui.label(error_message);
+                if ui.button("Ok").clicked() {
+                    show_error = false;
+                }
+            });
+        if !show_error {
+            resources.error_message.0 = None;
+        }
+    }
+
     if ui_state.show_new_warning {
         let mut show_new_warning = true;
         egui::Window::new("Save changes")
@@ -299,7 +406,14 @@ pub fn draw_egui(
                     {
                         ui.close_menu();
 
-                        screenshot_grid(&ui_state, &grid, &gradient, &mut commands)
+                        screenshot_grid(
+                            &ui_state,
+                            &grid,
+                            &resources.levels,
+                            &resources.frequency_maps,
+                            &gradient,
+                            &mut commands,
+                        )
                     }
 
                     if ui
@@ -370,6 +484,15 @@ pub fn draw_egui(
                     ui.set_min_width(ui.available_width());
 
                     // Sources
+                    let mut mic_ids = mic_set.p3().iter().map(|mic| mic.id).collect::<Vec<_>>();
+                    mic_ids.sort();
+                    let mut group_ids = source_group_set
+                        .p1()
+                        .iter()
+                        .map(|group| group.id)
+                        .collect::<Vec<_>>();
+                    group_ids.sort();
+
                     let binding = source_set.p1();
                     let selected_source = binding.iter().next();
                     let selected_source = selected_source
@@ -390,6 +513,31 @@ pub fn draw_egui(
                                 None
                             })
                             .show(ui, |ui| {
+                                ui.horizontal(|ui| {
+                                    if ui
+                                        .checkbox(&mut source.enabled, "Enabled")
+                                        .on_hover_text("Disabled sources are not simulated")
+                                        .changed()
+                                    {
+                                        events.reset_ev.send(Reset::default());
+                                    }
+                                    ui.add_enabled_ui(source.enabled, |ui| {
+                                        if ui
+                                            .toggle_value(&mut source.muted, "Mute")
+                                            .changed()
+                                        {
+                                            events.reset_ev.send(Reset::default());
+                                        }
+                                        if ui
+                                            .toggle_value(&mut source.solo, "Solo")
+                                            .on_hover_text("If any source is soloed, only the soloed sources are audible")
+                                            .changed()
+                                        {
+                                            events.reset_ev.send(Reset::default());
+                                        }
+                                    });
+                                });
+
                                 ui.horizontal(|ui| {
                                     ui.label("x:");
                                     if ui
@@ -416,172 +564,616 @@ pub fn draw_egui(
                                     }
                                 });
 
-                                egui::ComboBox::from_label("Waveform")
-                                    .selected_text(format!("{}", source.source_type))
+                                let group_text = |group: Option<usize>| {
+                                    group.map_or("None".to_string(), |id| format!("Group {}", id))
+                                };
+                                egui::ComboBox::from_label("Source group")
+                                    .selected_text(group_text(source.group))
                                     .show_ui(ui, |ui| {
-                                        ui.selectable_value(
-                                            &mut source.source_type,
-                                            SourceType::default_sin(),
-                                            "Sinus",
-                                        );
-                                        ui.selectable_value(
-                                            &mut source.source_type,
-                                            SourceType::default_periodic_gauss(),
-                                            "Periodic Gauss",
-                                        );
-                                        ui.selectable_value(
-                                            &mut source.source_type,
-                                            SourceType::default_gauss_impulse(),
-                                            "Gauss Impulse",
-                                        );
-                                        ui.selectable_value(
-                                            &mut source.source_type,
-                                            SourceType::default_noise(),
-                                            "White noise",
-                                        );
-                                        if ui_state.wave_files {
-                                            ui.selectable_value(
-                                                &mut source.source_type,
-                                                SourceType::default_wave(),
-                                                "Wave file",
-                                            );
-                                        }
-                                    });
-
-                                match &mut source.source_type {
-                                    SourceType::Sin {
-                                        phase,
-                                        frequency,
-                                        amplitude,
-                                    } => {
-                                        if ui
-                                            .add(
-                                                egui::Slider::new(frequency, 20.0..=20000.0)
-                                                    .logarithmic(true)
-                                                    .text("Frequency (Hz)"),
-                                            )
-                                            .changed()
+                                        for group in
+                                            std::iter::once(None).chain(group_ids.iter().copied().map(Some))
                                         {
-                                            events.reset_ev.send(Reset::default());
+                                            if ui
+                                                .selectable_value(&mut source.group, group, group_text(group))
+                                                .changed()
+                                            {
+                                                events.reset_ev.send(Reset::default());
+                                            }
                                         }
                                         if ui
-                                            .add(
-                                                egui::Slider::new(amplitude, 0.0..=25.0)
-                                                    .text("Amplitude"),
-                                            )
-                                            .changed()
+                                            .selectable_label(false, "New group")
+                                            .on_hover_text("Creates a group with the signal of this source")
+                                            .clicked()
                                         {
+                                            let id = resources.ids.get_new_source_group_id();
+                                            commands.spawn(SourceGroup {
+                                                id,
+                                                source_type: source.source_type.clone(),
+                                            });
+                                            source.group = Some(id);
                                             events.reset_ev.send(Reset::default());
                                         }
+                                    });
+
+                                match source.group {
+                                    Some(group) if group_ids.contains(&group) => {
+                                        ui.label(format!("The signal is set by group {}", group));
                                         if ui
                                             .add(
-                                                egui::Slider::new(phase, 0.0..=360.0)
-                                                    .text("Phase (°)"),
+                                                egui::Slider::new(&mut source.phase_offset, 0.0..=360.0)
+                                                    .text("Phase offset (°)"),
                                             )
+                                            .on_hover_text("Phase shift of periodic group signals")
                                             .changed()
                                         {
                                             events.reset_ev.send(Reset::default());
                                         }
                                     }
-                                    SourceType::PeriodicGauss {
-                                        phase,
-                                        frequency,
-                                        amplitude,
-                                        std_dev,
-                                    } => {
-                                        if ui
-                                            .add(
-                                                egui::Slider::new(frequency, 20.0..=20000.0)
-                                                    .logarithmic(true)
-                                                    .text("Frequency (Hz)"),
-                                            )
-                                            .changed()
-                                        {
-                                            events.reset_ev.send(Reset::default());
+                                    _ => signal_ui(
+                                        ui,
+                                        SignalTarget::Source(source.id),
+                                        &mut source.source_type,
+                                        &ui_state,
+                                        &mut events,
+                                        &resources.wave_samples,
+                                        &mic_ids,
+                                    ),
+                                }
+
+                                ui.separator();
+
+                                egui::ComboBox::from_label("Directivity")
+                                    .selected_text(format!("{}", source.directivity))
+                                    .show_ui(ui, |ui| {
+                                        for directivity in [
+                                            Directivity::Omnidirectional,
+                                            Directivity::Dipole,
+                                            Directivity::Cardioid,
+                                            Directivity::default_multipole(),
+                                        ] {
+                                            let selected = std::mem::discriminant(
+                                                &source.directivity,
+                                            ) == std::mem::discriminant(&directivity);
+                                            if ui
+                                                .selectable_label(
+                                                    selected,
+                                                    format!("{}", directivity),
+                                                )
+                                                .clicked()
+                                                && !selected
+                                            {
+                                                source.directivity = directivity;
+                                                events.reset_ev.send(Reset::default());
+                                            }
                                         }
+                                    });
+                                if let Directivity::Multipole { monopole, dipole } =
+                                    &mut source.directivity
+                                {
+                                    if ui
+                                        .add(
+                                            egui::Slider::new(monopole, -1.0..=1.0)
+                                                .text("Monopole weight"),
+                                        )
+                                        .changed()
+                                    {
+                                        events.reset_ev.send(Reset::default());
+                                    }
+                                    if ui
+                                        .add(
+                                            egui::Slider::new(dipole, -1.0..=1.0)
+                                                .text("Dipole weight"),
+                                        )
+                                        .on_hover_text("The pattern is monopole + dipole * cos(θ - orientation)")
+                                        .changed()
+                                    {
+                                        events.reset_ev.send(Reset::default());
+                                    }
+                                }
+                                if source.directivity != Directivity::Omnidirectional
+                                    && ui
+                                        .add(
+                                            egui::Slider::new(&mut source.orientation, 0.0..=360.0)
+                                                .text("Orientation (°)"),
+                                        )
+                                        .changed()
+                                {
+                                    events.reset_ev.send(Reset::default());
+                                }
+
+                                ui.separator();
+
+                                ui.label("Filter chain")
+                                    .on_hover_text("The filters are applied to the signal in order");
+                                if filter_chain_ui(ui, &mut source.filters) {
+                                    events.reset_ev.send(Reset::default());
+                                }
+
+                                if ui
+                                    .add(egui::Button::new("Delete").fill(Color32::DARK_RED))
+                                    .clicked()
+                                {
+                                    commands.entity(*entity).despawn();
+                                }
+                            });
+                        if collapse.header_response.contains_pointer()
+                            || collapse.body_response.is_some()
+                        {
+                            commands.entity(*entity).try_insert(MenuSelected);
+                        } else {
+                            commands.entity(*entity).remove::<MenuSelected>();
+                        }
+                    });
+
+                    if !source_set.p0().is_empty() {
+                        ui.separator();
+                    }
+
+                    // Source groups
+                    let mut members = source_set
+                        .p3()
+                        .iter()
+                        .filter_map(|source| source.group.map(|group| (group, source.id)))
+                        .collect::<Vec<_>>();
+                    members.sort();
+
+                    let mut binding = source_group_set.p0();
+                    let mut group_vec = binding.iter_mut().collect::<Vec<_>>();
+                    group_vec.sort_by_cached_key(|(_, group)| group.id);
+
+                    group_vec.iter_mut().for_each(|(entity, ref mut group)| {
+                        egui::CollapsingHeader::new(format!("Source group {}", group.id))
+                            .open(if ui_state.collapse_header {
+                                Some(false)
+                            } else {
+                                None
+                            })
+                            .show(ui, |ui| {
+                                let group_members = members
+                                    .iter()
+                                    .filter(|(id, _)| *id == group.id)
+                                    .map(|(_, source)| source.to_string())
+                                    .collect::<Vec<_>>();
+                                ui.label(if group_members.is_empty() {
+                                    "No members".to_string()
+                                } else {
+                                    format!("Sources: {}", group_members.join(", "))
+                                });
+
+                                signal_ui(
+                                    ui,
+                                    SignalTarget::SourceGroup(group.id),
+                                    &mut group.source_type,
+                                    &ui_state,
+                                    &mut events,
+                                    &resources.wave_samples,
+                                    &[],
+                                );
+
+                                if ui
+                                    .add(egui::Button::new("Delete").fill(Color32::DARK_RED))
+                                    .on_hover_text("The members play their own signal again")
+                                    .clicked()
+                                {
+                                    commands.entity(*entity).despawn();
+                                    events.reset_ev.send(Reset::default());
+                                }
+                            });
+                    });
+
+                    if !source_group_set.p0().is_empty() {
+                        ui.separator();
+                    }
+
+                    // Line arrays
+                    let binding = line_array_set.p1();
+                    let selected_line_array = binding.iter().next();
+                    let selected_line_array = selected_line_array
+                        .map(|(_, line_array)| line_array.id as i32)
+                        .unwrap_or(-1_i32);
+
+                    let mut binding = line_array_set.p0();
+                    let mut line_array_vec = binding.iter_mut().collect::<Vec<_>>();
+                    line_array_vec.sort_by_cached_key(|(_, line_array)| line_array.id);
+
+                    line_array_vec.iter_mut().for_each(|(entity, ref mut line_array)| {
+                        let collapse =
+                            egui::CollapsingHeader::new(format!("Line array {}", line_array.id))
+                                .open(if selected_line_array == line_array.id as i32 {
+                                    Some(true)
+                                } else if ui_state.collapse_header {
+                                    Some(false)
+                                } else {
+                                    None
+                                })
+                                .show(ui, |ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label("x:");
                                         if ui
                                             .add(
-                                                egui::Slider::new(amplitude, 0.0..=25.0)
-                                                    .text("Amplitude"),
+                                                egui::DragValue::new(&mut line_array.x)
+                                                    .speed(1)
+                                                    .range(0.0..=SIMULATION_WIDTH as f32 - 1.),
                                             )
                                             .changed()
                                         {
                                             events.reset_ev.send(Reset::default());
                                         }
+                                        ui.add_space(10.);
+                                        ui.label("y:");
                                         if ui
                                             .add(
-                                                egui::Slider::new(phase, 0.0..=360.0)
-                                                    .text("Phase (°)"),
+                                                egui::DragValue::new(&mut line_array.y)
+                                                    .speed(1)
+                                                    .range(0.0..=SIMULATION_HEIGHT as f32 - 1.),
                                             )
                                             .changed()
                                         {
                                             events.reset_ev.send(Reset::default());
                                         }
-                                        if ui
-                                            .add(
-                                                egui::Slider::new(std_dev, 0.0..=1.0)
-                                                    .text("Standard deviation"),
+                                    });
+
+                                    if ui
+                                        .add(
+                                            egui::Slider::new(&mut line_array.elements, 1..=64)
+                                                .text("Elements"),
+                                        )
+                                        .changed()
+                                    {
+                                        events.reset_ev.send(Reset::default());
+                                    }
+                                    if ui
+                                        .add(
+                                            egui::Slider::new(&mut line_array.spacing, 0.01..=1.0)
+                                                .logarithmic(true)
+                                                .text("Spacing (m)"),
+                                        )
+                                        .changed()
+                                    {
+                                        events.reset_ev.send(Reset::default());
+                                    }
+                                    if ui
+                                        .add(
+                                            egui::Slider::new(
+                                                &mut line_array.rotation,
+                                                0.0..=360.0,
                                             )
-                                            .changed()
-                                        {
-                                            events.reset_ev.send(Reset::default());
-                                        }
+                                            .text("Rotation (°)"),
+                                        )
+                                        .on_hover_text("Direction the array is facing")
+                                        .changed()
+                                    {
+                                        events.reset_ev.send(Reset::default());
+                                    }
+                                    if ui
+                                        .add(
+                                            egui::Slider::new(
+                                                &mut line_array.arc_angle,
+                                                0.0..=180.0,
+                                            )
+                                            .text("Arc angle (°)"),
+                                        )
+                                        .on_hover_text("Opening angle of the arc the elements are placed on, 0 places them on a straight line")
+                                        .changed()
+                                    {
+                                        events.reset_ev.send(Reset::default());
                                     }
-                                    SourceType::GaussImpulse {
-                                        amplitude,
-                                        std_dev,
-                                    } => {
+                                    if ui
+                                        .add(
+                                            egui::Slider::new(
+                                                &mut line_array.steering_angle,
+                                                -90.0..=90.0,
+                                            )
+                                            .text("Steering angle (°)"),
+                                        )
+                                        .on_hover_text("Angle of the main lobe relative to the facing direction")
+                                        .changed()
+                                    {
+                                        events.reset_ev.send(Reset::default());
+                                    }
+                                    ui.label(format!(
+                                        "Delay between elements: {:.4} ms",
+                                        line_array.element_delay() * 1000.
+                                    ));
+
+                                    egui::ComboBox::from_label("Window")
+                                        .selected_text(format!("{}", line_array.window))
+                                        .show_ui(ui, |ui| {
+                                            for window in [
+                                                ArrayWindow::Uniform,
+                                                ArrayWindow::Hann,
+                                                ArrayWindow::Chebyshev {
+                                                    sidelobe_level: 30.,
+                                                },
+                                            ] {
+                                                let selected = std::mem::discriminant(
+                                                    &line_array.window,
+                                                ) == std::mem::discriminant(&window);
+                                                if ui
+                                                    .selectable_label(
+                                                        selected,
+                                                        format!("{}", window),
+                                                    )
+                                                    .clicked()
+                                                    && !selected
+                                                {
+                                                    line_array.window = window;
+                                                    events.reset_ev.send(Reset::default());
+                                                }
+                                            }
+                                        });
+                                    if let ArrayWindow::Chebyshev { sidelobe_level } =
+                                        &mut line_array.window
+                                    {
                                         if ui
                                             .add(
-                                                egui::Slider::new(amplitude, 0.0..=25.0)
-                                                    .text("Amplitude"),
+                                                egui::Slider::new(sidelobe_level, 10.0..=100.0)
+                                                    .text("Side lobe attenuation (dB)"),
                                             )
                                             .changed()
                                         {
                                             events.reset_ev.send(Reset::default());
                                         }
+                                    }
+
+                                    ui.separator();
+
+                                    signal_ui(
+                                        ui,
+                                        SignalTarget::LineArray(line_array.id),
+                                        &mut line_array.source_type,
+                                        &ui_state,
+                                        &mut events,
+                                        &resources.wave_samples,
+                                        &[],
+                                    );
+
+                                    if ui
+                                        .add(egui::Button::new("Delete").fill(Color32::DARK_RED))
+                                        .clicked()
+                                    {
+                                        commands.entity(*entity).despawn();
+                                    }
+                                });
+                        if collapse.header_response.contains_pointer()
+                            || collapse.body_response.is_some()
+                        {
+                            commands.entity(*entity).try_insert(MenuSelected);
+                        } else {
+                            commands.entity(*entity).remove::<MenuSelected>();
+                        }
+                    });
+
+                    if !line_array_set.p0().is_empty() {
+                        ui.separator();
+                    }
+
+                    // Plane waves
+                    let binding = plane_wave_set.p1();
+                    let selected_plane_wave = binding.iter().next();
+                    let selected_plane_wave = selected_plane_wave
+                        .map(|(_, plane_wave)| plane_wave.id as i32)
+                        .unwrap_or(-1_i32);
+
+                    let mut binding = plane_wave_set.p0();
+                    let mut plane_wave_vec = binding.iter_mut().collect::<Vec<_>>();
+                    plane_wave_vec.sort_by_cached_key(|(_, plane_wave)| plane_wave.id);
+
+                    plane_wave_vec.iter_mut().for_each(|(entity, ref mut plane_wave)| {
+                        let collapse =
+                            egui::CollapsingHeader::new(format!("Plane wave {}", plane_wave.id))
+                                .open(if selected_plane_wave == plane_wave.id as i32 {
+                                    Some(true)
+                                } else if ui_state.collapse_header {
+                                    Some(false)
+                                } else {
+                                    None
+                                })
+                                .show(ui, |ui| {
+                                    if ui
+                                        .add(
+                                            egui::Slider::new(&mut plane_wave.angle, 0.0..=360.0)
+                                                .text("Angle (°)"),
+                                        )
+                                        .on_hover_text("Direction the wave is traveling in")
+                                        .changed()
+                                    {
+                                        events.reset_ev.send(Reset::default());
+                                    }
+
+                                    ui.separator();
+
+                                    signal_ui(
+                                        ui,
+                                        SignalTarget::PlaneWave(plane_wave.id),
+                                        &mut plane_wave.source_type,
+                                        &ui_state,
+                                        &mut events,
+                                        &resources.wave_samples,
+                                        &[],
+                                    );
+
+                                    if ui
+                                        .add(egui::Button::new("Delete").fill(Color32::DARK_RED))
+                                        .clicked()
+                                    {
+                                        commands.entity(*entity).despawn();
+                                    }
+                                });
+                        if collapse.header_response.contains_pointer()
+                            || collapse.body_response.is_some()
+                        {
+                            commands.entity(*entity).try_insert(MenuSelected);
+                        } else {
+                            commands.entity(*entity).remove::<MenuSelected>();
+                        }
+                    });
+
+                    if !plane_wave_set.p0().is_empty() {
+                        ui.separator();
+                    }
+
+                    // Vibrating surfaces
+                    let rect_wall_ids = rect_wall_set
+                        .p3()
+                        .iter()
+                        .map(|wall| wall.id)
+                        .collect::<Vec<_>>();
+                    let circ_wall_ids = circ_wall_set
+                        .p3()
+                        .iter()
+                        .map(|wall| wall.id)
+                        .collect::<Vec<_>>();
+
+                    let binding = surface_set.p1();
+                    let selected_surface = binding.iter().next();
+                    let selected_surface = selected_surface
+                        .map(|(_, surface)| surface.id as i32)
+                        .unwrap_or(-1_i32);
+
+                    let mut binding = surface_set.p0();
+                    let mut surface_vec = binding.iter_mut().collect::<Vec<_>>();
+                    surface_vec.sort_by_cached_key(|(_, surface)| surface.id);
+
+                    surface_vec.iter_mut().for_each(|(entity, ref mut surface)| {
+                        let collapse = egui::CollapsingHeader::new(format!(
+                            "Vibrating surface {}",
+                            surface.id
+                        ))
+                        .open(if selected_surface == surface.id as i32 {
+                            Some(true)
+                        } else if ui_state.collapse_header {
+                            Some(false)
+                        } else {
+                            None
+                        })
+                        .show(ui, |ui| {
+                            let is_circ_wall = circ_wall_ids.contains(&surface.wall_id);
+                            egui::ComboBox::from_label("Wall")
+                                .selected_text(if is_circ_wall {
+                                    format!("Circular wall {}", surface.wall_id)
+                                } else if rect_wall_ids.contains(&surface.wall_id) {
+                                    format!("Rectangular wall {}", surface.wall_id)
+                                } else {
+                                    "No wall".to_string()
+                                })
+                                .show_ui(ui, |ui| {
+                                    for id in &rect_wall_ids {
                                         if ui
-                                            .add(
-                                                egui::Slider::new(std_dev, 0.0..=1.0)
-                                                    .logarithmic(true)
-                                                    .text("Standard deviation"),
+                                            .selectable_label(
+                                                surface.wall_id == *id,
+                                                format!("Rectangular wall {}", id),
                                             )
-                                            .changed()
+                                            .clicked()
                                         {
+                                            surface.wall_id = *id;
+                                            surface.edge = SurfaceEdge::Top;
                                             events.reset_ev.send(Reset::default());
                                         }
                                     }
-                                    SourceType::WhiteNoise { amplitude } => {
+                                    for id in &circ_wall_ids {
                                         if ui
-                                            .add(
-                                                egui::Slider::new(amplitude, 0.0..=25.0)
-                                                    .text("Amplitude"),
+                                            .selectable_label(
+                                                surface.wall_id == *id,
+                                                format!("Circular wall {}", id),
                                             )
-                                            .changed()
+                                            .clicked()
                                         {
+                                            surface.wall_id = *id;
+                                            surface.edge = SurfaceEdge::Arc {
+                                                start: -45.,
+                                                end: 45.,
+                                            };
                                             events.reset_ev.send(Reset::default());
                                         }
                                     }
-                                    SourceType::WaveFile { amplitude } => {
+                                });
+
+                            if let SurfaceEdge::Arc { start, end } = &mut surface.edge {
+                                if ui
+                                    .add(egui::Slider::new(start, -360.0..=360.0).text("Start (°)"))
+                                    .changed()
+                                {
+                                    events.reset_ev.send(Reset::default());
+                                }
+                                if ui
+                                    .add(egui::Slider::new(end, -360.0..=360.0).text("End (°)"))
+                                    .on_hover_text("The arc goes counterclockwise from start to end")
+                                    .changed()
+                                {
+                                    events.reset_ev.send(Reset::default());
+                                }
+                            } else {
+                                egui::ComboBox::from_label("Edge")
+                                    .selected_text(format!("{}", surface.edge))
+                                    .show_ui(ui, |ui| {
+                                        for edge in [
+                                            SurfaceEdge::Top,
+                                            SurfaceEdge::Bottom,
+                                            SurfaceEdge::Left,
+                                            SurfaceEdge::Right,
+                                        ] {
+                                            if ui
+                                                .selectable_value(
+                                                    &mut surface.edge,
+                                                    edge,
+                                                    format!("{}", edge),
+                                                )
+                                                .changed()
+                                            {
+                                                events.reset_ev.send(Reset::default());
+                                            }
+                                        }
+                                    });
+                            }
+
+                            egui::ComboBox::from_label("Mode")
+                                .selected_text(format!("{}", surface.mode))
+                                .show_ui(ui, |ui| {
+                                    for mode in
+                                        [SurfaceMode::Piston, SurfaceMode::Flexural { order: 1 }]
+                                    {
+                                        let selected = std::mem::discriminant(&surface.mode)
+                                            == std::mem::discriminant(&mode);
                                         if ui
-                                            .add(
-                                                egui::Slider::new(amplitude, 0.0..=1000.0)
-                                                    .text("Amplitude"),
-                                            )
-                                            .changed()
+                                            .selectable_label(selected, format!("{}", mode))
+                                            .clicked()
+                                            && !selected
                                         {
+                                            surface.mode = mode;
                                             events.reset_ev.send(Reset::default());
                                         }
-                                    },
-                                }
-
+                                    }
+                                });
+                            if let SurfaceMode::Flexural { order } = &mut surface.mode {
                                 if ui
-                                    .add(egui::Button::new("Delete").fill(Color32::DARK_RED))
-                                    .clicked()
+                                    .add(egui::Slider::new(order, 1..=10).text("Order"))
+                                    .on_hover_text("Number of half waves along the surface")
+                                    .changed()
                                 {
-                                    commands.entity(*entity).despawn();
+                                    events.reset_ev.send(Reset::default());
                                 }
-                            });
+                            }
+
+                            ui.separator();
+
+                            signal_ui(
+                                ui,
+                                SignalTarget::Surface(surface.id),
+                                &mut surface.source_type,
+                                &ui_state,
+                                &mut events,
+                                &resources.wave_samples,
+                                &[],
+                            );
+
+                            if ui
+                                .add(egui::Button::new("Delete").fill(Color32::DARK_RED))
+                                .clicked()
+                            {
+                                commands.entity(*entity).despawn();
+                            }
+                        });
                         if collapse.header_response.contains_pointer()
                             || collapse.body_response.is_some()
                         {
@@ -591,7 +1183,7 @@ pub fn draw_egui(
                         }
                     });
 
-                    if !source_set.p0().is_empty() {
+                    if !surface_set.p0().is_empty() {
                         ui.separator();
                     }
 
@@ -645,28 +1237,39 @@ pub fn draw_egui(
                                         }
                                         if ui_state.show_mic_export && ui
                                             .add(egui::Button::new("Export CSV"))
-                                            .on_hover_text("Export all past time/value pairs as CSV in the current directory. (Values are only recorded if the plot is opened)")
+                                            .on_hover_text("Export all past time/value pairs as CSV. (Values are only recorded if the plot is opened)")
                                             .clicked()
                                         {
-                                            // TODO: file picker?
-                                            let id = mic.id;
-                                            mic.write_to_file(&format!("mic_{}.csv", id));
+                                            match encode_csv(&[mic], grid.delta_t, ui_state.delta_l) {
+                                                Ok(contents) => commands
+                                                    .dialog()
+                                                    .add_filter("CSV", &["csv"])
+                                                    .set_file_name(format!("microphone_{}.csv", mic.id))
+                                                    .set_directory("./")
+                                                    .set_title("Select a file to save to")
+                                                    .save_file::<CsvExportContents>(contents),
+                                                Err(err) => resources.error_message.0 = Some(err),
+                                            }
                                         }
-                                        if ui_state.wave_files && ui
+                                        if ui_state.show_mic_export && ui
                                             .add(egui::Button::new("Export wav"))
+                                            .on_hover_text("Export the recording as a wave file with the format and normalization set in the volume plot")
                                             .clicked()
                                         {
-                                            //TODO: write wav file
-                                            let spec = hound::WavSpec {
-                                                channels: 1,
-                                                sample_rate: (1. / grid.delta_t) as u32,
-                                                bits_per_sample: 16,
-                                                sample_format: hound::SampleFormat::Int,
-                                            };
-                                            let mut writer = hound::WavWriter::create("testout.wav", spec).unwrap();
-                                            for sample in mic.record.iter().map(|s| s[1]) {
-                                                let amplitude = i16::MAX as f64;
-                                                writer.write_sample((sample * amplitude) as i16).unwrap();
+                                            match encode_wav(
+                                                &[mic.record.iter().map(|record| record[1]).collect()],
+                                                (1. / grid.delta_t).round() as u32,
+                                                ui_state.wav_sample_format,
+                                                ui_state.wav_normalization,
+                                            ) {
+                                                Ok(contents) => commands
+                                                    .dialog()
+                                                    .add_filter("WAV", &["wav"])
+                                                    .set_file_name(format!("microphone_{}.wav", mic.id))
+                                                    .set_directory("./")
+                                                    .set_title("Select a file to save to")
+                                                    .save_file::<WavExportContents>(contents),
+                                                Err(err) => resources.error_message.0 = Some(err),
                                             }
                                         }
                                     });
@@ -681,6 +1284,13 @@ pub fn draw_egui(
                     });
 
                     if !mic_set.p0().is_empty() {
+                        if ui
+                            .button("Time-reversal mirror")
+                            .on_hover_text("Replaces the microphones with sources that re-emit their time-reversed recordings and the sources with microphones, so the wave refocuses at the original sources. The microphones record while the plots are shown")
+                            .clicked()
+                        {
+                            events.time_reversal_ev.send(TimeReversal);
+                        }
                         ui.separator();
                     }
 
@@ -1011,6 +1621,18 @@ pub fn draw_egui(
                         for (e, _) in mic_set.p0().iter() {
                             commands.entity(e).despawn();
                         }
+                        for (e, _) in line_array_set.p0().iter() {
+                            commands.entity(e).despawn();
+                        }
+                        for (e, _) in plane_wave_set.p0().iter() {
+                            commands.entity(e).despawn();
+                        }
+                        for (e, _) in surface_set.p0().iter() {
+                            commands.entity(e).despawn();
+                        }
+                        for (e, _) in source_group_set.p0().iter() {
+                            commands.entity(e).despawn();
+                        }
 
                         grid.reset_cells(ui_state.boundary_width);
                         events.wall_update_ev.send(UpdateWalls);
@@ -1057,6 +1679,84 @@ pub fn draw_egui(
 
                 ui.add_space(5.);
 
+                ui.horizontal_wrapped(|ui| {
+                    let render_mode = ui_state.render_mode;
+                    egui::ComboBox::from_id_salt("render_mode")
+                        .selected_text(render_mode.to_string())
+                        .show_ui(ui, |ui| {
+                            for mode in [
+                                RenderMode::Pressure,
+                                RenderMode::RmsLevel,
+                                RenderMode::PeakLevel,
+                                RenderMode::Magnitude,
+                                RenderMode::Phase,
+                            ] {
+                                ui.selectable_value(
+                                    &mut ui_state.render_mode,
+                                    mode,
+                                    mode.to_string(),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text("Show a map accumulated since the last reset instead of the pressure");
+                    // the maps are only accumulated while they are shown, so they start over
+                    // when they are shown again
+                    if ui_state.render_mode.shows_levels() && !render_mode.shows_levels() {
+                        resources.levels.reset();
+                    }
+                    if ui_state.render_mode.shows_frequency_maps()
+                        && !render_mode.shows_frequency_maps()
+                    {
+                        resources.frequency_maps.reset();
+                    }
+
+                    if ui_state.render_mode.shows_frequency_maps() {
+                        frequency_maps_ui(ui, &mut resources.frequency_maps, grid.delta_t);
+                    }
+
+                    if ui_state.render_mode != RenderMode::Pressure {
+                        ui.add(
+                            egui::DragValue::new(&mut ui_state.min_level_gradient)
+                                .speed(0.5)
+                                .suffix(" dB"),
+                        )
+                        .on_hover_text("Level of the lowest color");
+                        ui.add(
+                            egui::DragValue::new(&mut ui_state.max_level_gradient)
+                                .speed(0.5)
+                                .suffix(" dB"),
+                        )
+                        .on_hover_text("Level of the highest color");
+
+                        if ui
+                            .button("Export levels")
+                            .on_hover_text("Save the level of every cell to a CSV file, use the screenshot to save the image")
+                            .clicked()
+                        {
+                            match encode_level_map(
+                                &resources.levels,
+                                &resources.frequency_maps,
+                                ui_state.render_mode,
+                                ui_state.boundary_width,
+                                grid.delta_t,
+                                ui_state.delta_l,
+                            ) {
+                                Ok(contents) => commands
+                                    .dialog()
+                                    .add_filter("CSV", &["csv"])
+                                    .set_file_name("levels.csv")
+                                    .set_directory("./")
+                                    .set_title("Select a file to save to")
+                                    .save_file::<CsvExportContents>(contents),
+                                Err(err) => resources.error_message.0 = Some(err),
+                            }
+                        }
+                    }
+                });
+
+                ui.add_space(5.);
+
                 ui.horizontal(|ui| {
                     ui.label(format!(
                         "Time: {:.5} ms",
@@ -1120,6 +1820,22 @@ pub fn draw_egui(
                                     PlaceType::CircWall,
                                     "Circular Wall",
                                 );
+                                ui.selectable_value(
+                                    &mut ui_state.cur_place_type,
+                                    PlaceType::LineArray,
+                                    "Line Array",
+                                );
+                                ui.selectable_value(
+                                    &mut ui_state.cur_place_type,
+                                    PlaceType::PlaneWave,
+                                    "Plane Wave",
+                                );
+                                ui.selectable_value(
+                                    &mut ui_state.cur_place_type,
+                                    PlaceType::VibratingSurface,
+                                    "Vibrating Surface",
+                                )
+                                .on_hover_text("Click on the edge of a wall to let it vibrate");
                             });
 
                         if matches!(
@@ -1159,6 +1875,13 @@ pub fn draw_egui(
                 bottom: 0.,
             }))
             .show(ctx, |ui| {
+                let mut source_ids = source_set
+                    .p3()
+                    .iter()
+                    .map(|source| source.id)
+                    .collect::<Vec<_>>();
+                source_ids.sort();
+
                 let mut binding = mic_set.p0();
 
                 let mut mics = binding
@@ -1183,6 +1906,10 @@ pub fn draw_egui(
                             sim_time.time_since_start as f64,
                             time.delta_seconds_f64(),
                             &mut ui_state,
+                            &mut resources.noise_control,
+                            &source_ids,
+                            &mut resources.error_message,
+                            &mut resources.plot_cache,
                         ),
                     );
             });
@@ -1303,6 +2030,10 @@ pub fn draw_egui(
 
             if !ui_state.render_abc_area && !ui_state.hide_gizmos {
                 let painter = ui.painter();
+                let rect_walls = rect_wall_set.p3().iter().copied().collect::<Vec<_>>();
+                let rect_walls = rect_walls.iter().collect::<Vec<_>>();
+                let circ_walls = circ_wall_set.p3().iter().copied().collect::<Vec<_>>();
+                let circ_walls = circ_walls.iter().collect::<Vec<_>>();
                 //menu gizmos
                 if !ui_state.tools_enabled {
                     for (_, wall) in rect_wall_set.p2().iter() {
@@ -1351,6 +2082,40 @@ pub fn draw_egui(
                             *gradient,
                         );
                     }
+                    // all line arrays
+                    for (_, line_array) in line_array_set.p2().iter() {
+                        line_array.draw_gizmo(
+                            painter,
+                            &ToolType::Edit,
+                            true,
+                            &ui_state.image_rect,
+                            Some(&format!("{}", line_array.id)),
+                            ui_state.delta_l,
+                            *gradient,
+                        );
+                    }
+                    // all vibrating surfaces
+                    for (_, surface) in surface_set.p2().iter() {
+                        surface.draw_gizmo(
+                            painter,
+                            &surface.calc_elements(&rect_walls, &circ_walls),
+                            true,
+                            &ui_state.image_rect,
+                            *gradient,
+                        );
+                    }
+                    // all plane waves
+                    for (_, plane_wave) in plane_wave_set.p2().iter() {
+                        plane_wave.draw_gizmo(
+                            painter,
+                            &ToolType::Edit,
+                            true,
+                            &ui_state.image_rect,
+                            Some(&format!("{}", plane_wave.id)),
+                            ui_state.delta_l,
+                            *gradient,
+                        );
+                    }
                 } else {
                     // TODO: drawing selected gizmos on top means that the text is also drawn twice
                     // Tool specific gizmos
@@ -1450,9 +2215,608 @@ pub fn draw_egui(
                             *gradient,
                         );
                     }
+                    // all line arrays
+                    for line_array in line_array_set.p3().iter() {
+                        line_array.draw_gizmo(
+                            painter,
+                            &ui_state.current_tool,
+                            false,
+                            &ui_state.image_rect,
+                            Some(&format!("{}", line_array.id)),
+                            ui_state.delta_l,
+                            *gradient,
+                        );
+                    }
+                    // selected line arrays
+                    for (_, line_array) in line_array_set.p1().iter() {
+                        line_array.draw_gizmo(
+                            painter,
+                            &ui_state.current_tool,
+                            true,
+                            &ui_state.image_rect,
+                            Some(&format!("{}", line_array.id)),
+                            ui_state.delta_l,
+                            *gradient,
+                        );
+                    }
+                    // all vibrating surfaces
+                    for surface in surface_set.p3().iter() {
+                        surface.draw_gizmo(
+                            painter,
+                            &surface.calc_elements(&rect_walls, &circ_walls),
+                            false,
+                            &ui_state.image_rect,
+                            *gradient,
+                        );
+                    }
+                    // selected vibrating surfaces
+                    for (_, surface) in surface_set.p1().iter() {
+                        surface.draw_gizmo(
+                            painter,
+                            &surface.calc_elements(&rect_walls, &circ_walls),
+                            true,
+                            &ui_state.image_rect,
+                            *gradient,
+                        );
+                    }
+                    // all plane waves
+                    for plane_wave in plane_wave_set.p3().iter() {
+                        plane_wave.draw_gizmo(
+                            painter,
+                            &ui_state.current_tool,
+                            false,
+                            &ui_state.image_rect,
+                            Some(&format!("{}", plane_wave.id)),
+                            ui_state.delta_l,
+                            *gradient,
+                        );
+                    }
+                    // selected plane waves
+                    for (_, plane_wave) in plane_wave_set.p1().iter() {
+                        plane_wave.draw_gizmo(
+                            painter,
+                            &ui_state.current_tool,
+                            true,
+                            &ui_state.image_rect,
+                            Some(&format!("{}", plane_wave.id)),
+                            ui_state.delta_l,
+                            *gradient,
+                        );
+                    }
                 }
             }
         });
 
     ui_state.collapse_header = false;
 }
+
+/// Draws the waveform selection and the settings of a signal.
+/// * `target` - The object the signal belongs to, used when a file is loaded for it.
+/// * `mic_ids` - The ids of the microphones the signal can be routed from, only sources can be
+///   driven by a microphone.
+fn signal_ui(
+    ui: &mut egui::Ui,
+    target: SignalTarget,
+    source_type: &mut SourceType,
+    ui_state: &UiState,
+    events: &mut EventSystemParams,
+    wave_samples: &WaveSamples,
+    mic_ids: &[usize],
+) {
+    egui::ComboBox::from_label("Waveform")
+        .selected_text(format!("{}", source_type))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(source_type, SourceType::default_sin(), "Sinus");
+            ui.selectable_value(
+                source_type,
+                SourceType::default_periodic_gauss(),
+                "Periodic Gauss",
+            );
+            ui.selectable_value(
+                source_type,
+                SourceType::default_gauss_impulse(),
+                "Gauss Impulse",
+            );
+            ui.selectable_value(source_type, SourceType::default_noise(), "White noise");
+            ui.selectable_value(source_type, SourceType::default_expression(), "Expression");
+            ui.selectable_value(source_type, SourceType::default_csv(), "CSV file");
+            if ui_state.wave_files {
+                ui.selectable_value(source_type, SourceType::default_wave(), "Wave file");
+            }
+            if let (SignalTarget::Source(_), Some(&mic_id)) = (target, mic_ids.first()) {
+                if !matches!(source_type, SourceType::Microphone { .. }) {
+                    ui.selectable_value(
+                        source_type,
+                        SourceType::default_microphone(mic_id),
+                        "Microphone",
+                    );
+                }
+            }
+        });
+
+    let rms_factor = source_type.rms_factor();
+    match source_type {
+        SourceType::Sin {
+            phase,
+            frequency,
+            amplitude,
+        } => {
+            if ui
+                .add(
+                    egui::Slider::new(frequency, 20.0..=20000.0)
+                        .logarithmic(true)
+                        .text("Frequency (Hz)"),
+                )
+                .changed()
+            {
+                events.reset_ev.send(Reset::default());
+            }
+            if amplitude_ui(ui, amplitude, 0.0..=25.0, rms_factor, target) {
+                events.reset_ev.send(Reset::default());
+            }
+            if ui
+                .add(egui::Slider::new(phase, 0.0..=360.0).text("Phase (°)"))
+                .changed()
+            {
+                events.reset_ev.send(Reset::default());
+            }
+        }
+        SourceType::PeriodicGauss {
+            phase,
+            frequency,
+            amplitude,
+            std_dev,
+        } => {
+            if ui
+                .add(
+                    egui::Slider::new(frequency, 20.0..=20000.0)
+                        .logarithmic(true)
+                        .text("Frequency (Hz)"),
+                )
+                .changed()
+            {
+                events.reset_ev.send(Reset::default());
+            }
+            if amplitude_ui(ui, amplitude, 0.0..=25.0, rms_factor, target) {
+                events.reset_ev.send(Reset::default());
+            }
+            if ui
+                .add(egui::Slider::new(phase, 0.0..=360.0).text("Phase (°)"))
+                .changed()
+            {
+                events.reset_ev.send(Reset::default());
+            }
+            if ui
+                .add(egui::Slider::new(std_dev, 0.0..=1.0).text("Standard deviation"))
+                .changed()
+            {
+                events.reset_ev.send(Reset::default());
+            }
+        }
+        SourceType::GaussImpulse { amplitude, std_dev } => {
+            if amplitude_ui(ui, amplitude, 0.0..=1.0, rms_factor, target) {
+                events.reset_ev.send(Reset::default());
+            }
+            if ui
+                .add(
+                    egui::Slider::new(std_dev, 0.0..=1.0)
+                        .logarithmic(true)
+                        .text("Standard deviation"),
+                )
+                .changed()
+            {
+                events.reset_ev.send(Reset::default());
+            }
+        }
+        SourceType::WhiteNoise { amplitude } => {
+            if amplitude_ui(ui, amplitude, 0.0..=25.0, rms_factor, target) {
+                events.reset_ev.send(Reset::default());
+            }
+        }
+        SourceType::Expression {
+            expression,
+            amplitude,
+        } => {
+            let mut text = expression.text().to_string();
+            if ui
+                .add(
+                    egui::TextEdit::singleline(&mut text)
+                        .code_editor()
+                        .hint_text("sin(2*pi*440*t)"),
+                )
+                .on_hover_text("Signal as a function of the time t (in s). Supported are + - * / % ^, pi, tau, e and functions like sin, cos, exp, ln, log, sqrt, abs, step, min and max.")
+                .changed()
+            {
+                expression.set_text(text);
+                events.reset_ev.send(Reset::default());
+            }
+            if let Some(error) = expression.error() {
+                ui.colored_label(Color32::LIGHT_RED, error);
+            }
+            if amplitude_ui(ui, amplitude, 0.0..=25.0, rms_factor, target) {
+                events.reset_ev.send(Reset::default());
+            }
+        }
+        SourceType::WaveFile {
+            path,
+            channel,
+            amplitude,
+            playback,
+            start_offset,
+        } => {
+            ui.horizontal(|ui| {
+                if ui.button("Load wave file").clicked() {
+                    events.load_wav_ev.send(LoadWav { target });
+                }
+                ui.label(if path.is_empty() {
+                    "No file loaded"
+                } else {
+                    std::path::Path::new(path.as_str())
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .unwrap_or(path.as_str())
+                })
+                .on_hover_text(path.as_str());
+            });
+            let channel_count = wave_samples
+                .0
+                .get(path.as_str())
+                .map_or(0, |wave_data| wave_data.channels.len());
+            egui::ComboBox::from_label("Channel")
+                .selected_text(format!("{}", channel))
+                .show_ui(ui, |ui| {
+                    if ui
+                        .selectable_value(
+                            channel,
+                            WaveChannel::Downmix,
+                            format!("{}", WaveChannel::Downmix),
+                        )
+                        .changed()
+                    {
+                        events.reset_ev.send(Reset::default());
+                    }
+                    for index in 0..channel_count {
+                        if ui
+                            .selectable_value(
+                                channel,
+                                WaveChannel::Channel(index),
+                                format!("{}", WaveChannel::Channel(index)),
+                            )
+                            .changed()
+                        {
+                            events.reset_ev.send(Reset::default());
+                        }
+                    }
+                });
+            if amplitude_ui(ui, amplitude, 0.0..=25.0, rms_factor, target) {
+                events.reset_ev.send(Reset::default());
+            }
+            egui::ComboBox::from_label("Playback")
+                .selected_text(format!("{}", playback))
+                .show_ui(ui, |ui| {
+                    for mode in [Playback::Loop, Playback::OneShot] {
+                        if ui
+                            .selectable_value(playback, mode, format!("{}", mode))
+                            .changed()
+                        {
+                            events.reset_ev.send(Reset::default());
+                        }
+                    }
+                });
+            if ui
+                .add(egui::Slider::new(start_offset, 0.0..=10.0).text("Start offset (s)"))
+                .changed()
+            {
+                events.reset_ev.send(Reset::default());
+            }
+        }
+        SourceType::Microphone {
+            mic_id,
+            delay,
+            gain,
+        } => {
+            egui::ComboBox::from_label("Microphone")
+                .selected_text(format!("Microphone {}", mic_id))
+                .show_ui(ui, |ui| {
+                    for id in mic_ids {
+                        if ui
+                            .selectable_value(mic_id, *id, format!("Microphone {}", id))
+                            .changed()
+                        {
+                            events.reset_ev.send(Reset::default());
+                        }
+                    }
+                });
+            if !mic_ids.contains(mic_id) {
+                ui.colored_label(Color32::LIGHT_RED, "The microphone does not exist");
+            }
+            let mut delay_ms = *delay * 1000.;
+            if ui
+                .add(egui::Slider::new(&mut delay_ms, 0.0..=100.0).text("Delay (ms)"))
+                .changed()
+            {
+                *delay = delay_ms / 1000.;
+                events.reset_ev.send(Reset::default());
+            }
+            if ui
+                .add(
+                    egui::Slider::new(gain, 0.0..=10.0)
+                        .text("Gain")
+                        .logarithmic(true),
+                )
+                .on_hover_text("Sound pressure at 1 m per sound pressure at the microphone")
+                .changed()
+            {
+                events.reset_ev.send(Reset::default());
+            }
+        }
+        SourceType::Recording {
+            mic_id,
+            points,
+            amplitude,
+        } => {
+            ui.label(format!(
+                "Recording of microphone {} ({:.1} ms)",
+                mic_id,
+                points.last().map_or(0., |point| point[0]) * 1000.
+            ));
+            if amplitude_ui(ui, amplitude, 0.0..=25.0, rms_factor, target) {
+                events.reset_ev.send(Reset::default());
+            }
+        }
+        SourceType::Csv { path, amplitude } => {
+            ui.horizontal(|ui| {
+                if ui.button("Load CSV file").clicked() {
+                    events
+                        .load_csv_ev
+                        .send(LoadCsv { target });
+                }
+                ui.label(if path.is_empty() {
+                    "No file loaded"
+                } else {
+                    std::path::Path::new(path.as_str())
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .unwrap_or(path.as_str())
+                })
+                .on_hover_text(path.as_str());
+            })
+            .response
+            .on_hover_text("Two columns: time (in s) and value. The signal is linearly interpolated and holds the first and last value outside of the table.");
+            if amplitude_ui(ui, amplitude, 0.0..=25.0, rms_factor, target) {
+                events.reset_ev.send(Reset::default());
+            }
+        }
+    }
+
+    // plane waves and surfaces are not point sources, their pressure does not depend on the frequency
+    if source_type.is_broadband()
+        && !matches!(
+            target,
+            SignalTarget::PlaneWave(_) | SignalTarget::Surface(_)
+        )
+    {
+        ui.label(format!("Calibrated at {} Hz", CALIBRATION_FREQUENCY))
+            .on_hover_text("The sound pressure of a point source at 1 m depends on the frequency, so the amplitude and level of a broadband signal are only exact at this frequency");
+    }
+}
+
+/// Draws the amplitude of a source (in Pa at 1 m) together with the resulting sound pressure level
+/// and sound power. Returns whether the amplitude was changed.
+/// * `rms_factor` - The factor that converts the amplitude to the RMS sound pressure of the source.
+/// * `target` - The object the signal belongs to. The signal of a plane wave is shown with its
+///   intensity and the signal of a vibrating surface is a velocity.
+fn amplitude_ui(
+    ui: &mut egui::Ui,
+    amplitude: &mut f32,
+    range: std::ops::RangeInclusive<f32>,
+    rms_factor: f32,
+    target: SignalTarget,
+) -> bool {
+    if let SignalTarget::Surface(_) = target {
+        let changed = ui
+            .add(
+                egui::Slider::new(amplitude, 0.0..=*range.end() / 25.)
+                    .text("Velocity amplitude (m/s)"),
+            )
+            .changed();
+        ui.label(format!(
+            "Radiated sound pressure: {:.1} dB SPL",
+            pressure_to_db_spl(velocity_to_pressure(*amplitude * rms_factor)).max(0.)
+        ))
+        .on_hover_text("Sound pressure level of the plane wave radiated by a large rigid surface");
+        return changed;
+    }
+
+    let plane_wave = matches!(target, SignalTarget::PlaneWave(_));
+    let mut changed = ui
+        .add(egui::Slider::new(amplitude, range).text(if plane_wave {
+            "Amplitude (Pa)"
+        } else {
+            "Amplitude at 1 m (Pa)"
+        }))
+        .changed();
+
+    if plane_wave {
+        ui.horizontal(|ui| {
+            let mut level = pressure_to_db_spl(*amplitude * rms_factor).max(0.);
+            if ui
+                .add(
+                    egui::DragValue::new(&mut level)
+                        .speed(0.1)
+                        .range(0.0..=150.0)
+                        .suffix(" dB SPL"),
+                )
+                .changed()
+            {
+                *amplitude = db_spl_to_pressure(level) / rms_factor;
+                changed = true;
+            }
+            ui.add(egui::Separator::default().vertical());
+            ui.label(format!(
+                "Intensity: {:.2e} W/m²",
+                plane_wave_intensity(*amplitude * rms_factor)
+            ));
+        });
+        return changed;
+    }
+
+    ui.horizontal(|ui| {
+        let mut level = pressure_to_db_spl(*amplitude * rms_factor).max(0.);
+        if ui
+            .add(
+                egui::DragValue::new(&mut level)
+                    .speed(0.1)
+                    .range(0.0..=150.0)
+                    .suffix(" dB SPL"),
+            )
+            .on_hover_text("Sound pressure level at a distance of 1 m in the free field")
+            .changed()
+        {
+            *amplitude = db_spl_to_pressure(level) / rms_factor;
+            changed = true;
+        }
+        ui.label("at 1 m");
+        ui.add(egui::Separator::default().vertical());
+        ui.label(format!(
+            "Sound power: {:.2e} W/m",
+            sound_power_per_length(*amplitude * rms_factor)
+        ))
+        .on_hover_text("Radiated sound power per meter of the (two dimensional) line source");
+    });
+
+    changed
+}
+
+/// Draws the filter chain of a source, returns true if the chain was changed.
+fn filter_chain_ui(ui: &mut egui::Ui, filters: &mut Vec<Filter>) -> bool {
+    let mut changed = false;
+    let mut move_up = None;
+    let mut remove = None;
+    let count = filters.len();
+
+    for (index, filter) in filters.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(format!("{}. {}", index + 1, filter));
+            if ui
+                .add_enabled(index > 0, egui::Button::new("⏶"))
+                .on_hover_text("Move up")
+                .clicked()
+            {
+                move_up = Some(index);
+            }
+            if ui
+                .add_enabled(index + 1 < count, egui::Button::new("⏷"))
+                .on_hover_text("Move down")
+                .clicked()
+            {
+                move_up = Some(index + 1);
+            }
+            if ui.button("✖").on_hover_text("Remove").clicked() {
+                remove = Some(index);
+            }
+        });
+
+        changed |= match filter {
+            Filter::LowPass { frequency, q }
+            | Filter::HighPass { frequency, q }
+            | Filter::BandPass { frequency, q } => {
+                ui.add(
+                    egui::Slider::new(frequency, 20.0..=20000.0)
+                        .logarithmic(true)
+                        .text("Frequency (Hz)"),
+                )
+                .changed()
+                    | ui.add(egui::Slider::new(q, 0.1..=20.0).logarithmic(true).text("Q"))
+                        .changed()
+            }
+            Filter::Gain { gain } => ui
+                .add(egui::Slider::new(gain, -40.0..=40.0).text("Gain (dB)"))
+                .changed(),
+            Filter::Delay { delay } => {
+                let mut delay_ms = *delay * 1000.;
+                let changed = ui
+                    .add(egui::Slider::new(&mut delay_ms, 0.0..=100.0).text("Delay (ms)"))
+                    .changed();
+                *delay = delay_ms / 1000.;
+                changed
+            }
+            Filter::Polarity => false,
+        };
+    }
+
+    if let Some(index) = move_up {
+        filters.swap(index - 1, index);
+        changed = true;
+    }
+    if let Some(index) = remove {
+        filters.remove(index);
+        changed = true;
+    }
+
+    egui::ComboBox::from_id_salt("add_filter")
+        .selected_text("Add filter")
+        .show_ui(ui, |ui| {
+            for filter in Filter::defaults() {
+                if ui.selectable_label(false, format!("{}", filter)).clicked() {
+                    filters.push(filter);
+                    changed = true;
+                }
+            }
+        });
+
+    changed
+}
+
+/// Draws the frequencies of the frequency maps, the selected one is shown.
+/// * `delta_t` - The time step of the simulation (in s), the frequencies are limited to the
+///   Nyquist frequency.
+fn frequency_maps_ui(ui: &mut egui::Ui, frequency_maps: &mut FrequencyMaps, delta_t: f32) {
+    let mut selected = frequency_maps.selected;
+    egui::ComboBox::from_id_salt("frequency_map_select")
+        .selected_text(format!("Map {}", selected + 1))
+        .show_ui(ui, |ui| {
+            for (index, frequency) in frequency_maps.frequencies.iter().enumerate() {
+                ui.selectable_value(
+                    &mut selected,
+                    index,
+                    format!("Map {} ({} Hz)", index + 1, frequency),
+                );
+            }
+        })
+        .response
+        .on_hover_text("All maps are calculated at the same time, select the one that is shown");
+    frequency_maps.selected = selected;
+
+    let nyquist = (0.5 / delta_t).min(20000.);
+    if let Some(frequency) = frequency_maps.frequencies.get_mut(selected) {
+        if ui
+            .add(
+                egui::DragValue::new(frequency)
+                    .speed(1.)
+                    .range(1.0..=nyquist)
+                    .suffix(" Hz"),
+            )
+            .changed()
+        {
+            frequency_maps.reset_map(selected);
+        }
+    }
+
+    if ui
+        .button("Add")
+        .on_hover_text("Add a map at another frequency")
+        .clicked()
+    {
+        let frequency = frequency_maps.selected_frequency().unwrap_or(1000.);
+        frequency_maps.add(frequency);
+    }
+    if frequency_maps.frequencies.len() > 1
+        && ui
+            .button("Remove")
+            .on_hover_text("Remove the shown map")
+            .clicked()
+    {
+        frequency_maps.remove_selected();
+    }
+}
@@ -0,0 +1,439 @@
+use std::fmt;
+use std::io::Cursor;
+
+use bevy::prelude::*;
+use bevy_file_dialog::{DialogFileSaved, SaveContents};
+
+use super::state::{ErrorMessage, RenderMode};
+use crate::components::microphone::Microphone;
+use crate::math::constants::{SIMULATION_HEIGHT, SIMULATION_WIDTH};
+use crate::math::fft::Spectrum;
+use crate::math::room_acoustics::RoomParameters;
+use crate::math::transformations::coords_to_index;
+use crate::simulation::frequency_maps::FrequencyMaps;
+use crate::simulation::levels::PressureLevels;
+
+/// Marker component for the file dialog and the corresponding event.
+pub struct WavExportContents;
+
+/// Marker component for the file dialog and the corresponding event.
+pub struct CsvExportContents;
+
+/// Marker component for the file dialog and the corresponding event.
+pub struct SvgExportContents;
+
+/// The sample format of an exported wave file
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum WavSampleFormat {
+    #[default]
+    Int16,
+    Int24,
+    Float32,
+}
+
+impl fmt::Display for WavSampleFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WavSampleFormat::Int16 => write!(f, "16 bit integer"),
+            WavSampleFormat::Int24 => write!(f, "24 bit integer"),
+            WavSampleFormat::Float32 => write!(f, "32 bit float"),
+        }
+    }
+}
+
+/// How the recorded sound pressure is scaled to the samples of an exported wave file
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum WavNormalization {
+    /// the highest absolute value of all channels is scaled to full scale
+    #[default]
+    Peak,
+    /// a full scale sample corresponds to a fixed sound pressure, so levels can be compared
+    /// between files
+    Calibrated {
+        /// sound pressure of a full scale sample (in Pa)
+        full_scale: f32,
+    },
+}
+
+impl fmt::Display for WavNormalization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WavNormalization::Peak => write!(f, "Peak"),
+            WavNormalization::Calibrated { .. } => write!(f, "Calibrated"),
+        }
+    }
+}
+
+/// Encodes recorded sound pressures (in Pa) as a wave file with one channel per recording.
+///
+/// Shorter recordings are padded with silence. Integer samples are clipped at full scale.
+/// * `sample_rate` - The sample rate of the recordings (in Hz).
+pub fn encode_wav(
+    channels: &[Vec<f64>],
+    sample_rate: u32,
+    format: WavSampleFormat,
+    normalization: WavNormalization,
+) -> Result<Vec<u8>, String> {
+    if channels.is_empty() {
+        return Err("There are no recordings to export".to_string());
+    }
+
+    let (bits_per_sample, sample_format) = match format {
+        WavSampleFormat::Int16 => (16, hound::SampleFormat::Int),
+        WavSampleFormat::Int24 => (24, hound::SampleFormat::Int),
+        WavSampleFormat::Float32 => (32, hound::SampleFormat::Float),
+    };
+    let spec = hound::WavSpec {
+        channels: channels.len() as u16,
+        sample_rate,
+        bits_per_sample,
+        sample_format,
+    };
+
+    let scale = match normalization {
+        WavNormalization::Peak => {
+            let peak = channels
+                .iter()
+                .flatten()
+                .fold(0., |peak: f64, sample| peak.max(sample.abs()));
+            if peak > 0. {
+                1. / peak
+            } else {
+                1.
+            }
+        }
+        WavNormalization::Calibrated { full_scale } => 1. / full_scale.max(f32::EPSILON) as f64,
+    };
+    let full_scale_int = ((1_i64 << (bits_per_sample - 1)) - 1) as f64;
+
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).map_err(|err| err.to_string())?;
+        let length = channels.iter().map(Vec::len).max().unwrap_or(0);
+        for index in 0..length {
+            for channel in channels {
+                let sample = channel.get(index).unwrap_or(&0.) * scale;
+                match format {
+                    WavSampleFormat::Float32 => writer.write_sample(sample as f32),
+                    WavSampleFormat::Int16 | WavSampleFormat::Int24 => {
+                        writer.write_sample((sample.clamp(-1., 1.) * full_scale_int).round() as i32)
+                    }
+                }
+                .map_err(|err| err.to_string())?;
+            }
+        }
+        writer.finalize().map_err(|err| err.to_string())?;
+    }
+
+    Ok(cursor.into_inner())
+}
+
+/// Encodes the recordings of microphones as a CSV file with a time column and one sound
+/// pressure column per microphone.
+///
+/// The file starts with comment lines (starting with `#`) describing the scene. Rows are
+/// matched by their time, microphones that did not record at a time have an empty cell.
+/// * `delta_t` - The duration of a time step of the simulation (in s).
+/// * `delta_l` - The size of a cell (in m).
+pub fn encode_csv(mics: &[&Microphone], delta_t: f32, delta_l: f32) -> Result<Vec<u8>, String> {
+    let Some(longest) = mics.iter().max_by_key(|mic| mic.record.len()) else {
+        return Err("There are no recordings to export".to_string());
+    };
+
+    let mut metadata = String::from("# Recorded sound pressure of the microphones\n");
+    metadata += &format!("# sample rate: {} Hz\n", 1. / delta_t);
+    metadata += &format!("# cell size: {} m\n", delta_l);
+    for mic in mics {
+        metadata += &format!(
+            "# microphone {}: x = {} m, y = {} m (cell {}, {})\n",
+            mic.id,
+            mic.x as f32 * delta_l,
+            mic.y as f32 * delta_l,
+            mic.x,
+            mic.y
+        );
+    }
+
+    let mut writer = csv::Writer::from_writer(metadata.into_bytes());
+    let header = std::iter::once("time (s)".to_string())
+        .chain(
+            mics.iter()
+                .map(|mic| format!("microphone {} pressure (Pa)", mic.id)),
+        )
+        .collect::<Vec<_>>();
+    writer
+        .write_record(&header)
+        .map_err(|err| err.to_string())?;
+
+    // all microphones record at the same time steps, but may have started later
+    let mut positions = vec![0; mics.len()];
+    for &[time, _] in &longest.record {
+        let mut row = vec![time.to_string()];
+        for (mic, position) in mics.iter().zip(positions.iter_mut()) {
+            match mic.record.get(*position) {
+                Some(&[mic_time, pressure]) if mic_time == time => {
+                    row.push(pressure.to_string());
+                    *position += 1;
+                }
+                _ => row.push(String::new()),
+            }
+        }
+        writer.write_record(&row).map_err(|err| err.to_string())?;
+    }
+
+    writer.into_inner().map_err(|err| err.to_string())
+}
+
+/// Encodes the room acoustic parameters of the bands of an impulse response as a CSV file with
+/// one row per band. Parameters that could not be evaluated have an empty cell.
+/// * `mic_id` - The id of the microphone that recorded the impulse response.
+pub fn encode_room_parameters(
+    bands: &[(String, RoomParameters)],
+    mic_id: usize,
+) -> Result<Vec<u8>, String> {
+    if bands.is_empty() {
+        return Err("There are no room acoustic parameters to export".to_string());
+    }
+
+    let metadata = format!("# Room acoustic parameters of microphone {}\n", mic_id);
+    let mut writer = csv::Writer::from_writer(metadata.into_bytes());
+    writer
+        .write_record([
+            "band",
+            "T20 (s)",
+            "T30 (s)",
+            "EDT (s)",
+            "C50 (dB)",
+            "C80 (dB)",
+            "D50 (%)",
+            "centre time (s)",
+        ])
+        .map_err(|err| err.to_string())?;
+
+    let cell = |value: Option<f32>| value.map(|value| value.to_string()).unwrap_or_default();
+    for (band, parameters) in bands {
+        writer
+            .write_record([
+                band.clone(),
+                cell(parameters.t20),
+                cell(parameters.t30),
+                cell(parameters.edt),
+                cell(parameters.c50),
+                cell(parameters.c80),
+                cell(parameters.d50.map(|d50| d50 * 100.)),
+                cell(parameters.centre_time),
+            ])
+            .map_err(|err| err.to_string())?;
+    }
+
+    writer.into_inner().map_err(|err| err.to_string())
+}
+
+/// Encodes the spectra of microphones as a CSV file with a frequency column and a level and a
+/// phase column per microphone.
+/// * `unit` - The unit of the levels.
+pub fn encode_spectrum_csv(spectra: &[(usize, Spectrum)], unit: &str) -> Result<Vec<u8>, String> {
+    let Some((_, first)) = spectra.first() else {
+        return Err("There are no spectra to export".to_string());
+    };
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    let header = std::iter::once("frequency (Hz)".to_string())
+        .chain(spectra.iter().flat_map(|(mic_id, _)| {
+            [
+                format!("microphone {} level ({})", mic_id, unit),
+                format!("microphone {} phase (°)", mic_id),
+            ]
+        }))
+        .collect::<Vec<_>>();
+    writer
+        .write_record(&header)
+        .map_err(|err| err.to_string())?;
+
+    // all spectra are calculated with the same settings and have the same bins
+    for (index, frequency) in first.frequencies.iter().enumerate() {
+        let row = std::iter::once(frequency.to_string())
+            .chain(spectra.iter().flat_map(|(_, spectrum)| {
+                [spectrum.levels.get(index), spectrum.phases.get(index)]
+                    .map(|value| value.map(f32::to_string).unwrap_or_default())
+            }))
+            .collect::<Vec<_>>();
+        writer.write_record(&row).map_err(|err| err.to_string())?;
+    }
+
+    writer.into_inner().map_err(|err| err.to_string())
+}
+
+/// Encodes the accumulated levels or the frequency map of all cells of the simulation area as a
+/// CSV file with one row per cell row, starting at the top.
+/// * `mode` - The rendered map to export, every mode except [`RenderMode::Pressure`].
+/// * `delta_t` - The duration of a time step of the simulation (in s).
+/// * `delta_l` - The size of a cell (in m).
+pub fn encode_level_map(
+    levels: &PressureLevels,
+    frequency_maps: &FrequencyMaps,
+    mode: RenderMode,
+    boundary_width: u32,
+    delta_t: f32,
+    delta_l: f32,
+) -> Result<Vec<u8>, String> {
+    let frequency = frequency_maps.selected_frequency().unwrap_or_default();
+    let (value, samples, description): (Box<dyn Fn(usize) -> f32>, _, _) = match mode {
+        RenderMode::RmsLevel => (
+            Box::new(|index| levels.rms_level(index)),
+            levels.samples(),
+            format!("{} of every cell (in dB SPL)", mode),
+        ),
+        RenderMode::PeakLevel => (
+            Box::new(|index| levels.peak_level(index)),
+            levels.samples(),
+            format!("{} of every cell (in dB SPL)", mode),
+        ),
+        RenderMode::Magnitude => (
+            Box::new(|index| frequency_maps.magnitude_level(index)),
+            frequency_maps.samples(),
+            format!("level of every cell at {} Hz (in dB SPL)", frequency),
+        ),
+        RenderMode::Phase => (
+            Box::new(|index| frequency_maps.phase(index)),
+            frequency_maps.samples(),
+            format!("phase of every cell at {} Hz (in degrees)", frequency),
+        ),
+        RenderMode::Pressure => return Err("Select a level render mode to export".to_string()),
+    };
+    if samples == 0 {
+        return Err("There are no accumulated levels to export".to_string());
+    }
+
+    let mut metadata = format!("# {}\n", description);
+    metadata += &format!(
+        "# accumulated over {} samples ({} s)\n",
+        samples,
+        samples as f32 * delta_t
+    );
+    metadata += &format!(
+        "# {} x {} cells of {} m, rows from top to bottom\n",
+        SIMULATION_WIDTH, SIMULATION_HEIGHT, delta_l
+    );
+
+    let mut writer = csv::Writer::from_writer(metadata.into_bytes());
+    for y in boundary_width..(SIMULATION_HEIGHT + boundary_width) {
+        let row = (boundary_width..(SIMULATION_WIDTH + boundary_width))
+            .map(|x| value(coords_to_index(x, y, boundary_width)).to_string())
+            .collect::<Vec<_>>();
+        writer.write_record(&row).map_err(|err| err.to_string())?;
+    }
+
+    writer.into_inner().map_err(|err| err.to_string())
+}
+
+/// Shows an error message if an exported file could not be written.
+pub fn export_saved<T: SaveContents>(
+    mut ev_saved: EventReader<DialogFileSaved<T>>,
+    mut error_message: ResMut<ErrorMessage>,
+) {
+    for event in ev_saved.read() {
+        if let Err(err) = &event.result {
+            error_message.0 = Some(format!("Could not save \"{}\": {}", event.file_name, err));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(contents: Vec<u8>) -> (hound::WavSpec, Vec<i32>) {
+        let mut reader = hound::WavReader::new(Cursor::new(contents)).unwrap();
+        let samples = reader.samples::<i32>().collect::<Result<_, _>>().unwrap();
+        (reader.spec(), samples)
+    }
+
+    #[test]
+    fn wav_has_one_channel_per_recording() {
+        let channels = vec![vec![0.5, -2., 1.], vec![0.25]];
+        let contents = encode_wav(
+            &channels,
+            48_000,
+            WavSampleFormat::Int16,
+            WavNormalization::Peak,
+        )
+        .unwrap();
+
+        let (spec, samples) = decode(contents);
+        assert_eq!(spec.channels, 2);
+        assert_eq!(spec.sample_rate, 48_000);
+        assert_eq!(spec.bits_per_sample, 16);
+        // interleaved, the peak of 2 Pa is full scale and the shorter recording is padded
+        assert_eq!(samples, vec![8192, 4096, -32767, 0, 16384, 0]);
+    }
+
+    #[test]
+    fn calibrated_wav_keeps_the_sound_pressure() {
+        let channels = vec![vec![1., -0.5]];
+        let contents = encode_wav(
+            &channels,
+            44_100,
+            WavSampleFormat::Float32,
+            WavNormalization::Calibrated { full_scale: 2. },
+        )
+        .unwrap();
+
+        let mut reader = hound::WavReader::new(Cursor::new(contents)).unwrap();
+        assert_eq!(reader.spec().sample_format, hound::SampleFormat::Float);
+        let samples = reader
+            .samples::<f32>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(samples, vec![0.5, -0.25]);
+    }
+
+    #[test]
+    fn calibrated_24_bit_wav_clips_at_full_scale() {
+        let channels = vec![vec![3., -3., 0.5]];
+        let contents = encode_wav(
+            &channels,
+            48_000,
+            WavSampleFormat::Int24,
+            WavNormalization::Calibrated { full_scale: 1. },
+        )
+        .unwrap();
+
+        let (spec, samples) = decode(contents);
+        assert_eq!(spec.bits_per_sample, 24);
+        assert_eq!(samples, vec![8_388_607, -8_388_607, 4_194_304]);
+    }
+
+    #[test]
+    fn wav_without_recordings_is_an_error() {
+        assert!(encode_wav(&[], 48_000, WavSampleFormat::Int16, WavNormalization::Peak).is_err());
+    }
+
+    #[test]
+    fn csv_has_metadata_and_empty_cells_before_a_microphone_started() {
+        let mut first = Microphone::new(4, 6, 0);
+        first.record = vec![[0., 0.25], [0.5, -0.5], [1., 0.125]];
+        let mut second = Microphone::new(10, 1, 1);
+        second.record = vec![[0.5, 1.], [1., -1.]];
+
+        let contents = encode_csv(&[&first, &second], 0.5, 0.5).unwrap();
+
+        assert_eq!(
+            String::from_utf8(contents).unwrap(),
+            "# Recorded sound pressure of the microphones\n\
+             # sample rate: 2 Hz\n\
+             # cell size: 0.5 m\n\
+             # microphone 0: x = 2 m, y = 3 m (cell 4, 6)\n\
+             # microphone 1: x = 5 m, y = 0.5 m (cell 10, 1)\n\
+             time (s),microphone 0 pressure (Pa),microphone 1 pressure (Pa)\n\
+             0,0.25,\n\
+             0.5,-0.5,1\n\
+             1,0.125,-1\n"
+        );
+    }
+
+    #[test]
+    fn csv_without_microphones_is_an_error() {
+        assert!(encode_csv(&[], 0.5, 0.5).is_err());
+    }
+}
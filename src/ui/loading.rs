@@ -2,15 +2,20 @@ use bevy::prelude::*;
 use bevy_file_dialog::DialogFileLoaded;
 use serde::Deserialize;
 
-use super::state::UiState;
+use super::state::{ErrorMessage, SignalTarget, UiState};
+use crate::components::line_array::LineArray;
 use crate::components::microphone::Microphone;
-use crate::components::source::Source;
+use crate::components::plane_wave::PlaneWave;
+use crate::components::source::{Source, SourceType, WaveChannel};
+use crate::components::source_group::SourceGroup;
+use crate::components::vibrating_surface::VibratingSurface;
 use crate::components::wall::{CircWall, RectWall};
-use crate::events::{Reset, UpdateWalls};
+use crate::events::{Reset, SceneObjects, UpdateWalls};
+use crate::math::calibration::source_gain;
 use crate::math::constants::PROPAGATION_SPEED;
 use crate::render::gradient::Gradient;
 use crate::simulation::grid::Grid;
-use crate::simulation::plugin::{ComponentIDs, WaveSamples};
+use crate::simulation::plugin::{ComponentIDs, SampleTable, SampleTables, WaveData, WaveSamples};
 
 /// Marker component for the file dialog and the corresponding event.
 pub struct SceneSaveFileContents;
@@ -22,11 +27,23 @@ struct SceneSaveData {
     mics: Vec<Microphone>,
     rect_walls: Vec<RectWall>,
     circ_walls: Vec<CircWall>,
+    #[serde(default)]
+    line_arrays: Vec<LineArray>,
+    #[serde(default)]
+    plane_waves: Vec<PlaneWave>,
+    #[serde(default)]
+    surfaces: Vec<VibratingSurface>,
+    #[serde(default)]
+    source_groups: Vec<SourceGroup>,
     gradient: Gradient,
     max_gradient: f32,
     min_gradient: f32,
     reset_on_change: bool,
     delta_l: f32,
+    /// whether the amplitudes are sound pressures at 1 m, false for files saved before the
+    /// sources were calibrated
+    #[serde(default)]
+    calibrated: bool,
 }
 
 /// Loads a file when receiving a [`DialogFileLoaded`] event from the file dialog.
@@ -38,33 +55,42 @@ pub fn scene_save_file_loaded(
     mut grid: ResMut<Grid>,
     mut ids: ResMut<ComponentIDs>,
     mut gradient: ResMut<Gradient>,
-    sources: Query<(Entity, &Source)>,
-    mics: Query<(Entity, &Microphone)>,
-    rect_walls: Query<(Entity, &RectWall)>,
-    circ_walls: Query<(Entity, &CircWall)>,
+    objects: SceneObjects,
     mut ui_state: ResMut<UiState>,
+    mut wave_samples: ResMut<WaveSamples>,
+    mut sample_tables: ResMut<SampleTables>,
+    mut error_message: ResMut<ErrorMessage>,
 ) {
     if let Some(data) = ev_loaded.read().next() {
-        let save_data = serde_json::from_slice::<SceneSaveData>(&data.contents).unwrap();
+        let save_data = match serde_json::from_slice::<SceneSaveData>(&data.contents) {
+            Ok(save_data) => save_data,
+            Err(err) => {
+                error_message.0 = Some(format!("Could not load \"{}\": {}", data.file_name, err));
+                return;
+            }
+        };
 
         // Clear all entities
-        for (entity, _) in sources.iter() {
-            commands.entity(entity).despawn();
-        }
-        for (entity, _) in mics.iter() {
-            commands.entity(entity).despawn();
-        }
-        for (entity, _) in rect_walls.iter() {
-            commands.entity(entity).despawn();
-        }
-        for (entity, _) in circ_walls.iter() {
+        for entity in objects.iter() {
             commands.entity(entity).despawn();
         }
 
         ids.reset();
 
         // Load entities
-        for source in save_data.sources {
+        for mut source in save_data.sources {
+            if !save_data.calibrated {
+                // the amplitude used to be injected into the grid as it is
+                let delta_t = save_data.delta_l / PROPAGATION_SPEED;
+                let gain = source_gain(source.source_type.calibration_frequency(), delta_t);
+                source.source_type.scale_amplitude(1. / gain);
+            }
+            load_signal_files(
+                &source.source_type,
+                &mut wave_samples,
+                &mut sample_tables,
+                &mut error_message,
+            );
             commands.spawn(source);
             ids.get_new_source_id();
         }
@@ -80,6 +106,46 @@ pub fn scene_save_file_loaded(
             commands.spawn(circ_wall);
             ids.get_new_wall_id();
         }
+        for line_array in save_data.line_arrays {
+            load_signal_files(
+                &line_array.source_type,
+                &mut wave_samples,
+                &mut sample_tables,
+                &mut error_message,
+            );
+            commands.spawn(line_array);
+            ids.get_new_line_array_id();
+        }
+        for plane_wave in save_data.plane_waves {
+            load_signal_files(
+                &plane_wave.source_type,
+                &mut wave_samples,
+                &mut sample_tables,
+                &mut error_message,
+            );
+            commands.spawn(plane_wave);
+            ids.get_new_plane_wave_id();
+        }
+        for surface in save_data.surfaces {
+            load_signal_files(
+                &surface.source_type,
+                &mut wave_samples,
+                &mut sample_tables,
+                &mut error_message,
+            );
+            commands.spawn(surface);
+            ids.get_new_surface_id();
+        }
+        for source_group in save_data.source_groups {
+            load_signal_files(
+                &source_group.source_type,
+                &mut wave_samples,
+                &mut sample_tables,
+                &mut error_message,
+            );
+            commands.spawn(source_group);
+            ids.get_new_source_group_id();
+        }
 
         *gradient = save_data.gradient;
         ui_state.max_gradient = save_data.max_gradient;
@@ -92,49 +158,405 @@ pub fn scene_save_file_loaded(
     }
 }
 
+/// Reads the wave or CSV file a signal refers to, as the samples are not part of the save file.
+fn load_signal_files(
+    source_type: &SourceType,
+    wave_samples: &mut WaveSamples,
+    sample_tables: &mut SampleTables,
+    error_message: &mut ErrorMessage,
+) {
+    match source_type {
+        SourceType::WaveFile { path, .. }
+            if !path.is_empty() && !wave_samples.0.contains_key(path) =>
+        {
+            match std::fs::read(path)
+                .map_err(|err| err.to_string())
+                .and_then(|contents| decode_wav(&contents))
+            {
+                Ok(wave_data) => {
+                    wave_samples.0.insert(path.clone(), wave_data);
+                }
+                Err(err) => {
+                    error_message.0 = Some(format!("Could not load \"{}\": {}", path, err));
+                }
+            }
+        }
+        SourceType::Csv { path, .. } if !path.is_empty() && !sample_tables.0.contains_key(path) => {
+            match std::fs::read(path)
+                .map_err(|err| err.to_string())
+                .and_then(|contents| parse_csv_table(&contents))
+            {
+                Ok(table) => {
+                    sample_tables.0.insert(path.clone(), table);
+                }
+                Err(err) => {
+                    error_message.0 = Some(format!("Could not load \"{}\": {}", path, err));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the signal of the object a loaded file is assigned to.
+fn target_signal<'a>(
+    target: SignalTarget,
+    sources: &'a mut Query<&mut Source>,
+    line_arrays: &'a mut Query<&mut LineArray>,
+    plane_waves: &'a mut Query<&mut PlaneWave>,
+    surfaces: &'a mut Query<&mut VibratingSurface>,
+    source_groups: &'a mut Query<&mut SourceGroup>,
+) -> Option<Mut<'a, SourceType>> {
+    match target {
+        SignalTarget::Source(id) => sources
+            .iter_mut()
+            .find(|source| source.id == id)
+            .map(|source| source.map_unchanged(|source| &mut source.source_type)),
+        SignalTarget::LineArray(id) => line_arrays
+            .iter_mut()
+            .find(|line_array| line_array.id == id)
+            .map(|line_array| line_array.map_unchanged(|line_array| &mut line_array.source_type)),
+        SignalTarget::PlaneWave(id) => plane_waves
+            .iter_mut()
+            .find(|plane_wave| plane_wave.id == id)
+            .map(|plane_wave| plane_wave.map_unchanged(|plane_wave| &mut plane_wave.source_type)),
+        SignalTarget::Surface(id) => surfaces
+            .iter_mut()
+            .find(|surface| surface.id == id)
+            .map(|surface| surface.map_unchanged(|surface| &mut surface.source_type)),
+        SignalTarget::SourceGroup(id) => source_groups
+            .iter_mut()
+            .find(|source_group| source_group.id == id)
+            .map(|source_group| {
+                source_group.map_unchanged(|source_group| &mut source_group.source_type)
+            }),
+    }
+}
+
 /// Marker component for the file dialog and the corresponding event.
 pub struct WavFileContents;
 
+/// Decodes all channels of a wave file.
+///
+/// Integer files with up to 32 bits and 32 or 64 bit float files are supported.
+/// Integer samples are normalized to the range -1..1.
+pub fn decode_wav(contents: &[u8]) -> Result<WaveData, String> {
+    let mut reader = match hound::WavReader::new(contents) {
+        Ok(reader) => reader,
+        // hound does not support 64 bit float files
+        Err(err) => return decode_float64_wav(contents).ok_or(err.to_string()),
+    };
+    let spec = reader.spec();
+
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Int => {
+            if spec.bits_per_sample > 32 {
+                return Err(format!(
+                    "{} bit integer samples are not supported",
+                    spec.bits_per_sample
+                ));
+            }
+            let full_scale = (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / full_scale))
+                .collect::<Result<Vec<f32>, _>>()
+        }
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<f32>, _>>(),
+    }
+    .map_err(|err| err.to_string())?;
+
+    if spec.channels == 0 {
+        return Err("the file has no channels".to_string());
+    }
+
+    Ok(WaveData::new(
+        deinterleave(&samples, spec.channels as usize),
+        spec.sample_rate as f32,
+    ))
+}
+
+/// Decodes a wave file with 64 bit float samples. Returns `None` if the file is not of that format.
+fn decode_float64_wav(contents: &[u8]) -> Option<WaveData> {
+    const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+    const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+    let read_u16 = |at: usize| {
+        Some(u16::from_le_bytes(
+            contents.get(at..at + 2)?.try_into().ok()?,
+        ))
+    };
+    let read_u32 = |at: usize| {
+        Some(u32::from_le_bytes(
+            contents.get(at..at + 4)?.try_into().ok()?,
+        ))
+    };
+
+    if contents.get(0..4)? != b"RIFF" || contents.get(8..12)? != b"WAVE" {
+        return None;
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut position = 12;
+    while position + 8 <= contents.len() {
+        let chunk_id = &contents[position..position + 4];
+        let chunk_len = read_u32(position + 4)? as usize;
+        let chunk_start = position + 8;
+        let chunk_end = (chunk_start + chunk_len).min(contents.len());
+
+        match chunk_id {
+            b"fmt " => {
+                let mut format_tag = read_u16(chunk_start)?;
+                if format_tag == WAVE_FORMAT_EXTENSIBLE {
+                    // the sub format GUID starts with the actual format tag
+                    format_tag = read_u16(chunk_start + 24)?;
+                }
+                let channels = read_u16(chunk_start + 2)?;
+                let sample_rate = read_u32(chunk_start + 4)?;
+                let bits_per_sample = read_u16(chunk_start + 14)?;
+                format = Some((format_tag, channels, sample_rate, bits_per_sample));
+            }
+            b"data" => data = Some(&contents[chunk_start..chunk_end]),
+            _ => {}
+        }
+
+        // chunks are padded to an even length
+        position = chunk_start + chunk_len + chunk_len % 2;
+    }
+
+    let (format_tag, channels, sample_rate, bits_per_sample) = format?;
+    if format_tag != WAVE_FORMAT_IEEE_FLOAT || bits_per_sample != 64 || channels == 0 {
+        return None;
+    }
+
+    let samples = data?
+        .chunks_exact(8)
+        .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()) as f32)
+        .collect::<Vec<_>>();
+
+    Some(WaveData::new(
+        deinterleave(&samples, channels as usize),
+        sample_rate as f32,
+    ))
+}
+
+/// Splits interleaved samples into their channels
+fn deinterleave(samples: &[f32], channels: usize) -> Vec<Vec<f32>> {
+    (0..channels)
+        .map(|channel| {
+            samples
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .copied()
+                .collect()
+        })
+        .collect()
+}
+
+/// Loads a wave file when receiving a [`DialogFileLoaded`] event from the file dialog
+/// and assigns it to the object that requested it.
 pub fn wav_file_loaded(
     mut ev_loaded: EventReader<DialogFileLoaded<WavFileContents>>,
     mut ui_state: ResMut<UiState>,
     mut reset_ev: EventWriter<Reset>,
     mut wave_samples: ResMut<WaveSamples>,
+    mut sources: Query<&mut Source>,
+    mut line_arrays: Query<&mut LineArray>,
+    mut plane_waves: Query<&mut PlaneWave>,
+    mut surfaces: Query<&mut VibratingSurface>,
+    mut source_groups: Query<&mut SourceGroup>,
+    mut error_message: ResMut<ErrorMessage>,
 ) {
     if let Some(data) = ev_loaded.read().next() {
-        // let reader = hound::WavReader::open("assets/misc/audio.wav");
-        let reader = hound::WavReader::new(data.contents.as_slice());
-        if reader.is_ok() {
-            let mut reader = reader.unwrap();
-            println!("{:?}", reader.spec().bits_per_sample);
-            let samples = match reader.spec().sample_format {
-                hound::SampleFormat::Int => {
-                    match reader.spec().bits_per_sample {
-                        16 => reader
-                            .samples::<i16>()
-                            .map(|s| s.unwrap() as f32 / i16::MAX as f32)
-                            .collect::<Vec<f32>>(),
-                        32 => reader
-                            .samples::<i32>()
-                            .map(|s| s.unwrap() as f32 / i32::MAX as f32)
-                            .collect::<Vec<f32>>(), //normalisation isn't correct i think
-                        _ => todo!(),
+        let Some(target) = ui_state.wave_file_target.take() else {
+            return;
+        };
+
+        match decode_wav(&data.contents) {
+            Ok(wave_data) => {
+                #[cfg(not(target_arch = "wasm32"))]
+                let file_path = data.path.to_string_lossy().into_owned();
+                #[cfg(target_arch = "wasm32")]
+                let file_path = data.file_name.clone();
+
+                wave_samples.0.insert(file_path.clone(), wave_data);
+
+                if let Some(mut source_type) = target_signal(
+                    target,
+                    &mut sources,
+                    &mut line_arrays,
+                    &mut plane_waves,
+                    &mut surfaces,
+                    &mut source_groups,
+                ) {
+                    if let SourceType::WaveFile { path, channel, .. } = &mut *source_type {
+                        *path = file_path;
+                        *channel = WaveChannel::Downmix;
                     }
                 }
-                hound::SampleFormat::Float => match reader.spec().bits_per_sample {
-                    32 => reader
-                        .samples::<f32>()
-                        .collect::<Result<Vec<f32>, _>>()
-                        .unwrap(),
-                    _ => todo!(),
-                },
-            };
-            wave_samples.0 = samples;
 
-            // set delta l to correct sample rate
-            ui_state.delta_l = PROPAGATION_SPEED / reader.spec().sample_rate as f32;
+                reset_ev.send(Reset::default());
+            }
+            Err(err) => {
+                error_message.0 = Some(format!("Could not load \"{}\": {}", data.file_name, err));
+            }
+        }
+    }
+}
+
+/// Marker component for the file dialog and the corresponding event.
+pub struct CsvFileContents;
+
+/// Parses a CSV file with two columns: the time (in s) and the value of the signal.
+///
+/// Columns may be separated by `,` or `;`. A non numeric first row is treated as a header.
+/// The points are sorted by time.
+pub fn parse_csv_table(contents: &[u8]) -> Result<SampleTable, String> {
+    let text = std::str::from_utf8(contents).map_err(|err| err.to_string())?;
+    let first_line = text
+        .lines()
+        .find(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .unwrap_or("");
+    let delimiter = if first_line.contains(';') { b';' } else { b',' };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(delimiter)
+        .comment(Some(b'#'))
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(contents);
+
+    let mut points = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|err| err.to_string())?;
+        if record.iter().all(|field| field.is_empty()) {
+            continue;
+        }
+        let parse = |column: usize| {
+            record
+                .get(column)
+                .and_then(|field| field.parse::<f32>().ok())
+        };
+        match (parse(0), parse(1)) {
+            (Some(time), Some(value)) if time.is_finite() && value.is_finite() => {
+                points.push([time, value]);
+            }
+            // the first row may contain the column names
+            _ if index == 0 => {}
+            _ => {
+                return Err(format!(
+                    "row {} does not contain a time and a value",
+                    index + 1
+                ));
+            }
+        }
+    }
+
+    if points.len() < 2 {
+        return Err("the file needs at least two rows of time and value".to_string());
+    }
+
+    points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+
+    Ok(SampleTable::new(points))
+}
+
+/// Loads a CSV file when receiving a [`DialogFileLoaded`] event from the file dialog
+/// and assigns it to the object that requested it.
+pub fn csv_file_loaded(
+    mut ev_loaded: EventReader<DialogFileLoaded<CsvFileContents>>,
+    mut ui_state: ResMut<UiState>,
+    mut reset_ev: EventWriter<Reset>,
+    mut sample_tables: ResMut<SampleTables>,
+    mut sources: Query<&mut Source>,
+    mut line_arrays: Query<&mut LineArray>,
+    mut plane_waves: Query<&mut PlaneWave>,
+    mut surfaces: Query<&mut VibratingSurface>,
+    mut source_groups: Query<&mut SourceGroup>,
+    mut error_message: ResMut<ErrorMessage>,
+) {
+    if let Some(data) = ev_loaded.read().next() {
+        let Some(target) = ui_state.csv_file_target.take() else {
+            return;
+        };
+
+        match parse_csv_table(&data.contents) {
+            Ok(table) => {
+                #[cfg(not(target_arch = "wasm32"))]
+                let file_path = data.path.to_string_lossy().into_owned();
+                #[cfg(target_arch = "wasm32")]
+                let file_path = data.file_name.clone();
+
+                sample_tables.0.insert(file_path.clone(), table);
+
+                if let Some(mut source_type) = target_signal(
+                    target,
+                    &mut sources,
+                    &mut line_arrays,
+                    &mut plane_waves,
+                    &mut surfaces,
+                    &mut source_groups,
+                ) {
+                    if let SourceType::Csv { path, .. } = &mut *source_type {
+                        *path = file_path;
+                    }
+                }
 
-            reset_ev.send(Reset::default());
+                reset_ev.send(Reset::default());
+            }
+            Err(err) => {
+                error_message.0 = Some(format!("Could not load \"{}\": {}", data.file_name, err));
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::source::Playback;
+
+    #[test]
+    fn wave_file_sources_of_old_scenes_load_without_a_file() {
+        let source_type =
+            serde_json::from_str::<SourceType>(r#"{"WaveFile":{"amplitude":100.0}}"#).unwrap();
+
+        assert_eq!(
+            source_type,
+            SourceType::WaveFile {
+                path: String::new(),
+                channel: WaveChannel::Downmix,
+                amplitude: 100.,
+                playback: Playback::Loop,
+                start_offset: 0.,
+            }
+        );
+    }
+
+    #[test]
+    fn csv_tables_skip_the_header_and_sort_the_points() {
+        let sample_table = parse_csv_table(b"time,value\n0.2, 3\n0,1\n\n0.1,2\n").unwrap();
+        assert_eq!(sample_table.points, vec![[0., 1.], [0.1, 2.], [0.2, 3.]]);
+    }
+
+    #[test]
+    fn csv_tables_may_be_separated_by_semicolons() {
+        let sample_table = parse_csv_table(b"# drive signal\n0;0.5\n0.001;-0.5\n").unwrap();
+        assert_eq!(sample_table.points, vec![[0., 0.5], [0.001, -0.5]]);
+    }
+
+    #[test]
+    fn csv_tables_need_two_rows_of_numbers() {
+        assert_eq!(
+            parse_csv_table(b"time,value\n0,1\n"),
+            Err("the file needs at least two rows of time and value".to_string())
+        );
+        assert_eq!(
+            parse_csv_table(b"0,1\n0.1,x\n"),
+            Err("row 2 does not contain a time and a value".to_string())
+        );
+    }
+}
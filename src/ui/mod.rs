@@ -1,4 +1,5 @@
 pub mod draw;
+pub mod export;
 pub mod keybinds;
 pub mod loading;
 pub mod plugin;
@@ -3,11 +3,13 @@ use bevy::prelude::*;
 use bevy_file_dialog::FileDialogPlugin;
 
 use super::draw::draw_egui;
+use super::export::{export_saved, CsvExportContents, SvgExportContents, WavExportContents};
 use super::loading::{
-    scene_save_file_loaded, wav_file_loaded, SceneSaveFileContents, WavFileContents,
+    csv_file_loaded, scene_save_file_loaded, wav_file_loaded, CsvFileContents,
+    SceneSaveFileContents, WavFileContents,
 };
-use super::state::{ClipboardBuffer, FftMicrophone, UiState};
-use super::tabs::DockState;
+use super::state::{ClipboardBuffer, ErrorMessage, FftMicrophone, UiState};
+use super::tabs::{DockState, PlotCache};
 
 pub struct UiPlugin;
 
@@ -17,13 +19,30 @@ impl Plugin for UiPlugin {
             .init_resource::<ClipboardBuffer>()
             .init_resource::<DockState>()
             .init_resource::<FftMicrophone>()
+            .init_resource::<ErrorMessage>()
+            .init_resource::<PlotCache>()
             .add_plugins((
                 FileDialogPlugin::new()
                     .with_save_file::<SceneSaveFileContents>()
+                    .with_save_file::<WavExportContents>()
+                    .with_save_file::<CsvExportContents>()
+                    .with_save_file::<SvgExportContents>()
                     .with_load_file::<SceneSaveFileContents>()
-                    .with_load_file::<WavFileContents>(),
+                    .with_load_file::<WavFileContents>()
+                    .with_load_file::<CsvFileContents>(),
                 FrameTimeDiagnosticsPlugin,
             ))
-            .add_systems(Update, (draw_egui, scene_save_file_loaded, wav_file_loaded));
+            .add_systems(
+                Update,
+                (
+                    draw_egui,
+                    scene_save_file_loaded,
+                    wav_file_loaded,
+                    csv_file_loaded,
+                    export_saved::<WavExportContents>,
+                    export_saved::<CsvExportContents>,
+                    export_saved::<SvgExportContents>,
+                ),
+            );
     }
 }
@@ -6,7 +6,7 @@ use egui_extras::{Column, TableBuilder};
 
 use super::draw::EventSystemParams;
 use super::state::UiState;
-use crate::events::{LoadWav, Reset};
+use crate::events::Reset;
 use crate::math::constants::{SIMULATION_HEIGHT, SIMULATION_WIDTH};
 use crate::render::gradient::Gradient;
 use crate::simulation::grid::Grid;
@@ -165,7 +165,7 @@ pub fn draw_preferences(
                                     row.col(|ui| {
                                         ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
                                             ui.add(
-                                                egui::DragValue::new(&mut ui_state_tmp.min_gradient).speed(0.01)
+                                                egui::DragValue::new(&mut ui_state_tmp.min_gradient).speed(0.01).suffix(" Pa")
                                             );
                                         });
                                     });
@@ -179,7 +179,7 @@ pub fn draw_preferences(
                                     row.col(|ui| {
                                         ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
                                             ui.add(
-                                                egui::DragValue::new(&mut ui_state_tmp.max_gradient).speed(0.01)
+                                                egui::DragValue::new(&mut ui_state_tmp.max_gradient).speed(0.01).suffix(" Pa")
                                             );
                                         });
                                     });
@@ -238,12 +238,6 @@ pub fn draw_preferences(
                                         row.col(|ui| {
                                             ui.with_layout(Layout::left_to_right(egui::Align::Center), |ui|{
                                                 ui.label("Use .wav files as sound source");
-                                                if ui_state_tmp.wave_files && ui
-                                                    .add(egui::Button::new("Load wave file"))
-                                                    .clicked()
-                                                {
-                                                    events.load_wav_ev.send(LoadWav);
-                                                }
                                             });
                                         });
                                     });
@@ -1,7 +1,11 @@
 use serde::Serialize;
 
+use crate::components::line_array::LineArray;
 use crate::components::microphone::Microphone;
+use crate::components::plane_wave::PlaneWave;
 use crate::components::source::Source;
+use crate::components::source_group::SourceGroup;
+use crate::components::vibrating_surface::VibratingSurface;
 use crate::components::wall::{CircWall, RectWall};
 use crate::render::gradient::Gradient;
 
@@ -12,11 +16,17 @@ struct SaveData<'a> {
     mics: &'a Vec<&'a Microphone>,
     rect_walls: &'a Vec<&'a RectWall>,
     circ_walls: &'a Vec<&'a CircWall>,
+    line_arrays: &'a Vec<&'a LineArray>,
+    plane_waves: &'a Vec<&'a PlaneWave>,
+    surfaces: &'a Vec<&'a VibratingSurface>,
+    source_groups: &'a Vec<&'a SourceGroup>,
     gradient: &'a Gradient,
     max_gradient: f32,
     min_gradient: f32,
     reset_on_change: bool,
     delta_l: f32,
+    /// whether the amplitudes are sound pressures at 1 m, always true for new files
+    calibrated: bool,
 }
 
 /// Serializes the given data to a byte vector of JSON.
@@ -25,6 +35,10 @@ pub fn serialize(
     mics: &Vec<&Microphone>,
     rect_walls: &Vec<&RectWall>,
     circ_walls: &Vec<&CircWall>,
+    line_arrays: &Vec<&LineArray>,
+    plane_waves: &Vec<&PlaneWave>,
+    surfaces: &Vec<&VibratingSurface>,
+    source_groups: &Vec<&SourceGroup>,
     gradient: &Gradient,
     max_gradient: f32,
     min_gradient: f32,
@@ -36,11 +50,16 @@ pub fn serialize(
         mics,
         rect_walls,
         circ_walls,
+        line_arrays,
+        plane_waves,
+        surfaces,
+        source_groups,
         gradient,
         max_gradient,
         min_gradient,
         reset_on_change,
         delta_l,
+        calibrated: true,
     };
 
     serde_json::to_vec(&save_data)
@@ -2,6 +2,11 @@ use std::fmt;
 
 use bevy::prelude::*;
 
+use super::export::{WavNormalization, WavSampleFormat};
+use crate::math::bands::{BandResolution, FrequencyWeighting};
+use crate::math::window::SpectralWindow;
+use crate::render::gradient::Gradient;
+
 /// A resource to store the current simulation time in seconds.
 #[derive(Default, Resource)]
 pub struct SimTime {
@@ -35,6 +40,9 @@ pub enum PlaceType {
     Mic,
     RectWall,
     CircWall,
+    LineArray,
+    PlaneWave,
+    VibratingSurface,
 }
 
 impl fmt::Display for PlaceType {
@@ -44,6 +52,9 @@ impl fmt::Display for PlaceType {
             PlaceType::Mic => write!(f, "Microphone"),
             PlaceType::RectWall => write!(f, "Rectangle Wall"),
             PlaceType::CircWall => write!(f, "Circle Wall"),
+            PlaceType::LineArray => write!(f, "Line Array"),
+            PlaceType::PlaneWave => write!(f, "Plane Wave"),
+            PlaceType::VibratingSurface => write!(f, "Vibrating Surface"),
         }
     }
 }
@@ -63,7 +74,51 @@ impl fmt::Display for FftScaling {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             FftScaling::Normalized => write!(f, "Normalized"),
-            FftScaling::Decibels => write!(f, "dB"),
+            FftScaling::Decibels => write!(f, "dB SPL"),
+        }
+    }
+}
+
+/// What is shown for every cell of the simulation
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum RenderMode {
+    /// the instantaneous sound pressure
+    #[default]
+    Pressure,
+    /// the level of the RMS pressure since the last reset
+    RmsLevel,
+    /// the level of the peak pressure since the last reset
+    PeakLevel,
+    /// the level at a single frequency since the last reset
+    Magnitude,
+    /// the phase at a single frequency since the last reset
+    Phase,
+}
+
+impl RenderMode {
+    /// Returns whether the mode shows the accumulated [`PressureLevels`].
+    ///
+    /// [`PressureLevels`]: crate::simulation::levels::PressureLevels
+    pub fn shows_levels(self) -> bool {
+        matches!(self, RenderMode::RmsLevel | RenderMode::PeakLevel)
+    }
+
+    /// Returns whether the mode shows the [`FrequencyMaps`].
+    ///
+    /// [`FrequencyMaps`]: crate::simulation::frequency_maps::FrequencyMaps
+    pub fn shows_frequency_maps(self) -> bool {
+        matches!(self, RenderMode::Magnitude | RenderMode::Phase)
+    }
+}
+
+impl fmt::Display for RenderMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderMode::Pressure => write!(f, "Pressure"),
+            RenderMode::RmsLevel => write!(f, "RMS level"),
+            RenderMode::PeakLevel => write!(f, "Peak level"),
+            RenderMode::Magnitude => write!(f, "Magnitude at frequency"),
+            RenderMode::Phase => write!(f, "Phase at frequency"),
         }
     }
 }
@@ -96,14 +151,56 @@ pub struct UiState {
     pub read_epilepsy_warning: bool,
     pub show_fft_approx: bool,
     pub fft_window_size: usize,
+    pub fft_window: SpectralWindow,
+    /// whether the spectra of the whole recording are averaged
+    pub fft_averaging: bool,
+    /// factor the FFT blocks are extended with zeros by
+    pub fft_zero_padding: usize,
+    pub show_fft_phase: bool,
+    pub show_fft_peaks: bool,
+    /// maximum number of peaks per spectrum
+    pub fft_peak_count: usize,
+    /// minimum prominence of a peak (in dB)
+    pub fft_peak_prominence: f32,
     pub collapse_header: bool,
     pub max_gradient: f32,
     pub min_gradient: f32,
+    pub render_mode: RenderMode,
+    /// level shown with the highest color of the gradient in the level render modes (in dB)
+    pub max_level_gradient: f32,
+    /// level shown with the lowest color of the gradient in the level render modes (in dB)
+    pub min_level_gradient: f32,
     pub hide_gizmos: bool,
     pub show_new_warning: bool,
     pub show_frequencies: bool,
     pub show_mic_export: bool,
     pub wave_files: bool,
+    /// object the next loaded wave file is assigned to
+    pub wave_file_target: Option<SignalTarget>,
+    /// object the next loaded CSV file is assigned to
+    pub csv_file_target: Option<SignalTarget>,
+    pub wav_sample_format: WavSampleFormat,
+    pub wav_normalization: WavNormalization,
+    /// microphone whose impulse response is analyzed in the room acoustics tab
+    pub room_acoustics_mic: Option<usize>,
+    /// microphone shown in the spectrogram tab
+    pub spectrogram_mic: Option<usize>,
+    pub spectrogram_window_size: usize,
+    /// number of samples between the starts of two spectrogram frames
+    pub spectrogram_hop: usize,
+    pub spectrogram_gradient: Gradient,
+    /// range of levels below the maximum that is shown in the spectrogram (in dB)
+    pub spectrogram_dynamic_range: f32,
+    /// microphone shown in the bands tab
+    pub bands_mic: Option<usize>,
+    pub band_resolution: BandResolution,
+    pub frequency_weighting: FrequencyWeighting,
+    /// duration of the latest part of the recording whose band levels are shown (in s)
+    pub band_duration: f32,
+    pub transfer_reference_mic: Option<usize>,
+    pub transfer_measurement_mic: Option<usize>,
+    /// number of samples of the segments the transfer function is averaged over
+    pub transfer_segment_size: usize,
 }
 
 impl Default for UiState {
@@ -134,18 +231,64 @@ impl Default for UiState {
             read_epilepsy_warning: false,
             show_fft_approx: false,
             fft_window_size: 1024,
+            fft_window: SpectralWindow::default(),
+            fft_averaging: false,
+            fft_zero_padding: 1,
+            show_fft_phase: false,
+            show_fft_peaks: false,
+            fft_peak_count: 5,
+            fft_peak_prominence: 6.,
             collapse_header: false,
             max_gradient: 1.25,
             min_gradient: -1.25,
+            render_mode: RenderMode::default(),
+            max_level_gradient: 110.,
+            min_level_gradient: 50.,
             hide_gizmos: false,
             show_new_warning: false,
             show_frequencies: false,
             show_mic_export: false,
             wave_files: false,
+            wave_file_target: None,
+            csv_file_target: None,
+            wav_sample_format: WavSampleFormat::default(),
+            wav_normalization: WavNormalization::default(),
+            room_acoustics_mic: None,
+            spectrogram_mic: None,
+            spectrogram_window_size: 1024,
+            spectrogram_hop: 256,
+            spectrogram_gradient: Gradient::default(),
+            spectrogram_dynamic_range: 80.,
+            bands_mic: None,
+            band_resolution: BandResolution::default(),
+            frequency_weighting: FrequencyWeighting::default(),
+            band_duration: 0.1,
+            transfer_reference_mic: None,
+            transfer_measurement_mic: None,
+            transfer_segment_size: 1024,
         }
     }
 }
 
+/// An object with a signal that a loaded wave or CSV file can be assigned to
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SignalTarget {
+    /// the source with the given id
+    Source(usize),
+    /// the line array with the given id
+    LineArray(usize),
+    /// the plane wave with the given id
+    PlaneWave(usize),
+    /// the vibrating surface with the given id
+    Surface(usize),
+    /// the source group with the given id
+    SourceGroup(usize),
+}
+
+/// A resource to store an error message that is shown to the user until it is dismissed.
+#[derive(Resource, Default)]
+pub struct ErrorMessage(pub Option<String>);
+
 /// A resource to store the currently copied [`Entity`] for the clipboard.
 #[derive(Resource, Default)]
 pub struct ClipboardBuffer {
@@ -1,13 +1,43 @@
+use std::collections::HashMap;
+
 use bevy::ecs::system::{Commands, Resource};
 use bevy_file_dialog::FileDialogExt;
-use egui_plot::{GridMark, Line, Plot, PlotBounds, PlotPoints};
+use egui_plot::{
+    Bar, BarChart, GridMark, Line, MarkerShape, Plot, PlotBounds, PlotImage, PlotPoint, PlotPoints,
+    Points, VLine,
+};
 use plotters::prelude::*;
 
+use super::export::{
+    encode_csv, encode_room_parameters, encode_spectrum_csv, encode_wav, CsvExportContents,
+    SvgExportContents, WavExportContents, WavNormalization, WavSampleFormat,
+};
 use super::loading::SceneSaveFileContents;
-use super::state::{FftScaling, UiState};
+use super::state::{ErrorMessage, FftScaling, UiState};
 use crate::components::microphone::Microphone;
-use crate::math::fft::calc_mic_spectrum;
+use crate::math::bands::{
+    band_filter, band_levels, band_name, exact_center, BandResolution, FrequencyWeighting,
+    OCTAVE_BANDS,
+};
+use crate::math::calibration::pressure_to_db_spl;
+use crate::math::fft::{
+    calc_mic_spectrogram, calc_mic_spectrum, calc_transfer_function, find_peaks, Peak, Spectrum,
+    TransferFunction, WelchSum,
+};
+use crate::math::room_acoustics::{onset, schroeder_decay, RoomParameters};
 use crate::math::transformations::interpolate;
+use crate::math::window::SpectralWindow;
+use crate::render::gradient::Gradient;
+use crate::simulation::noise_control::{NoiseControl, NoiseControlStage};
+
+/// Duration over which the sound pressure level of the microphones is calculated (in s)
+const LEVEL_DURATION: f64 = 0.125;
+/// Maximum number of points of a decay curve in the room acoustics plot
+const MAX_DECAY_POINTS: usize = 2000;
+/// Maximum number of frames of the spectrogram, older frames scroll out of the plot
+const MAX_SPECTROGRAM_FRAMES: usize = 256;
+/// Highest frequency shown in the spectrogram (in Hz)
+const MAX_SPECTROGRAM_FREQUENCY: f32 = 20_000.;
 
 #[derive(Resource)]
 pub struct DockState {
@@ -21,9 +51,93 @@ impl Default for DockState {
     }
 }
 
+/// A resource holding the analysis results of the plot tabs, so a recording is only analyzed
+/// again when it changes instead of every frame
+#[derive(Resource, Default)]
+pub struct PlotCache {
+    room_acoustics: Option<Cached<RecordKey, RoomAcoustics>>,
+    /// keyed by the reference and measurement recording and the segment size
+    transfer_function: Option<Cached<(RecordKey, RecordKey, usize), Option<TransferFunction>>>,
+    /// spectra of the microphones, keyed by their id
+    spectra: HashMap<usize, MicSpectrum>,
+    /// frames of the spectrogram, keyed by the recording, the window size and the hop
+    spectrogram: Option<Cached<SpectrogramKey, SpectrogramFrames>>,
+    /// image of the spectrogram, keyed like its frames and by the colormap and the dynamic range
+    spectrogram_texture: Option<Cached<SpectrogramTextureKey, egui::TextureHandle>>,
+    /// levels of the bands, keyed by the recording, the bands, the weighting and the duration
+    bands: Option<Cached<BandsKey, Vec<(f32, f32)>>>,
+}
+
+type SpectrogramKey = (RecordKey, usize, usize);
+type SpectrogramFrames = Vec<(f64, Vec<f32>)>;
+type SpectrogramTextureKey = (RecordKey, usize, usize, Gradient, f32);
+type BandsKey = (RecordKey, BandResolution, FrequencyWeighting, f32);
+
+/// Identifies the recording of a microphone at the cell size it was recorded with
+#[derive(PartialEq)]
+struct RecordKey {
+    mic_id: Option<usize>,
+    delta_t: f32,
+    len: usize,
+    last: Option<[f64; 2]>,
+}
+
+impl RecordKey {
+    fn new(mic: Option<&Microphone>, delta_t: f32) -> Self {
+        Self {
+            mic_id: mic.map(|mic| mic.id),
+            delta_t,
+            len: mic.map_or(0, |mic| mic.record.len()),
+            last: mic.and_then(|mic| mic.record.last().copied()),
+        }
+    }
+}
+
+/// A value that was calculated for the given key
+struct Cached<K, V> {
+    key: K,
+    value: V,
+}
+
+/// Returns the cached value, calculating it again if the key has changed
+fn cached<K: PartialEq, V>(
+    cache: &mut Option<Cached<K, V>>,
+    key: K,
+    calc: impl FnOnce() -> V,
+) -> &V {
+    if cache.as_ref().is_none_or(|cached| cached.key != key) {
+        *cache = Some(Cached { key, value: calc() });
+    }
+    &cache.as_ref().unwrap().value
+}
+
+/// The spectrum of a microphone together with the blocks of its record averaged so far
+#[derive(Default)]
+struct MicSpectrum {
+    /// cell size, block size, window and zero padding of the averaged blocks
+    settings: Option<(f32, usize, SpectralWindow, usize)>,
+    /// simulation time of the last calculation, a reset of the simulation starts a new record
+    sim_time: f64,
+    welch: WelchSum,
+    /// keyed by the record, the scaling and whether the blocks are averaged
+    spectrum: Option<Cached<(RecordKey, FftScaling, bool), Spectrum>>,
+}
+
+/// The room acoustic parameters and the schroeder decay curves (time in ms, level in dB) of an
+/// impulse response, in total and per octave band
+struct RoomAcoustics {
+    parameters: Vec<(String, RoomParameters)>,
+    decays: Vec<(String, Vec<[f64; 2]>)>,
+}
+
 pub enum Tab {
     Volume,
     Frequency,
+    Spectrogram,
+    Bands,
+    TransferFunction,
+    NoiseControl,
+    RoomAcoustics,
 }
 
 pub struct PlotTabs<'a> {
@@ -33,6 +147,10 @@ pub struct PlotTabs<'a> {
     sim_time: f64,
     delta_time: f64,
     ui_state: &'a mut UiState,
+    noise_control: &'a mut NoiseControl,
+    source_ids: &'a [usize],
+    error_message: &'a mut ErrorMessage,
+    plot_cache: &'a mut PlotCache,
 }
 
 impl<'a> PlotTabs<'a> {
@@ -43,6 +161,10 @@ impl<'a> PlotTabs<'a> {
         sim_time: f64,
         delta_time: f64,
         ui_state: &'a mut UiState,
+        noise_control: &'a mut NoiseControl,
+        source_ids: &'a [usize],
+        error_message: &'a mut ErrorMessage,
+        plot_cache: &'a mut PlotCache,
     ) -> Self {
         Self {
             mics,
@@ -51,6 +173,10 @@ impl<'a> PlotTabs<'a> {
             sim_time,
             ui_state,
             delta_time,
+            noise_control,
+            source_ids,
+            error_message,
+            plot_cache,
         }
     }
 }
@@ -61,6 +187,11 @@ impl egui_dock::TabViewer for PlotTabs<'_> {
         match tab {
             Tab::Volume => "Volume".into(),
             Tab::Frequency => "Frequency".into(),
+            Tab::Spectrogram => "Spectrogram".into(),
+            Tab::Bands => "Bands".into(),
+            Tab::TransferFunction => "Transfer function".into(),
+            Tab::NoiseControl => "Noise control".into(),
+            Tab::RoomAcoustics => "Room acoustics".into(),
         }
     }
 
@@ -78,7 +209,31 @@ impl egui_dock::TabViewer for PlotTabs<'_> {
                 ui.horizontal(|ui| {
                     ui.checkbox(&mut self.ui_state.scroll_volume_plot, "Scroll volume plot");
 
-                    ui.with_layout(egui::Layout::top_down(egui::Align::RIGHT), |ui| {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.menu_button("Export to WAV", |ui| self.wav_export_ui(ui));
+
+                        if ui
+                            .add_enabled(
+                                self.mics.iter().any(|mic| !mic.record.is_empty()),
+                                egui::Button::new("Export to CSV"),
+                            )
+                            .on_hover_text("Save the recordings of all microphones to one CSV file")
+                            .clicked()
+                        {
+                            let mics = self.mics.iter().map(|mic| &**mic).collect::<Vec<_>>();
+                            match encode_csv(&mics, self.delta_t, self.ui_state.delta_l) {
+                                Ok(contents) => self
+                                    .commands
+                                    .dialog()
+                                    .add_filter("CSV", &["csv"])
+                                    .set_file_name("microphones.csv")
+                                    .set_directory("./")
+                                    .set_title("Select a file to save to")
+                                    .save_file::<CsvExportContents>(contents),
+                                Err(err) => self.error_message.0 = Some(err),
+                            }
+                        }
+
                         if ui
                             .button("Export to SVG")
                             .on_hover_text("Save the plot to an SVG file")
@@ -144,7 +299,7 @@ impl egui_dock::TabViewer for PlotTabs<'_> {
                                     .x_labels(5)
                                     .y_labels(5)
                                     .y_label_formatter(&|x| format!("{:.2}", x))
-                                    .y_desc("Amplitude")
+                                    .y_desc("Sound pressure (Pa)")
                                     .x_desc("Simulation time (s)")
                                     .draw()
                                     .unwrap();
@@ -194,6 +349,20 @@ impl egui_dock::TabViewer for PlotTabs<'_> {
                     });
                 });
 
+                ui.horizontal_wrapped(|ui| {
+                    for mic in self.mics.iter() {
+                        let level = mic
+                            .level(LEVEL_DURATION)
+                            .map_or("-".to_string(), |level| format!("{:.1}", level));
+                        ui.label(format!("Microphone {}: {} dB SPL", mic.id, level))
+                            .on_hover_text(format!(
+                                "Sound pressure level of the last {} ms",
+                                LEVEL_DURATION * 1000.
+                            ));
+                        ui.add(egui::Separator::default().vertical());
+                    }
+                });
+
                 ui.separator();
 
                 let scroll_volume_plot = self.ui_state.scroll_volume_plot;
@@ -203,9 +372,9 @@ impl egui_dock::TabViewer for PlotTabs<'_> {
                     .allow_drag(!scroll_volume_plot)
                     .allow_scroll(!scroll_volume_plot)
                     .x_axis_label("Simulation time (ms)")
-                    .y_axis_label("Amplitude")
+                    .y_axis_label("Sound pressure (Pa)")
                     .label_formatter(|_, value| {
-                        format!("Amplitude: {:.2}\nTime: {:.4} ms", value.y, value.x)
+                        format!("Sound pressure: {:.3} Pa\nTime: {:.4} ms", value.y, value.x)
                     })
                     .legend(egui_plot::Legend::default())
                     .show(ui, |plot_ui| {
@@ -293,19 +462,100 @@ impl egui_dock::TabViewer for PlotTabs<'_> {
                         });
                 });
 
+                let ui_state = &*self.ui_state;
+                let (delta_t, sim_time) = (self.delta_t, self.sim_time);
+                let cache = &mut self.plot_cache.spectra;
+                cache.retain(|mic_id, _| self.mics.iter().any(|mic| mic.id == *mic_id));
+                let spectra = self
+                    .mics
+                    .iter()
+                    .filter(|mic| mic.show_fft)
+                    .map(|mic| {
+                        let cached_spectrum = cache.entry(mic.id).or_default();
+                        let settings = (
+                            delta_t,
+                            ui_state.fft_window_size,
+                            ui_state.fft_window,
+                            ui_state.fft_zero_padding,
+                        );
+                        if cached_spectrum.settings != Some(settings)
+                            || sim_time < cached_spectrum.sim_time
+                        {
+                            *cached_spectrum = MicSpectrum {
+                                settings: Some(settings),
+                                ..Default::default()
+                            };
+                        }
+                        cached_spectrum.sim_time = sim_time;
+
+                        let spectrum = cached(
+                            &mut cached_spectrum.spectrum,
+                            (
+                                RecordKey::new(Some(mic), delta_t),
+                                ui_state.fft_scaling,
+                                ui_state.fft_averaging,
+                            ),
+                            || {
+                                calc_mic_spectrum(
+                                    mic,
+                                    ui_state.fft_scaling,
+                                    delta_t,
+                                    ui_state.fft_window_size,
+                                    ui_state.fft_window,
+                                    ui_state.fft_averaging,
+                                    ui_state.fft_zero_padding,
+                                    &mut cached_spectrum.welch,
+                                )
+                            },
+                        );
+                        (mic.id, spectrum.clone())
+                    })
+                    .collect::<Vec<_>>();
+
+                ui.horizontal(|ui| self.spectrum_settings_ui(ui, &spectra));
+
                 ui.separator();
 
+                let peaks = if self.ui_state.show_fft_peaks {
+                    spectra
+                        .iter()
+                        .map(|(mic_id, spectrum)| {
+                            (
+                                *mic_id,
+                                find_peaks(
+                                    spectrum,
+                                    self.ui_state.fft_scaling,
+                                    self.ui_state.fft_peak_count,
+                                    self.ui_state.fft_peak_prominence,
+                                ),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                } else {
+                    vec![]
+                };
+                if self.ui_state.show_fft_peaks {
+                    peaks_table_ui(ui, &peaks, self.ui_state.fft_scaling);
+                }
+
                 let unit = match self.ui_state.fft_scaling {
                     FftScaling::Normalized => "",
-                    FftScaling::Decibels => "(dB)",
+                    FftScaling::Decibels => "(dB SPL)",
+                };
+                let height = if self.ui_state.show_fft_phase {
+                    (ui.available_height() / 2. - ui.spacing().item_spacing.y).max(50.)
+                } else {
+                    ui.available_height()
                 };
                 Plot::new("fft_plot")
+                    .height(height)
+                    .link_axis("fft_frequency", true, false)
                     .allow_zoom([false, false])
                     .allow_scroll(false)
                     .allow_drag(false)
                     .allow_boxed_zoom(false)
                     .x_axis_label("Frequency (Hz)")
-                    .y_axis_label(format!("Intensity {}", unit))
+                    .y_axis_label(format!("Level {}", unit))
                     .legend(egui_plot::Legend::default())
                     .x_grid_spacer(|input| {
                         let mut marks = Vec::with_capacity(
@@ -323,7 +573,7 @@ impl egui_dock::TabViewer for PlotTabs<'_> {
                     .x_axis_formatter(|mark, _| format!("{:.0}", 10_f64.powf(mark.value)))
                     .label_formatter(move |_, value| {
                         format!(
-                            "Intensity: {:.2} {}\nFrequency: {:.2} (Hz)",
+                            "Level: {:.2} {}\nFrequency: {:.2} (Hz)",
                             value.y,
                             unit,
                             10_f64.powf(value.x)
@@ -335,26 +585,21 @@ impl egui_dock::TabViewer for PlotTabs<'_> {
                         let mut current_highest_x = 1f64;
                         let mut current_highest_y = 1f64;
 
-                        for (index, mic) in self.mics.iter_mut().enumerate() {
-                            if !mic.show_fft {
-                                continue;
-                            }
-
-                            let mapped_spectrum = calc_mic_spectrum(
-                                mic,
-                                self.ui_state.fft_scaling,
-                                self.delta_t,
-                                self.ui_state.fft_window_size,
-                            );
-
-                            // remove the first element, because of log it is at x=-inf
-                            let mapped_spectrum = &mapped_spectrum[1..];
+                        for (index, (mic_id, spectrum)) in spectra.iter().enumerate() {
+                            let mapped_spectrum = &spectrum
+                                .frequencies
+                                .iter()
+                                .zip(&spectrum.levels)
+                                .map(|(frequency, level)| [frequency.log10() as f64, *level as f64])
+                                .collect::<Vec<_>>();
 
                             if self.ui_state.show_fft_approx {
                                 let mut result = Vec::with_capacity(mapped_spectrum.len());
 
-                                let n =
-                                    (self.ui_state.fft_window_size as f64 / 256.).round() as i32;
+                                let n = (self.ui_state.fft_window_size as f64
+                                    * self.ui_state.fft_zero_padding as f64
+                                    / 256.)
+                                    .round() as i32;
                                 for i in 0..mapped_spectrum.len() {
                                     let lower = if i as i32 - n < 0 {
                                         0usize
@@ -382,11 +627,11 @@ impl egui_dock::TabViewer for PlotTabs<'_> {
                                         .collect(),
                                 );
                                 let line = Line::new(points);
-                                plot_ui.line(line.name(format!("Approximation {}", mic.id)));
+                                plot_ui.line(line.name(format!("Approximation {}", mic_id)));
                             } else {
                                 let points = PlotPoints::new(mapped_spectrum.to_vec());
                                 let line = Line::new(points);
-                                plot_ui.line(line.name(format!("Microphone {}", mic.id)));
+                                plot_ui.line(line.name(format!("Microphone {}", mic_id)));
                             }
 
                             let y_padding = match self.ui_state.fft_scaling {
@@ -427,6 +672,28 @@ impl egui_dock::TabViewer for PlotTabs<'_> {
                             }
                         }
 
+                        for (mic_id, peaks) in &peaks {
+                            let points = peaks
+                                .iter()
+                                .map(|peak| [peak.frequency.log10() as f64, peak.level as f64])
+                                .collect::<Vec<_>>();
+                            for point in &points {
+                                plot_ui.text(
+                                    egui_plot::Text::new(
+                                        PlotPoint::new(point[0], point[1]),
+                                        format!("{:.1} Hz", 10_f64.powf(point[0])),
+                                    )
+                                    .anchor(egui::Align2::CENTER_BOTTOM),
+                                );
+                            }
+                            plot_ui.points(
+                                Points::new(points)
+                                    .shape(MarkerShape::Down)
+                                    .radius(4.)
+                                    .name(format!("Peaks {}", mic_id)),
+                            );
+                        }
+
                         const ANIMATION_DURATION: f64 = 0.2; // Duration in seconds
                         const UPDATE_RATE: f64 = 1.0 / ANIMATION_DURATION; // How fast to update based on duration
 
@@ -459,11 +726,1164 @@ impl egui_dock::TabViewer for PlotTabs<'_> {
                             [highest_x, highest_y],
                         ));
                     });
+
+                if self.ui_state.show_fft_phase {
+                    log_frequency_plot("fft_phase_plot", "Phase (°)")
+                        .link_axis("fft_frequency", true, false)
+                        .include_y(-180.)
+                        .include_y(180.)
+                        .show(ui, |plot_ui| {
+                            for (mic_id, spectrum) in &spectra {
+                                let points = spectrum
+                                    .frequencies
+                                    .iter()
+                                    .zip(&spectrum.phases)
+                                    .map(|(frequency, phase)| {
+                                        [frequency.log10() as f64, *phase as f64]
+                                    })
+                                    .collect::<Vec<_>>();
+                                plot_ui.line(
+                                    Line::new(PlotPoints::new(points))
+                                        .name(format!("Microphone {}", mic_id)),
+                                );
+                            }
+                        });
+                }
             }
+            Tab::Spectrogram => self.spectrogram_ui(ui),
+            Tab::Bands => self.bands_ui(ui),
+            Tab::TransferFunction => self.transfer_function_ui(ui),
+            Tab::NoiseControl => self.noise_control_ui(ui),
+            Tab::RoomAcoustics => self.room_acoustics_ui(ui),
         };
     }
 }
 
 pub fn create_tree() -> egui_dock::DockState<Tab> {
-    egui_dock::DockState::new(vec![Tab::Volume, Tab::Frequency])
+    egui_dock::DockState::new(vec![
+        Tab::Volume,
+        Tab::Frequency,
+        Tab::Spectrogram,
+        Tab::Bands,
+        Tab::TransferFunction,
+        Tab::NoiseControl,
+        Tab::RoomAcoustics,
+    ])
+}
+
+impl PlotTabs<'_> {
+    /// Draws the settings of the active noise control and its convergence.
+    fn noise_control_ui(&mut self, ui: &mut egui::Ui) {
+        let noise_control = &mut *self.noise_control;
+        let mut mic_ids = self.mics.iter().map(|mic| mic.id).collect::<Vec<_>>();
+        mic_ids.sort();
+        let mut changed = false;
+
+        ui.horizontal_wrapped(|ui| {
+            changed |= ui
+                .checkbox(&mut noise_control.enabled, "Enable")
+                .on_hover_text("Drive the secondary source with the filtered-x LMS controller")
+                .changed();
+
+            ui.add(egui::Separator::default().vertical());
+
+            for (label, mic_id) in [
+                ("Reference", &mut noise_control.reference_mic),
+                ("Error", &mut noise_control.error_mic),
+            ] {
+                egui::ComboBox::from_label(label)
+                    .selected_text(format!("Microphone {}", mic_id))
+                    .show_ui(ui, |ui| {
+                        for id in &mic_ids {
+                            changed |= ui
+                                .selectable_value(mic_id, *id, format!("Microphone {}", id))
+                                .changed();
+                        }
+                    });
+            }
+            egui::ComboBox::from_label("Secondary source")
+                .selected_text(format!("Source {}", noise_control.secondary_source))
+                .show_ui(ui, |ui| {
+                    for id in self.source_ids {
+                        changed |= ui
+                            .selectable_value(
+                                &mut noise_control.secondary_source,
+                                *id,
+                                format!("Source {}", id),
+                            )
+                            .changed();
+                    }
+                });
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Filter length:");
+            changed |= ui
+                .add(egui::DragValue::new(&mut noise_control.filter_length).range(1..=2048))
+                .on_hover_text("Number of taps of the control filter")
+                .changed();
+            ui.label("Step size:");
+            ui.add(
+                egui::DragValue::new(&mut noise_control.step_size)
+                    .speed(0.001)
+                    .range(0.0..=1.0),
+            )
+            .on_hover_text("Normalized step size of the control filter");
+
+            ui.add(egui::Separator::default().vertical());
+
+            ui.label("Secondary path length:");
+            ui.add(egui::DragValue::new(&mut noise_control.path_length).range(1..=4096))
+                .on_hover_text("Number of taps of the secondary path estimate");
+            ui.label("Identification:");
+            ui.add(
+                egui::DragValue::new(&mut noise_control.identification_duration)
+                    .speed(0.001)
+                    .range(0.0..=1.0)
+                    .suffix(" s"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut noise_control.identification_amplitude)
+                    .speed(0.01)
+                    .range(0.0..=25.0)
+                    .suffix(" Pa"),
+            )
+            .on_hover_text("RMS sound pressure of the identification noise at 1 m");
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label(format!("Stage: {}", noise_control.stage));
+            if ui.button("Identify secondary path").clicked() {
+                noise_control.identify();
+            }
+            if noise_control.stage == NoiseControlStage::Identification
+                && ui.button("Start control").clicked()
+            {
+                noise_control.stage = NoiseControlStage::Control;
+            }
+
+            if !mic_ids.contains(&noise_control.reference_mic)
+                || !mic_ids.contains(&noise_control.error_mic)
+                || !self.source_ids.contains(&noise_control.secondary_source)
+            {
+                ui.colored_label(
+                    egui::Color32::LIGHT_RED,
+                    "The microphones and the secondary source have to exist",
+                );
+            }
+
+            // attenuation of the error microphone since the start of the control
+            if let Some(control_start) = noise_control.control_start {
+                let levels = &noise_control.error_levels;
+                if let (Some(before), Some(now)) = (
+                    levels.iter().rev().find(|level| level[0] <= control_start),
+                    levels.last(),
+                ) {
+                    ui.add(egui::Separator::default().vertical());
+                    ui.label(format!("Attenuation: {:.1} dB", before[1] - now[1]));
+                }
+            }
+        });
+
+        if changed {
+            noise_control.reset();
+        }
+
+        ui.separator();
+
+        ui.columns(2, |columns| {
+            Plot::new("noise_control_plot")
+                .x_axis_label("Simulation time (ms)")
+                .y_axis_label("Level at the error microphone (dB SPL)")
+                .label_formatter(|_, value| {
+                    format!("Level: {:.1} dB SPL\nTime: {:.2} ms", value.y, value.x)
+                })
+                .show(&mut columns[0], |plot_ui| {
+                    let points = noise_control
+                        .error_levels
+                        .iter()
+                        .map(|level| [level[0] * 1000., level[1]])
+                        .collect::<Vec<_>>();
+                    plot_ui.line(Line::new(PlotPoints::new(points)).name("Error microphone"));
+                    if let Some(control_start) = noise_control.control_start {
+                        plot_ui.vline(VLine::new(control_start * 1000.).name("Control start"));
+                    }
+                });
+
+            let delta_t = self.delta_t as f64;
+            Plot::new("secondary_path_plot")
+                .x_axis_label("Time (ms)")
+                .y_axis_label("Secondary path estimate")
+                .show(&mut columns[1], |plot_ui| {
+                    let points = noise_control
+                        .secondary_path()
+                        .iter()
+                        .enumerate()
+                        .map(|(index, tap)| [index as f64 * delta_t * 1000., *tap as f64])
+                        .collect::<Vec<_>>();
+                    plot_ui.line(Line::new(PlotPoints::new(points)).name("Secondary path"));
+                });
+        });
+    }
+}
+
+impl PlotTabs<'_> {
+    /// Draws the settings of the wave file export and exports all microphones into one file.
+    fn wav_export_ui(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label("Sample format")
+            .selected_text(self.ui_state.wav_sample_format.to_string())
+            .show_ui(ui, |ui| {
+                for format in [
+                    WavSampleFormat::Int16,
+                    WavSampleFormat::Int24,
+                    WavSampleFormat::Float32,
+                ] {
+                    ui.selectable_value(
+                        &mut self.ui_state.wav_sample_format,
+                        format,
+                        format.to_string(),
+                    );
+                }
+            });
+
+        egui::ComboBox::from_label("Normalization")
+            .selected_text(self.ui_state.wav_normalization.to_string())
+            .show_ui(ui, |ui| {
+                for normalization in [
+                    WavNormalization::Peak,
+                    WavNormalization::Calibrated { full_scale: 20. },
+                ] {
+                    let selected = std::mem::discriminant(&self.ui_state.wav_normalization)
+                        == std::mem::discriminant(&normalization);
+                    if ui
+                        .selectable_label(selected, normalization.to_string())
+                        .clicked()
+                        && !selected
+                    {
+                        self.ui_state.wav_normalization = normalization;
+                    }
+                }
+            });
+        if let WavNormalization::Calibrated { full_scale } = &mut self.ui_state.wav_normalization {
+            ui.horizontal(|ui| {
+                ui.label("Full scale:");
+                ui.add(
+                    egui::DragValue::new(full_scale)
+                        .speed(0.1)
+                        .range(0.001..=10000.0)
+                        .suffix(" Pa"),
+                )
+                .on_hover_text("Sound pressure of a full scale sample");
+                ui.label(format!("({:.1} dB SPL)", pressure_to_db_spl(*full_scale)));
+            });
+        }
+
+        ui.separator();
+
+        let recorded = self.mics.iter().any(|mic| !mic.record.is_empty());
+        if ui
+            .add_enabled(recorded, egui::Button::new("Export all microphones"))
+            .on_hover_text("One channel per microphone, ordered by id")
+            .on_disabled_hover_text("The microphones record while the plots are shown")
+            .clicked()
+        {
+            let channels = self
+                .mics
+                .iter()
+                .map(|mic| mic.record.iter().map(|record| record[1]).collect())
+                .collect::<Vec<_>>();
+            match encode_wav(
+                &channels,
+                (1. / self.delta_t).round() as u32,
+                self.ui_state.wav_sample_format,
+                self.ui_state.wav_normalization,
+            ) {
+                Ok(contents) => self
+                    .commands
+                    .dialog()
+                    .add_filter("WAV", &["wav"])
+                    .set_file_name("microphones.wav")
+                    .set_directory("./")
+                    .set_title("Select a file to save to")
+                    .save_file::<WavExportContents>(contents),
+                Err(err) => self.error_message.0 = Some(err),
+            }
+            ui.close_menu();
+        }
+    }
+}
+
+impl PlotTabs<'_> {
+    /// Draws the decay curves and the room acoustic parameters of the impulse response recorded
+    /// by a microphone, in total and per octave band.
+    fn room_acoustics_ui(&mut self, ui: &mut egui::Ui) {
+        let mut mic_ids = self.mics.iter().map(|mic| mic.id).collect::<Vec<_>>();
+        mic_ids.sort();
+        let mic_id = &mut self.ui_state.room_acoustics_mic;
+        if mic_id.is_none_or(|id| !mic_ids.contains(&id)) {
+            *mic_id = mic_ids.first().copied();
+        }
+
+        let mic = mic_id.and_then(|id| self.mics.iter().find(|mic| mic.id == id));
+        let has_record = mic.is_some_and(|mic| !mic.record.is_empty());
+        let delta_t = self.delta_t;
+        let room_acoustics = cached(
+            &mut self.plot_cache.room_acoustics,
+            RecordKey::new(mic.map(|mic| &**mic), delta_t),
+            || {
+                let impulse_response = mic
+                    .map(|mic| {
+                        mic.record
+                            .iter()
+                            .map(|record| record[1] as f32)
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                calc_room_acoustics(impulse_response, delta_t)
+            },
+        );
+        let parameters = &room_acoustics.parameters;
+
+        ui.horizontal_wrapped(|ui| {
+            egui::ComboBox::from_label("Impulse response")
+                .selected_text(match mic_id {
+                    Some(id) => format!("Microphone {}", id),
+                    None => "No microphone".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    for id in &mic_ids {
+                        ui.selectable_value(mic_id, Some(*id), format!("Microphone {}", id));
+                    }
+                })
+                .response
+                .on_hover_text("Excite the room with an impulse, e.g. a gaussian source");
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui
+                    .add_enabled(has_record, egui::Button::new("Export to CSV"))
+                    .on_hover_text("Save the table of the parameters to a CSV file")
+                    .on_disabled_hover_text("The microphones record while the plots are shown")
+                    .clicked()
+                {
+                    match encode_room_parameters(parameters, mic_id.unwrap_or_default()) {
+                        Ok(contents) => self
+                            .commands
+                            .dialog()
+                            .add_filter("CSV", &["csv"])
+                            .set_file_name("room_acoustics.csv")
+                            .set_directory("./")
+                            .set_title("Select a file to save to")
+                            .save_file::<CsvExportContents>(contents),
+                        Err(err) => self.error_message.0 = Some(err),
+                    }
+                }
+            });
+        });
+
+        ui.separator();
+
+        let format = |value: Option<f32>, scale: f32, decimals: usize| {
+            value.map_or("-".to_string(), |value| {
+                format!("{:.*}", decimals, value * scale)
+            })
+        };
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            egui::Grid::new("room_acoustics_table")
+                .striped(true)
+                .show(ui, |ui| {
+                    for header in [
+                        "Band", "T20 (s)", "T30 (s)", "EDT (s)", "C50 (dB)", "C80 (dB)", "D50 (%)",
+                        "Ts (ms)",
+                    ] {
+                        ui.strong(header);
+                    }
+                    ui.end_row();
+
+                    for (name, parameters) in parameters {
+                        ui.label(name);
+                        ui.label(format(parameters.t20, 1., 2));
+                        ui.label(format(parameters.t30, 1., 2));
+                        ui.label(format(parameters.edt, 1., 2));
+                        ui.label(format(parameters.c50, 1., 1));
+                        ui.label(format(parameters.c80, 1., 1));
+                        ui.label(format(parameters.d50, 100., 1));
+                        ui.label(format(parameters.centre_time, 1000., 1));
+                        ui.end_row();
+                    }
+                });
+        });
+
+        ui.separator();
+
+        Plot::new("room_acoustics_plot")
+            .x_axis_label("Time after the direct sound (ms)")
+            .y_axis_label("Schroeder decay curve (dB)")
+            .legend(egui_plot::Legend::default())
+            .include_y(0.)
+            .include_y(-60.)
+            .label_formatter(|name, value| {
+                format!(
+                    "{}\nLevel: {:.1} dB\nTime: {:.2} ms",
+                    name, value.y, value.x
+                )
+            })
+            .show(ui, |plot_ui| {
+                for (name, points) in &room_acoustics.decays {
+                    plot_ui.line(Line::new(PlotPoints::new(points.clone())).name(name));
+                }
+            });
+    }
+}
+
+/// Calculates the room acoustic parameters and the decay curves of an impulse response, in total
+/// and per octave band
+fn calc_room_acoustics(impulse_response: Vec<f32>, delta_t: f32) -> RoomAcoustics {
+    let sample_rate = 1. / delta_t;
+    // the upper band edge has to be below the Nyquist frequency
+    let bands = OCTAVE_BANDS
+        .iter()
+        .filter(|center| **center * std::f32::consts::SQRT_2 < sample_rate / 2.)
+        .map(|center| {
+            (
+                band_name(*center),
+                band_filter(
+                    &impulse_response,
+                    exact_center(*center, 1.),
+                    1.,
+                    sample_rate,
+                ),
+            )
+        })
+        .collect::<Vec<_>>();
+    let bands = std::iter::once(("Broadband".to_string(), impulse_response))
+        .chain(bands)
+        .collect::<Vec<_>>();
+
+    let parameters = bands
+        .iter()
+        .map(|(name, samples)| (name.clone(), RoomParameters::calc(samples, sample_rate)))
+        .collect();
+    let decays = bands
+        .iter()
+        .filter_map(|(name, samples)| {
+            let decay = schroeder_decay(&samples[onset(samples)?..]);
+            let step = (decay.len() / MAX_DECAY_POINTS).max(1);
+            let points = decay
+                .iter()
+                .enumerate()
+                .step_by(step)
+                .filter(|(_, level)| level.is_finite())
+                .map(|(index, level)| [index as f64 * delta_t as f64 * 1000., *level as f64])
+                .collect();
+            Some((name.clone(), points))
+        })
+        .collect();
+
+    RoomAcoustics { parameters, decays }
+}
+
+impl PlotTabs<'_> {
+    /// Draws the spectrogram of the latest recording of a microphone, scrolling with the
+    /// simulation.
+    fn spectrogram_ui(&mut self, ui: &mut egui::Ui) {
+        let mut mic_ids = self.mics.iter().map(|mic| mic.id).collect::<Vec<_>>();
+        mic_ids.sort();
+        let ui_state = &mut *self.ui_state;
+        if ui_state
+            .spectrogram_mic
+            .is_none_or(|id| !mic_ids.contains(&id))
+        {
+            ui_state.spectrogram_mic = mic_ids.first().copied();
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            egui::ComboBox::from_label("Microphone")
+                .selected_text(match ui_state.spectrogram_mic {
+                    Some(id) => format!("Microphone {}", id),
+                    None => "No microphone".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    for id in &mic_ids {
+                        ui.selectable_value(
+                            &mut ui_state.spectrogram_mic,
+                            Some(*id),
+                            format!("Microphone {}", id),
+                        );
+                    }
+                });
+
+            ui.add(egui::Separator::default().vertical());
+
+            egui::ComboBox::from_label("Window size")
+                .selected_text(ui_state.spectrogram_window_size.to_string())
+                .show_ui(ui, |ui| {
+                    for window_size in [256, 512, 1024, 2048, 4096, 8192] {
+                        ui.selectable_value(
+                            &mut ui_state.spectrogram_window_size,
+                            window_size,
+                            format!("{}", window_size),
+                        );
+                    }
+                });
+            ui.label("Hop:");
+            ui.add(
+                egui::DragValue::new(&mut ui_state.spectrogram_hop)
+                    .range(16..=ui_state.spectrogram_window_size)
+                    .suffix(" samples"),
+            )
+            .on_hover_text("Number of samples between the starts of two frames");
+
+            ui.add(egui::Separator::default().vertical());
+
+            egui::ComboBox::from_label("Colormap")
+                .selected_text(format!("{:?}", ui_state.spectrogram_gradient))
+                .show_ui(ui, |ui| {
+                    for gradient in [
+                        Gradient::Turbo,
+                        Gradient::Viridis,
+                        Gradient::Magma,
+                        Gradient::Inferno,
+                        Gradient::Plasma,
+                        Gradient::Bw,
+                    ] {
+                        ui.selectable_value(
+                            &mut ui_state.spectrogram_gradient,
+                            gradient,
+                            format!("{:?}", gradient),
+                        );
+                    }
+                });
+            ui.label("Dynamic range:");
+            ui.add(
+                egui::DragValue::new(&mut ui_state.spectrogram_dynamic_range)
+                    .range(10.0..=200.0)
+                    .suffix(" dB"),
+            )
+            .on_hover_text("Range of levels below the maximum that is shown");
+        });
+
+        ui.separator();
+
+        let delta_t = self.delta_t;
+        let sample_rate = 1. / delta_t;
+        let mic = ui_state
+            .spectrogram_mic
+            .and_then(|id| self.mics.iter().find(|mic| mic.id == id))
+            .map(|mic| &**mic);
+        let (window_size, hop) = (ui_state.spectrogram_window_size, ui_state.spectrogram_hop);
+        let frames = cached(
+            &mut self.plot_cache.spectrogram,
+            (RecordKey::new(mic, delta_t), window_size, hop),
+            || {
+                mic.map(|mic| calc_mic_spectrogram(mic, window_size, hop, MAX_SPECTROGRAM_FRAMES))
+                    .unwrap_or_default()
+            },
+        );
+
+        // the bins are spaced by the sample rate divided by the window size
+        let bin_width = sample_rate / window_size as f32;
+        let bins = frames.first().map_or(0, |(_, levels)| {
+            levels
+                .len()
+                .min((MAX_SPECTROGRAM_FREQUENCY / bin_width) as usize + 1)
+        });
+
+        // the texture is only uploaded again when the frames or the colors change
+        let texture = cached(
+            &mut self.plot_cache.spectrogram_texture,
+            (
+                RecordKey::new(mic, delta_t),
+                window_size,
+                hop,
+                ui_state.spectrogram_gradient,
+                ui_state.spectrogram_dynamic_range,
+            ),
+            || {
+                let max_level = frames
+                    .iter()
+                    .flat_map(|(_, levels)| &levels[..bins])
+                    .fold(f32::NEG_INFINITY, |max, level| max.max(*level));
+                let min_level = max_level - ui_state.spectrogram_dynamic_range;
+
+                // the highest frequency is the top row of the image
+                let mut image =
+                    egui::ColorImage::new([frames.len().max(1), bins.max(1)], egui::Color32::BLACK);
+                for (x, (_, levels)) in frames.iter().enumerate() {
+                    for (bin, level) in levels[..bins].iter().enumerate() {
+                        let [r, g, b] = ui_state.spectrogram_gradient.at(
+                            level.clamp(min_level, max_level),
+                            min_level,
+                            max_level,
+                        );
+                        image[(x, bins - 1 - bin)] = egui::Color32::from_rgb(r, g, b);
+                    }
+                }
+
+                ui.ctx()
+                    .load_texture("spectrogram", image, egui::TextureOptions::NEAREST)
+            },
+        );
+
+        Plot::new("spectrogram_plot")
+            .x_axis_label("Simulation time (ms)")
+            .y_axis_label("Frequency (kHz)")
+            .label_formatter(|_, value| {
+                format!("Time: {:.2} ms\nFrequency: {:.2} kHz", value.x, value.y)
+            })
+            .show(ui, |plot_ui| {
+                let (Some((first, _)), Some((last, _))) = (frames.first(), frames.last()) else {
+                    return;
+                };
+                // every frame is as wide as the hop and every bin as high as the bin width
+                let hop = hop as f64 * delta_t as f64 * 1000.;
+                let width = (last - first) * 1000. + hop;
+                let height = bins as f64 * bin_width as f64 / 1000.;
+                plot_ui.image(PlotImage::new(
+                    texture,
+                    PlotPoint::new(
+                        (first + last) / 2. * 1000.,
+                        height / 2. - bin_width as f64 / 2000.,
+                    ),
+                    [width as f32, height as f32],
+                ));
+            });
+    }
+}
+
+impl PlotTabs<'_> {
+    /// Draws the weighted levels of the latest recording of a microphone in octave or third
+    /// octave bands.
+    fn bands_ui(&mut self, ui: &mut egui::Ui) {
+        let mut mic_ids = self.mics.iter().map(|mic| mic.id).collect::<Vec<_>>();
+        mic_ids.sort();
+        let ui_state = &mut *self.ui_state;
+        if ui_state.bands_mic.is_none_or(|id| !mic_ids.contains(&id)) {
+            ui_state.bands_mic = mic_ids.first().copied();
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            egui::ComboBox::from_label("Microphone")
+                .selected_text(match ui_state.bands_mic {
+                    Some(id) => format!("Microphone {}", id),
+                    None => "No microphone".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    for id in &mic_ids {
+                        ui.selectable_value(
+                            &mut ui_state.bands_mic,
+                            Some(*id),
+                            format!("Microphone {}", id),
+                        );
+                    }
+                });
+
+            ui.add(egui::Separator::default().vertical());
+
+            egui::ComboBox::from_label("Bands")
+                .selected_text(ui_state.band_resolution.to_string())
+                .show_ui(ui, |ui| {
+                    for resolution in [BandResolution::Octave, BandResolution::ThirdOctave] {
+                        ui.selectable_value(
+                            &mut ui_state.band_resolution,
+                            resolution,
+                            resolution.to_string(),
+                        );
+                    }
+                });
+            egui::ComboBox::from_label("Weighting")
+                .selected_text(ui_state.frequency_weighting.to_string())
+                .show_ui(ui, |ui| {
+                    for weighting in [
+                        FrequencyWeighting::A,
+                        FrequencyWeighting::C,
+                        FrequencyWeighting::Z,
+                    ] {
+                        ui.selectable_value(
+                            &mut ui_state.frequency_weighting,
+                            weighting,
+                            weighting.to_string(),
+                        );
+                    }
+                });
+
+            ui.add(egui::Separator::default().vertical());
+
+            ui.label("Averaging time:");
+            ui.add(
+                egui::DragValue::new(&mut ui_state.band_duration)
+                    .speed(0.001)
+                    .range(0.01..=10.0)
+                    .suffix(" s"),
+            )
+            .on_hover_text("Duration of the latest part of the recording that is analyzed");
+        });
+
+        let delta_t = self.delta_t;
+        let sample_rate = 1. / delta_t;
+        let mic = ui_state
+            .bands_mic
+            .and_then(|id| self.mics.iter().find(|mic| mic.id == id))
+            .map(|mic| &**mic);
+        let levels = cached(
+            &mut self.plot_cache.bands,
+            (
+                RecordKey::new(mic, delta_t),
+                ui_state.band_resolution,
+                ui_state.frequency_weighting,
+                ui_state.band_duration,
+            ),
+            || {
+                let Some(mic) = mic else {
+                    return vec![];
+                };
+                let length = (ui_state.band_duration * sample_rate) as usize;
+                let samples = mic.record[mic.record.len().saturating_sub(length)..]
+                    .iter()
+                    .map(|record| record[1] as f32)
+                    .collect::<Vec<_>>();
+                if samples.is_empty() {
+                    return vec![];
+                }
+                band_levels(
+                    &samples,
+                    ui_state.band_resolution,
+                    ui_state.frequency_weighting,
+                    sample_rate,
+                )
+            },
+        );
+
+        if !levels.is_empty() {
+            let total = 10.
+                * levels
+                    .iter()
+                    .map(|(_, level)| 10_f32.powf(level / 10.))
+                    .sum::<f32>()
+                    .log10();
+            ui.label(format!(
+                "Sum of the bands: {:.1} dB ({})",
+                total, ui_state.frequency_weighting
+            ));
+        }
+
+        ui.separator();
+
+        let names = levels
+            .iter()
+            .map(|(center, _)| band_name(*center))
+            .collect::<Vec<_>>();
+        let unit = format!("dB ({})", ui_state.frequency_weighting);
+        Plot::new("bands_plot")
+            .x_axis_label("Band")
+            .y_axis_label(format!("Level {}", unit))
+            .include_y(0.)
+            .x_grid_spacer(|input| {
+                (input.bounds.0.ceil().max(0.) as usize..=input.bounds.1.max(0.) as usize)
+                    .map(|index| GridMark {
+                        value: index as f64,
+                        step_size: 1.,
+                    })
+                    .collect()
+            })
+            .x_axis_formatter(|mark, _| {
+                names
+                    .get(mark.value.round() as usize)
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .label_formatter(|_, _| String::new())
+            .show(ui, |plot_ui| {
+                let bars = levels
+                    .iter()
+                    .enumerate()
+                    // silent bands have a level of -inf
+                    .filter(|(_, (_, level))| level.is_finite())
+                    .map(|(index, (center, level))| {
+                        Bar::new(index as f64, *level as f64)
+                            .name(band_name(*center))
+                            .width(0.8)
+                    })
+                    .collect();
+                plot_ui.bar_chart(BarChart::new(bars).element_formatter(Box::new(
+                    move |bar, _| format!("{}\nLevel: {:.1} {}", bar.name, bar.value, unit),
+                )));
+            });
+    }
+}
+
+impl PlotTabs<'_> {
+    /// Draws the transfer function and the coherence between a reference and a measurement
+    /// microphone.
+    fn transfer_function_ui(&mut self, ui: &mut egui::Ui) {
+        let mut mic_ids = self.mics.iter().map(|mic| mic.id).collect::<Vec<_>>();
+        mic_ids.sort();
+        let ui_state = &mut *self.ui_state;
+        if ui_state
+            .transfer_reference_mic
+            .is_none_or(|id| !mic_ids.contains(&id))
+        {
+            ui_state.transfer_reference_mic = mic_ids.first().copied();
+        }
+        if ui_state
+            .transfer_measurement_mic
+            .is_none_or(|id| !mic_ids.contains(&id))
+        {
+            ui_state.transfer_measurement_mic = mic_ids.get(1).or(mic_ids.first()).copied();
+        }
+
+        let mic = |id: Option<usize>| {
+            id.and_then(|id| self.mics.iter().find(|mic| mic.id == id))
+                .map(|mic| &**mic)
+        };
+        let reference = mic(ui_state.transfer_reference_mic);
+        let measurement = mic(ui_state.transfer_measurement_mic);
+        let delta_t = self.delta_t;
+        let transfer_function = cached(
+            &mut self.plot_cache.transfer_function,
+            (
+                RecordKey::new(reference, delta_t),
+                RecordKey::new(measurement, delta_t),
+                ui_state.transfer_segment_size,
+            ),
+            || {
+                let samples = |mic: Option<&Microphone>| {
+                    mic.map(|mic| {
+                        mic.record
+                            .iter()
+                            .map(|record| record[1] as f32)
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default()
+                };
+                let reference = samples(reference);
+                let measurement = samples(measurement);
+                // both microphones record until now, but one of them may have started later
+                let len = reference.len().min(measurement.len());
+                calc_transfer_function(
+                    &reference[reference.len() - len..],
+                    &measurement[measurement.len() - len..],
+                    1. / delta_t,
+                    ui_state.transfer_segment_size,
+                )
+            },
+        );
+
+        ui.horizontal_wrapped(|ui| {
+            for (label, mic_id) in [
+                ("Reference", &mut ui_state.transfer_reference_mic),
+                ("Measurement", &mut ui_state.transfer_measurement_mic),
+            ] {
+                egui::ComboBox::from_label(label)
+                    .selected_text(match mic_id {
+                        Some(id) => format!("Microphone {}", id),
+                        None => "No microphone".to_string(),
+                    })
+                    .show_ui(ui, |ui| {
+                        for id in &mic_ids {
+                            ui.selectable_value(mic_id, Some(*id), format!("Microphone {}", id));
+                        }
+                    });
+            }
+
+            ui.add(egui::Separator::default().vertical());
+
+            egui::ComboBox::from_label("Segment size")
+                .selected_text(ui_state.transfer_segment_size.to_string())
+                .show_ui(ui, |ui| {
+                    for segment_size in [256, 512, 1024, 2048, 4096, 8192] {
+                        ui.selectable_value(
+                            &mut ui_state.transfer_segment_size,
+                            segment_size,
+                            format!("{}", segment_size),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text("Number of samples of the averaged segments, overlapping by half");
+
+            ui.add(egui::Separator::default().vertical());
+
+            match transfer_function {
+                Some(transfer_function) => {
+                    ui.label(format!("Averages: {}", transfer_function.averages))
+                }
+                None => ui.label("The recordings are shorter than a segment"),
+            };
+        });
+
+        ui.separator();
+
+        let plots = [
+            ("transfer_magnitude_plot", "Magnitude (dB)"),
+            ("transfer_phase_plot", "Phase (°)"),
+            ("transfer_group_delay_plot", "Group delay (ms)"),
+            ("transfer_coherence_plot", "Coherence"),
+        ];
+        let height = (ui.available_height() / 2. - ui.spacing().item_spacing.y).max(50.);
+        ui.columns(2, |columns| {
+            for (index, (id, label)) in plots.into_iter().enumerate() {
+                let values = transfer_function.as_ref().map(|transfer_function| {
+                    let values = match index {
+                        0 => transfer_function.magnitude.clone(),
+                        1 => transfer_function.phase.clone(),
+                        2 => transfer_function
+                            .group_delay
+                            .iter()
+                            .map(|delay| delay * 1000.)
+                            .collect(),
+                        _ => transfer_function.coherence.clone(),
+                    };
+                    transfer_function
+                        .frequencies
+                        .iter()
+                        .zip(values)
+                        .filter(|(_, value)| value.is_finite())
+                        .map(|(frequency, value)| [frequency.log10() as f64, value as f64])
+                        .collect::<Vec<_>>()
+                });
+
+                let mut plot = log_frequency_plot(id, label)
+                    .link_axis("transfer_frequency", true, false)
+                    .height(height);
+                if index == 3 {
+                    plot = plot.include_y(0.).include_y(1.);
+                }
+                plot.show(&mut columns[index % 2], |plot_ui| {
+                    if let Some(values) = values {
+                        plot_ui.line(Line::new(PlotPoints::new(values)).name(label));
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// Creates a plot with a logarithmic frequency axis, the x values are the decadic logarithm of
+/// the frequency (in Hz).
+fn log_frequency_plot<'a>(id: &str, y_label: &'a str) -> Plot<'a> {
+    Plot::new(id)
+        .x_axis_label("Frequency (Hz)")
+        .y_axis_label(y_label)
+        .x_grid_spacer(|input| {
+            (input.bounds.0.floor() as i32..=input.bounds.1.ceil() as i32)
+                .flat_map(|decade| {
+                    (1..10).map(move |factor| GridMark {
+                        value: decade as f64 + (factor as f64).log10(),
+                        step_size: if factor == 1 { 1. } else { 0.1 },
+                    })
+                })
+                .collect()
+        })
+        .x_axis_formatter(|mark, _| {
+            if mark.step_size >= 1. {
+                format!("{:.0}", 10_f64.powf(mark.value))
+            } else {
+                String::new()
+            }
+        })
+        .label_formatter(move |_, value| {
+            format!(
+                "{}: {:.2}\nFrequency: {:.1} Hz",
+                y_label,
+                value.y,
+                10_f64.powf(value.x)
+            )
+        })
+}
+
+impl PlotTabs<'_> {
+    /// Draws the settings of the spectrum analysis and exports the spectra of the microphones.
+    fn spectrum_settings_ui(&mut self, ui: &mut egui::Ui, spectra: &[(usize, Spectrum)]) {
+        egui::ComboBox::from_label("Window")
+            .selected_text(self.ui_state.fft_window.to_string())
+            .show_ui(ui, |ui| {
+                for window in [
+                    SpectralWindow::Rectangular,
+                    SpectralWindow::Hann,
+                    SpectralWindow::Hamming,
+                    SpectralWindow::BlackmanHarris,
+                    SpectralWindow::FlatTop,
+                ] {
+                    ui.selectable_value(&mut self.ui_state.fft_window, window, window.to_string());
+                }
+            });
+
+        ui.add(egui::Separator::default().vertical());
+
+        ui.checkbox(&mut self.ui_state.fft_averaging, "Welch averaging")
+            .on_hover_text(
+                "Average the spectra of the whole recording in blocks overlapping by half",
+            );
+
+        ui.add(egui::Separator::default().vertical());
+
+        egui::ComboBox::from_label("Zero padding")
+            .selected_text(format!("{}x", self.ui_state.fft_zero_padding))
+            .show_ui(ui, |ui| {
+                for zero_padding in [1, 2, 4, 8] {
+                    ui.selectable_value(
+                        &mut self.ui_state.fft_zero_padding,
+                        zero_padding,
+                        format!("{}x", zero_padding),
+                    );
+                }
+            })
+            .response
+            .on_hover_text("Interpolate the spectrum by extending the blocks with zeros");
+
+        ui.add(egui::Separator::default().vertical());
+
+        ui.checkbox(&mut self.ui_state.show_fft_phase, "Show phase");
+
+        ui.add(egui::Separator::default().vertical());
+
+        ui.checkbox(&mut self.ui_state.show_fft_peaks, "Show peaks");
+        if self.ui_state.show_fft_peaks {
+            ui.add(
+                egui::DragValue::new(&mut self.ui_state.fft_peak_count)
+                    .range(1..=50)
+                    .prefix("max. "),
+            )
+            .on_hover_text("Maximum number of peaks per microphone");
+            ui.add(
+                egui::DragValue::new(&mut self.ui_state.fft_peak_prominence)
+                    .speed(0.1)
+                    .range(0.0..=100.0)
+                    .suffix(" dB"),
+            )
+            .on_hover_text("Minimum height of a peak above its surroundings");
+        }
+
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            let unit = match self.ui_state.fft_scaling {
+                FftScaling::Normalized => "normalized",
+                FftScaling::Decibels => "dB SPL",
+            };
+
+            if ui
+                .add_enabled(!spectra.is_empty(), egui::Button::new("Export to CSV"))
+                .on_hover_text("Save the shown spectra to a CSV file")
+                .clicked()
+            {
+                match encode_spectrum_csv(spectra, unit) {
+                    Ok(contents) => self
+                        .commands
+                        .dialog()
+                        .add_filter("CSV", &["csv"])
+                        .set_file_name("spectrum.csv")
+                        .set_directory("./")
+                        .set_title("Select a file to save to")
+                        .save_file::<CsvExportContents>(contents),
+                    Err(err) => self.error_message.0 = Some(err),
+                }
+            }
+
+            if ui
+                .add_enabled(!spectra.is_empty(), egui::Button::new("Export to SVG"))
+                .on_hover_text("Save the shown spectra to an SVG file")
+                .clicked()
+            {
+                self.commands
+                    .dialog()
+                    .add_filter("SVG", &["svg"])
+                    .set_file_name("spectrum.svg")
+                    .set_directory("./")
+                    .set_title("Select a file to save to")
+                    .save_file::<SvgExportContents>(spectrum_svg(spectra, unit).into_bytes());
+            }
+        });
+    }
+}
+
+/// Draws a table of the peaks of the spectra of the microphones on the right side.
+fn peaks_table_ui(ui: &mut egui::Ui, peaks: &[(usize, Vec<Peak>)], scaling: FftScaling) {
+    egui::SidePanel::right("fft_peaks_panel")
+        .resizable(false)
+        .show_inside(ui, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("fft_peaks_table")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Microphone");
+                        ui.strong("Frequency (Hz)");
+                        ui.strong(format!("Level ({})", scaling));
+                        ui.end_row();
+
+                        for (mic_id, peaks) in peaks {
+                            for peak in peaks {
+                                ui.label(mic_id.to_string());
+                                ui.label(format!("{:.1}", peak.frequency));
+                                ui.label(format!("{:.2}", peak.level));
+                                ui.end_row();
+                            }
+                        }
+                    });
+            });
+        });
+}
+
+/// Draws spectra with a logarithmic frequency axis into an SVG image.
+fn spectrum_svg(spectra: &[(usize, Spectrum)], unit: &str) -> String {
+    let colors = [RED, BLUE, GREEN, CYAN, MAGENTA, BLACK];
+
+    let values = || spectra.iter().flat_map(|(_, spectrum)| &spectrum.levels);
+    let lowest_y = values().copied().fold(f32::INFINITY, f32::min);
+    let highest_y = values().copied().fold(f32::NEG_INFINITY, f32::max);
+    let frequencies = || {
+        spectra
+            .iter()
+            .flat_map(|(_, spectrum)| &spectrum.frequencies)
+    };
+    let lowest_x = frequencies().copied().fold(f32::INFINITY, f32::min);
+    let highest_x = frequencies().copied().fold(0., f32::max);
+
+    let mut string_buffer = String::new();
+    {
+        let root = SVGBackend::with_string(&mut string_buffer, (1000, 600)).into_drawing_area();
+        root.fill(&WHITE).unwrap();
+        let root = root.margin(10, 10, 10, 10);
+
+        let mut chart = ChartBuilder::on(&root)
+            .x_label_area_size(40)
+            .y_label_area_size(50)
+            .build_cartesian_2d((lowest_x..highest_x).log_scale(), lowest_y..highest_y)
+            .unwrap();
+
+        chart
+            .configure_mesh()
+            .x_labels(10)
+            .y_labels(5)
+            .x_label_formatter(&|x| format!("{:.0}", x))
+            .y_label_formatter(&|y| format!("{:.2}", y))
+            .y_desc(format!("Level ({})", unit))
+            .x_desc("Frequency (Hz)")
+            .draw()
+            .unwrap();
+
+        for (index, (mic_id, spectrum)) in spectra.iter().enumerate() {
+            let color = colors[index % colors.len()];
+            chart
+                .draw_series(LineSeries::new(
+                    spectrum
+                        .frequencies
+                        .iter()
+                        .copied()
+                        .zip(spectrum.levels.iter().copied()),
+                    color,
+                ))
+                .unwrap()
+                .label(format!("Microphone {}", mic_id))
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        }
+
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()
+            .unwrap();
+    }
+
+    string_buffer
 }
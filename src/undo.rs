@@ -1,8 +1,12 @@
 use bevy::prelude::*;
 use egui::util::undoer::Undoer;
 
+use crate::components::line_array::LineArray;
 use crate::components::microphone::Microphone;
+use crate::components::plane_wave::PlaneWave;
 use crate::components::source::Source;
+use crate::components::source_group::SourceGroup;
+use crate::components::vibrating_surface::VibratingSurface;
 use crate::components::wall::{CircWall, RectWall};
 use crate::events::{Reset, UpdateWalls};
 use crate::simulation::plugin::ComponentIDs;
@@ -18,6 +22,10 @@ struct State {
     mics: Vec<Microphone>,
     rect_walls: Vec<RectWall>,
     circle_walls: Vec<CircWall>,
+    line_arrays: Vec<LineArray>,
+    plane_waves: Vec<PlaneWave>,
+    surfaces: Vec<VibratingSurface>,
+    source_groups: Vec<SourceGroup>,
     ids: ComponentIDs,
 }
 
@@ -42,22 +50,34 @@ fn update_state(
     mics: Query<&Microphone>,
     rect_walls: Query<&RectWall>,
     circle_walls: Query<&CircWall>,
+    line_arrays: Query<&LineArray>,
+    plane_waves: Query<&PlaneWave>,
+    surfaces: Query<&VibratingSurface>,
+    source_groups: Query<&SourceGroup>,
     ids: Res<ComponentIDs>,
     time: Res<Time>,
 ) {
-    let sources = sources.iter().copied().collect::<Vec<_>>();
+    let sources = sources.iter().cloned().collect::<Vec<_>>();
     let mics = mics
         .iter()
         .map(|mic| Microphone::new(mic.x, mic.y, mic.id))
         .collect::<Vec<_>>();
     let rect_walls = rect_walls.iter().copied().collect::<Vec<_>>();
     let circle_walls = circle_walls.iter().copied().collect::<Vec<_>>();
+    let line_arrays = line_arrays.iter().cloned().collect::<Vec<_>>();
+    let plane_waves = plane_waves.iter().cloned().collect::<Vec<_>>();
+    let surfaces = surfaces.iter().cloned().collect::<Vec<_>>();
+    let source_groups = source_groups.iter().cloned().collect::<Vec<_>>();
 
     let state = State {
         sources,
         mics,
         rect_walls,
         circle_walls,
+        line_arrays,
+        plane_waves,
+        surfaces,
+        source_groups,
         ids: *ids,
     };
 
@@ -107,21 +127,42 @@ pub fn undo_event(
     q_mics: Query<(Entity, &Microphone)>,
     q_rect_walls: Query<(Entity, &RectWall)>,
     q_circle_walls: Query<(Entity, &CircWall)>,
+    q_line_arrays: Query<(Entity, &LineArray)>,
+    q_plane_waves: Query<(Entity, &PlaneWave)>,
+    q_surfaces: Query<(Entity, &VibratingSurface)>,
+    q_source_groups: Query<(Entity, &SourceGroup)>,
 ) {
     for event in undo_ev.read() {
-        let sources = q_sources.iter().map(|x| *x.1).collect::<Vec<_>>();
+        let sources = q_sources.iter().map(|x| x.1.clone()).collect::<Vec<_>>();
         let mics = q_mics
             .iter()
             .map(|(_, mic)| Microphone::new(mic.x, mic.y, mic.id))
             .collect::<Vec<_>>();
         let rect_walls = q_rect_walls.iter().map(|x| *x.1).collect::<Vec<_>>();
         let circle_walls = q_circle_walls.iter().map(|x| *x.1).collect::<Vec<_>>();
+        let line_arrays = q_line_arrays
+            .iter()
+            .map(|x| x.1.clone())
+            .collect::<Vec<_>>();
+        let plane_waves = q_plane_waves
+            .iter()
+            .map(|x| x.1.clone())
+            .collect::<Vec<_>>();
+        let surfaces = q_surfaces.iter().map(|x| x.1.clone()).collect::<Vec<_>>();
+        let source_groups = q_source_groups
+            .iter()
+            .map(|x| x.1.clone())
+            .collect::<Vec<_>>();
 
         let current_state = State {
             sources,
             mics,
             rect_walls,
             circle_walls,
+            line_arrays,
+            plane_waves,
+            surfaces,
+            source_groups,
             ids: *ids,
         };
 
@@ -145,9 +186,21 @@ pub fn undo_event(
             for (e, _) in q_circle_walls.iter() {
                 commands.entity(e).despawn();
             }
+            for (e, _) in q_line_arrays.iter() {
+                commands.entity(e).despawn();
+            }
+            for (e, _) in q_plane_waves.iter() {
+                commands.entity(e).despawn();
+            }
+            for (e, _) in q_surfaces.iter() {
+                commands.entity(e).despawn();
+            }
+            for (e, _) in q_source_groups.iter() {
+                commands.entity(e).despawn();
+            }
 
             for source in &state.sources {
-                commands.spawn(*source);
+                commands.spawn(source.clone());
             }
             for mic in &state.mics {
                 commands.spawn(mic.clone());
@@ -158,6 +211,18 @@ pub fn undo_event(
             for circ_wall in &state.circle_walls {
                 commands.spawn(*circ_wall);
             }
+            for line_array in &state.line_arrays {
+                commands.spawn(line_array.clone());
+            }
+            for plane_wave in &state.plane_waves {
+                commands.spawn(plane_wave.clone());
+            }
+            for surface in &state.surfaces {
+                commands.spawn(surface.clone());
+            }
+            for source_group in &state.source_groups {
+                commands.spawn(source_group.clone());
+            }
 
             wall_update_ev.send(UpdateWalls);
             reset_ev.send(Reset::default());